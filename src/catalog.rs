@@ -0,0 +1,217 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use crate::Error;
+use crate::crd::v1::{ImageOverride, ImageSpec, PgOprImageCatalog, PgOprSpec};
+use crate::settings::Settings;
+use crate::workload::{self, ContainerImage};
+use kube::{Api, Client, ResourceExt, api::ListParams};
+use std::collections::BTreeMap;
+
+/// PostgreSQL major version of clusters without `spec.version`
+pub const DEFAULT_VERSION: &str = "18";
+/// PostgreSQL major versions with built-in images
+pub const SUPPORTED_VERSIONS: [&str; 6] = ["13", "14", "15", "16", "17", "18"];
+
+/// ImageSet holds the images running a cluster of one PostgreSQL major version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageSet {
+    pub primary: String,
+    pub replica: String,
+    pub pgmoneta: String,
+    pub pgexporter: String,
+}
+
+impl ImageSet {
+    /// The images built by the project for a major version
+    ///
+    /// # Arguments
+    /// - `version` - The PostgreSQL major version
+    pub fn builtin(version: &str) -> Self {
+        Self {
+            primary: format!("pgsql{}-primary-rocky10", version),
+            replica: format!("pgsql{}-replica-rocky10", version),
+            pgmoneta: workload::PGMONETA_IMAGE.to_string(),
+            pgexporter: workload::PGEXPORTER_IMAGE.to_string(),
+        }
+    }
+
+    fn apply(&mut self, images: &ImageOverride) {
+        if let Some(primary) = &images.primary {
+            self.primary = primary.clone();
+        }
+        if let Some(replica) = &images.replica {
+            self.replica = replica.clone();
+        }
+        if let Some(pgmoneta) = &images.pgmoneta {
+            self.pgmoneta = pgmoneta.clone();
+        }
+        if let Some(pgexporter) = &images.pgexporter {
+            self.pgexporter = pgexporter.clone();
+        }
+    }
+}
+
+/// ClusterImages are the images of a cluster after its image settings.
+pub struct ClusterImages {
+    pub primary: ContainerImage,
+    pub replica: ContainerImage,
+    pub pgmoneta: ContainerImage,
+    pub pgexporter: ContainerImage,
+    pub monitoring: ContainerImage,
+}
+
+impl ClusterImages {
+    /// Applies the image settings of a cluster and its components to the catalog images.
+    ///
+    /// The `image` of the cluster runs both the primary and the replicas.
+    ///
+    /// # Arguments
+    /// - `images` - The catalog images of the cluster version
+    /// - `spec` - The spec of the cluster
+    pub fn new(images: &ImageSet, spec: &PgOprSpec) -> Self {
+        let cluster = &spec.image_spec;
+        let pgexporter = spec.pgexporter.as_ref();
+
+        Self {
+            primary: container_image(&images.primary, cluster, cluster),
+            replica: container_image(&images.replica, cluster, cluster),
+            pgmoneta: container_image(
+                &images.pgmoneta,
+                component(spec.pgmoneta.as_ref().map(|s| &s.image_spec)),
+                cluster,
+            ),
+            pgexporter: container_image(
+                &images.pgexporter,
+                component(pgexporter.map(|s| &s.image_spec)),
+                cluster,
+            ),
+            monitoring: container_image(
+                workload::PGEXPORTER_MON_IMAGE,
+                component(
+                    pgexporter
+                        .and_then(|s| s.monitoring.as_ref())
+                        .map(|s| &s.image_spec),
+                ),
+                cluster,
+            ),
+        }
+    }
+}
+
+fn component(spec: Option<&ImageSpec>) -> &ImageSpec {
+    static NONE: ImageSpec = ImageSpec {
+        image: None,
+        image_registry: None,
+        image_pull_policy: None,
+        image_pull_secrets: None,
+    };
+    spec.unwrap_or(&NONE)
+}
+
+fn container_image(default: &str, component: &ImageSpec, cluster: &ImageSpec) -> ContainerImage {
+    let name = component.image.as_deref().unwrap_or(default);
+    let name = match component
+        .image_registry
+        .as_deref()
+        .or(cluster.image_registry.as_deref())
+    {
+        Some(registry) if !has_registry(name) => {
+            format!("{}/{}", registry.trim_end_matches('/'), name)
+        }
+        _ => name.to_string(),
+    };
+
+    ContainerImage {
+        name,
+        pull_policy: component
+            .image_pull_policy
+            .or(cluster.image_pull_policy)
+            .unwrap_or_default()
+            .as_str()
+            .to_string(),
+        pull_secrets: component
+            .image_pull_secrets
+            .as_ref()
+            .or(cluster.image_pull_secrets.as_ref())
+            .cloned()
+            .unwrap_or_default(),
+    }
+}
+
+/// Checks whether an image reference starts with a registry host, as in
+/// `registry.example.com/pgsql18` or `localhost:5000/pgsql18`.
+fn has_registry(image: &str) -> bool {
+    image
+        .split_once('/')
+        .is_some_and(|(host, _)| host.contains('.') || host.contains(':') || host == "localhost")
+}
+
+/// ImageCatalog maps PostgreSQL major versions to their images.
+///
+/// The built-in images are overridden by the operator configuration file, which in turn is
+/// overridden by PgOprImageCatalog resources in the order of their names. Overrides may add
+/// versions without built-in images.
+pub struct ImageCatalog {
+    images: BTreeMap<String, ImageSet>,
+}
+
+impl ImageCatalog {
+    /// The catalog of the built-in images
+    pub fn builtin() -> Self {
+        Self {
+            images: SUPPORTED_VERSIONS
+                .iter()
+                .map(|version| (version.to_string(), ImageSet::builtin(version)))
+                .collect(),
+        }
+    }
+
+    /// Builds the catalog from the built-in images and all overrides.
+    ///
+    /// # Arguments
+    /// - `client` - The Kubernetes client to list the PgOprImageCatalog resources with.
+    pub async fn load(client: Client) -> Result<Self, Error> {
+        let mut catalog = Self::builtin();
+
+        for (version, images) in &Settings::load()?.images {
+            catalog.apply(version, images);
+        }
+
+        let api: Api<PgOprImageCatalog> = Api::all(client);
+        let mut resources = match api.list(&ListParams::default()).await {
+            Ok(list) => list.items,
+            // the catalog CRD is missing until the operator is installed again
+            Err(kube::Error::Api(err)) if err.code == 404 => Vec::new(),
+            Err(err) => return Err(Error::from(err)),
+        };
+        resources.sort_by_key(|resource| resource.name_any());
+        for resource in &resources {
+            for entry in &resource.spec.images {
+                catalog.apply(&entry.version, &entry.images);
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    /// The images of a major version, if the catalog has it
+    ///
+    /// # Arguments
+    /// - `version` - The PostgreSQL major version
+    pub fn images(&self, version: &str) -> Option<&ImageSet> {
+        self.images.get(version)
+    }
+
+    fn apply(&mut self, version: &str, images: &ImageOverride) {
+        self.images
+            .entry(version.to_string())
+            .or_insert_with(|| ImageSet::builtin(version))
+            .apply(images);
+    }
+}
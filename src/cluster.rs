@@ -8,19 +8,63 @@
 
 mod cleanup;
 mod config;
+mod delay;
+mod failover;
+mod hba;
+mod replicas;
+mod replication;
+mod roles;
+mod rollout;
+mod secrets;
+mod slots;
 mod status;
+mod storage;
+mod switchover;
+mod synchronous;
 mod topology;
-
-use crate::crd::v1::{PgMonetaSpec, pgopr};
+mod tuning;
+mod upgrade;
+
+use crate::catalog::{self, ClusterImages, ImageCatalog};
+use crate::crd::v1::{
+    ConfigStatus, MemberConfigStatus, PgMonetaSpec, VolumeMode, WorkloadKind, pgopr,
+};
+use crate::credentials::Credentials;
 use crate::manager::{self, ResourceManager};
-use crate::workload::{DeploymentConfig, PG18_PRIMARY_IMAGE, PG18_REPLICA_IMAGE};
-use crate::{Error, persistent, pgexporter, pgmoneta, primary, replica, services};
+use crate::workload::{self, ContainerImage, DeploymentConfig};
+use crate::{Error, pgexporter, pgmoneta, primary, replica, services};
 use config::ConfigResult;
-use k8s_openapi::api::apps::v1::Deployment;
-use k8s_openapi::api::core::v1::{PersistentVolume, PersistentVolumeClaim, Secret};
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::{PersistentVolume, PersistentVolumeClaim};
 use kube::{Api, Client};
+use log::debug;
+use rollout::Rollout;
+use std::collections::BTreeSet;
 use std::sync::Arc;
-use topology::{ClusterMember, ClusterTopology};
+use std::time::Duration;
+use topology::{ClusterMember, ClusterTopology, DataClaim, MemberClaims};
+use upgrade::Progress;
+
+/// Delay until the next reconcile of a cluster at rest
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Resolves a delayed replica of the cluster by its member name or its `replicaSpecs` name.
+///
+/// Replicas are delayed through `spec.delayedReplicas.members` or `delayed` on their replica
+/// spec. Returns the member name of the delayed replica.
+///
+/// # Arguments
+/// - `pgopr` - The PgOpr resource defining the cluster.
+/// - `name` - Name of the replica.
+pub fn delayed_replica(pgopr: &pgopr, name: &str) -> Option<String> {
+    let topology = ClusterTopology::from_pgopr(pgopr);
+    [
+        name.to_string(),
+        topology::replica_name(topology.name(), name),
+    ]
+    .into_iter()
+    .find(|member| topology.is_delayed(member))
+}
 
 /// Cluster represents the desired state of a PostgreSQL Star Configuration
 pub struct Cluster {
@@ -40,31 +84,215 @@ impl Cluster {
 
     /// Reconciles the desired state of the cluster and updates the PgOpr status.
     ///
+    /// Returns the delay until the next reconcile, which is short while a switchover is in
+    /// progress.
+    ///
     /// # Arguments
     /// - `pgopr` - The PgOpr resource defining the cluster state.
-    pub async fn reconcile_state(&self, pgopr: Arc<pgopr>) -> Result<(), Error> {
-        let topology = ClusterTopology::from_pgopr(&pgopr);
+    pub async fn reconcile_state(&self, pgopr: Arc<pgopr>) -> Result<Duration, Error> {
+        let mut topology = ClusterTopology::from_pgopr(&pgopr);
+
+        let catalog = ImageCatalog::load(self.manager.get_client()).await?;
+        let requested = pgopr
+            .spec
+            .version
+            .as_deref()
+            .unwrap_or(catalog::DEFAULT_VERSION);
+        if catalog.images(requested).is_none() {
+            let err = Error::UnsupportedPostgresVersion(requested.to_string());
+            let status = status::invalid_spec(&pgopr, err.to_string());
+            self.patch_status(&topology, status).await?;
+            return Ok(RECONCILE_INTERVAL);
+        }
 
-        let version = pgopr.spec.version.as_deref().unwrap_or("18");
-        if version != "18" {
-            let err = Error::UnsupportedPostgresVersion(version.to_string());
+        let version = match upgrade::plan(&pgopr, requested) {
+            Ok(None) => requested.to_string(),
+            Ok(Some(upgrade)) => {
+                match upgrade::run(
+                    &self.manager,
+                    &pgopr,
+                    &topology,
+                    &upgrade,
+                    requested,
+                    &catalog,
+                )
+                .await?
+                {
+                    Progress::Running(message) => {
+                        let status = status::upgrading(&pgopr, upgrade, message);
+                        self.patch_status(&topology, status).await?;
+                        return Ok(RECONCILE_INTERVAL);
+                    }
+                    Progress::Failed(message) => {
+                        let status = status::upgrade_failed(&pgopr, upgrade, message);
+                        self.patch_status(&topology, status).await?;
+                        return Ok(RECONCILE_INTERVAL);
+                    }
+                    Progress::Complete => upgrade.to_version,
+                    Progress::Aborted => upgrade.from_version,
+                }
+            }
+            Err(message) => {
+                let status = status::invalid_spec(&pgopr, message);
+                self.patch_status(&topology, status).await?;
+                return Ok(RECONCILE_INTERVAL);
+            }
+        };
+        let Some(images) = catalog.images(&version) else {
+            let err = Error::UnsupportedPostgresVersion(version);
             let status = status::invalid_spec(&pgopr, err.to_string());
             self.patch_status(&topology, status).await?;
-            return Ok(());
+            return Ok(RECONCILE_INTERVAL);
+        };
+        let images = ClusterImages::new(images, &pgopr.spec);
+
+        if pgopr.spec.volume_mode == Some(VolumeMode::Block) {
+            let err = Error::UserInputError(
+                "Block volumes cannot hold a data directory, use Filesystem".to_string(),
+            );
+            let status = status::invalid_spec(&pgopr, err.to_string());
+            self.patch_status(&topology, status).await?;
+            return Ok(RECONCILE_INTERVAL);
+        }
+
+        if let Some(message) = storage::validate(&self.manager, &pgopr, &topology).await? {
+            let status = status::invalid_spec(&pgopr, message);
+            self.patch_status(&topology, status).await?;
+            return Ok(RECONCILE_INTERVAL);
+        }
+
+        if let Some(message) = secrets::validate(&self.manager, &topology).await? {
+            let status = status::invalid_secret(&pgopr, message);
+            self.patch_status(&topology, status).await?;
+            return Ok(RECONCILE_INTERVAL);
+        }
+
+        if let Some(message) = workload::validate_resources(&pgopr.spec)
+            .or_else(|| slots::validate(&pgopr.spec))
+            .or_else(|| synchronous::validate(&pgopr.spec))
+            .or_else(|| replication::validate(&topology))
+        {
+            let status = status::invalid_spec(&pgopr, message);
+            self.patch_status(&topology, status).await?;
+            return Ok(RECONCILE_INTERVAL);
+        }
+
+        if let Some(config) = &pgopr.spec.config
+            && let Some(message) = config::validate(config, &version)
+        {
+            let status = status::invalid_spec(&pgopr, message);
+            self.patch_status(&topology, status).await?;
+            return Ok(RECONCILE_INTERVAL);
         }
 
-        let config_info = if let Some(config) = &pgopr.spec.config {
-            Some(config::sync_config(&self.manager, &pgopr, config).await?)
+        if let Some(rules) = &pgopr.spec.hba
+            && let Some(message) = hba::validate(rules)
+        {
+            let status = status::invalid_spec(&pgopr, message);
+            self.patch_status(&topology, status).await?;
+            return Ok(RECONCILE_INTERVAL);
+        }
+
+        if let Some(message) = replicas::validate(topology.name(), &pgopr.spec, &version) {
+            let status = status::invalid_spec(&pgopr, message);
+            self.patch_status(&topology, status).await?;
+            return Ok(RECONCILE_INTERVAL);
+        }
+
+        if let Some(delayed) = &pgopr.spec.delayed_replicas
+            && let Some(message) = delay::validate(&topology, delayed, &version)
+        {
+            let status = status::invalid_spec(&pgopr, message);
+            self.patch_status(&topology, status).await?;
+            return Ok(RECONCILE_INTERVAL);
+        }
+
+        hba::sync(&self.manager, &pgopr, &topology, &version).await?;
+        let config_info = if let Some(config) = tuning::effective_config(&pgopr.spec) {
+            Some(config::sync_config(&self.manager, &pgopr, &config, &version).await?)
         } else {
             None
         };
 
-        self.sync_topology(&pgopr, &topology, config_info).await?;
+        let primary = topology.primary();
+        let mut failover = failover::run(&self.manager, &pgopr, &mut topology).await?;
+        switchover::run(&self.manager, &pgopr, &mut topology, &mut failover).await?;
+        let rollout = rollout::plan(
+            &self.manager,
+            &pgopr,
+            &topology,
+            &mut failover,
+            &images,
+            config_info.as_ref(),
+        )
+        .await?;
+        let requeue = if failover.switchover.is_some() {
+            switchover::POLL_INTERVAL
+        } else {
+            RECONCILE_INTERVAL
+        };
+        if topology.primary().name() != primary.name() {
+            let status = status::promoted(&pgopr, &topology, failover.clone());
+            self.patch_status(&topology, status).await?;
+        }
+
+        self.sync_topology(&pgopr, &topology, &images, &rollout)
+            .await?;
+        let in_use: BTreeSet<String> = topology
+            .members()
+            .iter()
+            .filter_map(|member| rollout.revision(member).config_map.clone())
+            .collect();
+        config::cleanup(
+            &self.manager,
+            &pgopr,
+            &topology,
+            config_info.as_ref().map(|config| config.name.as_str()),
+            &in_use,
+        )
+        .await?;
+        let config_status = self
+            .reload_config(
+                &pgopr,
+                &topology,
+                &version,
+                &rollout,
+                config_info.as_ref(),
+                failover.switchover.is_some(),
+            )
+            .await?;
 
-        let status = status::observe(&self.manager, &topology, &pgopr).await?;
+        let slots = slots::sync(&self.manager, &pgopr, &topology).await?;
+        replication::follow_upstreams(&self.manager, &topology).await?;
+        let delayed = delay::sync(&self.manager, &pgopr, &topology).await?;
+        let standbys = synchronous::sync(&self.manager, &pgopr.spec, &topology).await?;
+
+        let mut status = status::observe(
+            &self.manager,
+            &topology,
+            &pgopr,
+            &version,
+            rollout.status(),
+            failover,
+            config_status,
+        )
+        .await?;
+        if let Some(primary_slots) = slots.primary {
+            status::report_slots(
+                &pgopr,
+                primary_slots,
+                slots::wal_warning(&pgopr.spec),
+                &mut status,
+            );
+        }
+        status.managed_slots = slots.managed;
+        if let Some(standbys) = standbys {
+            status::report_replication(&pgopr, standbys, &mut status);
+        }
+        status.delayed_replicas = delayed;
         self.patch_status(&topology, status).await?;
 
-        Ok(())
+        Ok(requeue)
     }
 
     async fn patch_status(
@@ -91,19 +319,12 @@ impl Cluster {
         Ok(())
     }
 
-    /// Removes replica resources that are no longer needed based on the desired replica count.
+    /// Removes the resources of replicas that are no longer members of the cluster.
     ///
     /// # Arguments
-    /// - `name` - The base name of the cluster.
-    /// - `namespace` - The namespace where resources reside.
-    /// - `desired_replicas` - The number of replicas requested in the spec.
-    pub async fn cleanup_stale_replicas(
-        &self,
-        name: &str,
-        namespace: &str,
-        desired_replicas: u32,
-    ) -> Result<(), Error> {
-        cleanup::stale_replicas(&self.manager, name, namespace, desired_replicas).await
+    /// - `topology` - The expected cluster topology, its primary is never removed.
+    async fn cleanup_stale_replicas(&self, topology: &ClusterTopology) -> Result<(), Error> {
+        cleanup::stale_replicas(&self.manager, topology).await
     }
 
     /// Deletes all Kubernetes resources belonging to the cluster.
@@ -119,41 +340,62 @@ impl Cluster {
         &self,
         pgopr: &Arc<pgopr>,
         topology: &ClusterTopology,
-        config_info: Option<ConfigResult>,
+        images: &ClusterImages,
+        rollout: &Rollout,
     ) -> Result<(), Error> {
-        let primary = topology.primary();
-
-        let primary_config = DeploymentConfig {
-            image: PG18_PRIMARY_IMAGE,
-            resources: pgopr.spec.resources.as_ref(),
-            config_map_name: config_info.as_ref().map(|c| c.name.as_str()),
-            config_hash: config_info.as_ref().map(|c| c.hash.as_str()),
-        };
-        self.sync_primary(pgopr, topology, &primary, primary_config)
-            .await?;
-
-        for member in topology.replica_members() {
-            let replica_config = DeploymentConfig {
-                image: PG18_REPLICA_IMAGE,
-                resources: pgopr.spec.resources.as_ref(),
-                config_map_name: config_info.as_ref().map(|c| c.name.as_str()),
-                config_hash: config_info.as_ref().map(|c| c.hash.as_str()),
+        let credentials = topology.credentials();
+        let hba_name = topology.hba_name();
+        secrets::sync(&self.manager, pgopr, topology).await?;
+        secrets::rotate(&self.manager, pgopr, topology).await?;
+
+        for member in topology.members() {
+            let claims = storage::sync_member(&self.manager, pgopr, topology, &member).await?;
+            let revision = rollout.revision(&member);
+            let image = ContainerImage {
+                name: revision.image.clone(),
+                ..images.primary.clone()
             };
-            self.sync_replica(pgopr, topology, &member, replica_config)
+            let replica_spec = topology.replica_spec(member.name());
+            let config = DeploymentConfig {
+                cluster_name: topology.name(),
+                image: &image,
+                resources: replica_spec
+                    .and_then(|spec| spec.resources.as_ref())
+                    .or(pgopr.spec.resources.as_ref()),
+                config_map_name: revision.config_map.as_deref(),
+                hba_config_map: Some(&hba_name),
+                config_hash: revision.config_hash.as_deref(),
+                credentials,
+                fenced: topology.is_fenced(member.name()),
+                claim_name: claims.data.name(),
+                wal_claim_name: claims.wal.as_ref().map(DataClaim::name),
+                placement: replica_spec.map(|spec| &spec.placement),
+            };
+            self.sync_member(pgopr, topology, &member, &claims, config)
                 .await?;
         }
 
-        self.cleanup_stale_replicas(topology.name(), topology.namespace(), topology.replicas())
-            .await?;
+        roles::sync(&self.manager, topology).await?;
+        self.sync_role_services(pgopr, topology).await?;
+
+        self.cleanup_stale_replicas(topology).await?;
 
         if let Some(pgmoneta_spec) = &pgopr.spec.pgmoneta {
-            self.sync_pgmoneta(pgopr, topology, pgmoneta_spec).await?;
+            self.sync_pgmoneta(
+                pgopr,
+                topology,
+                pgmoneta_spec,
+                &images.pgmoneta,
+                credentials,
+            )
+            .await?;
         } else {
             self.cleanup_pgmoneta(topology).await?;
         }
 
         if pgopr.spec.pgexporter.is_some() {
-            self.sync_pgexporter(pgopr, topology).await?
+            self.sync_pgexporter(pgopr, topology, images, credentials)
+                .await?
         } else {
             self.cleanup_pgexporter(topology).await?
         }
@@ -161,44 +403,154 @@ impl Cluster {
         Ok(())
     }
 
-    async fn sync_primary(
+    /// Applies the reloadable parameters to the running members.
+    ///
+    /// Returns the configuration each member runs. A reloaded member runs the configuration
+    /// of the spec, unless it waits for a restart and still runs the ConfigMap it was started
+    /// with. The primary is left alone while a switchover is in progress, it shuts down and
+    /// restarts as a standby.
+    async fn reload_config(
         &self,
         pgopr: &Arc<pgopr>,
         topology: &ClusterTopology,
-        member: &ClusterMember,
-        config: DeploymentConfig<'_>,
-    ) -> Result<(), Error> {
-        self.sync_storage(pgopr, topology, member).await?;
+        version: &str,
+        rollout: &Rollout,
+        config_info: Option<&ConfigResult>,
+        switching_over: bool,
+    ) -> Result<ConfigStatus, Error> {
+        let config = tuning::effective_config(&pgopr.spec).unwrap_or_default();
+        let mut members = Vec::new();
+
+        for member in topology.members() {
+            if topology.is_fenced(member.name()) {
+                continue;
+            }
+
+            // the reloadable parameters of a replica spec apply to that member only
+            let mut member_config = config.clone();
+            if let Some(overrides) = topology
+                .replica_spec(member.name())
+                .and_then(|spec| spec.config.as_ref())
+            {
+                member_config.retain(|name, _| {
+                    !overrides
+                        .keys()
+                        .any(|override_name| override_name.eq_ignore_ascii_case(name))
+                });
+                member_config.extend(overrides.clone());
+            }
+
+            let reload = if switching_over && member.name() == topology.primary().name() {
+                Err(Error::SqlError("a switchover is in progress".to_string()))
+            } else {
+                config::reload(
+                    &self.manager,
+                    topology.namespace(),
+                    member.name(),
+                    &member_config,
+                    version,
+                )
+                .await
+            };
+            let (running, pending_parameters) = match reload {
+                Ok(parameters) => (true, parameters),
+                // a member that is not running picks up the configuration when it starts
+                Err(Error::SqlError(err)) => {
+                    debug!("Unable to reload {}: {}", member.name(), err);
+                    (false, Vec::new())
+                }
+                Err(err) => return Err(err),
+            };
 
-        let deployment = primary::build(member.name(), topology.namespace(), config);
-        self.manager.sync(pgopr, deployment).await?;
+            let pending_restart = !pending_parameters.is_empty()
+                || rollout
+                    .restarts()
+                    .iter()
+                    .any(|restart| restart == member.name());
+            let hash = if !running {
+                None
+            } else if pending_restart {
+                rollout
+                    .revision(&member)
+                    .config_map
+                    .as_deref()
+                    .and_then(|config_map| config::config_map_hash(topology.name(), config_map))
+                    .map(str::to_string)
+            } else {
+                config_info.map(|config| config.hash.clone())
+            };
 
-        let service = services::build(member.name(), topology.namespace(), 5432);
-        self.manager.sync(pgopr, service).await?;
+            members.push(MemberConfigStatus {
+                name: member.name().to_string(),
+                hash,
+                pending_restart,
+                pending_parameters,
+            });
+        }
 
-        Ok(())
+        Ok(ConfigStatus {
+            config_map: config_info.map(|config| config.name.clone()),
+            hash: config_info.map(|config| config.hash.clone()),
+            tuned: tuning::tuned(&pgopr.spec),
+            members,
+        })
     }
 
-    async fn sync_replica(
+    /// Syncs the workload and Service of a member.
+    ///
+    /// A member switching workload kind keeps its name and claims, the new workload is only
+    /// created once all pods of the old one are gone.
+    async fn sync_member(
         &self,
         pgopr: &Arc<pgopr>,
         topology: &ClusterTopology,
         member: &ClusterMember,
+        claims: &MemberClaims,
         config: DeploymentConfig<'_>,
     ) -> Result<(), Error> {
-        self.sync_storage(pgopr, topology, member).await?;
-
-        let slot_name = member.slot_name().ok_or_else(|| {
-            Error::UserInputError(format!("Replica {} is missing a slot name", member.name()))
-        })?;
-        let deployment = replica::build(
-            member.name(),
-            topology.name(),
-            topology.namespace(),
-            slot_name,
-            config,
-        );
-        self.manager.sync(pgopr, deployment).await?;
+        // the workload keeps the flavor it was created with, the role follows failovers
+        let deployment = if member.is_bootstrap() {
+            primary::build(member.name(), topology.namespace(), config)
+        } else {
+            let upstream = replication::template_upstream(&self.manager, topology, member).await?;
+            replica::build(
+                member.name(),
+                upstream.as_deref(),
+                topology.namespace(),
+                member.slot_name(),
+                config,
+            )
+        };
+
+        match topology.workload() {
+            WorkloadKind::Deployment => {
+                if cleanup::retire_workload::<StatefulSet>(
+                    &self.manager,
+                    topology.namespace(),
+                    member.name(),
+                    "StatefulSet",
+                )
+                .await?
+                {
+                    self.manager.sync(pgopr, deployment).await?;
+                }
+            }
+            WorkloadKind::StatefulSet => {
+                if cleanup::retire_workload::<Deployment>(
+                    &self.manager,
+                    topology.namespace(),
+                    member.name(),
+                    "ReplicaSet",
+                )
+                .await?
+                {
+                    let templates =
+                        storage::claim_templates(&self.manager, topology, member, claims).await?;
+                    let stateful_set = workload::stateful_set(deployment, templates);
+                    self.manager.sync(pgopr, stateful_set).await?;
+                }
+            }
+        }
 
         let service = services::build(member.name(), topology.namespace(), 5432);
         self.manager.sync(pgopr, service).await?;
@@ -206,28 +558,20 @@ impl Cluster {
         Ok(())
     }
 
-    async fn sync_storage(
+    async fn sync_role_services(
         &self,
         pgopr: &Arc<pgopr>,
         topology: &ClusterTopology,
-        member: &ClusterMember,
     ) -> Result<(), Error> {
-        let pv = persistent::build_pv(
-            &member.pv_name(),
-            topology.storage(),
-            member.name(),
-            member.host_path(),
-            topology.name(),
-        );
-        self.manager.sync_cluster(pv).await?;
-
-        let pvc = persistent::build_pvc(
-            &member.pvc_name(),
-            topology.namespace(),
-            topology.storage(),
-            member.name(),
-        );
-        self.manager.sync(pgopr, pvc).await?;
+        for (name, role) in [
+            (topology.read_write_name(), Some(manager::ROLE_PRIMARY)),
+            (topology.read_only_name(), Some(manager::ROLE_REPLICA)),
+            (topology.read_name(), None),
+        ] {
+            let service =
+                services::build_role(&name, topology.namespace(), topology.name(), role, 5432);
+            self.manager.sync(pgopr, service).await?;
+        }
 
         Ok(())
     }
@@ -237,39 +581,21 @@ impl Cluster {
         pgopr: &Arc<pgopr>,
         topology: &ClusterTopology,
         spec: &PgMonetaSpec,
+        image: &ContainerImage,
+        credentials: &Credentials,
     ) -> Result<(), Error> {
-        let storage = spec.storage.unwrap_or(10);
-        let host_path = format!("/tmp/kind-pgmoneta-{}", topology.name());
-
-        let pv = persistent::build_pgmoneta_pv(
-            &topology.pgmoneta_pv_name(),
-            storage,
-            &host_path,
-            topology.name(),
-        );
-        self.manager.sync_cluster(pv).await?;
-
-        let pvc = persistent::build_pvc(
-            &topology.pgmoneta_pvc_name(),
-            topology.namespace(),
-            storage,
-            &topology.pgmoneta_name(),
-        );
-        self.manager.sync(pgopr, pvc).await?;
-
-        let secret = pgmoneta::build_secret(
-            &topology.pgmoneta_secret_name(),
-            topology.namespace(),
-            "backup_pass",
-        );
-        self.manager.sync(pgopr, secret).await?;
+        storage::sync_pgmoneta(&self.manager, pgopr, topology, spec).await?;
 
         let deployment = pgmoneta::build_deployment(
             &topology.pgmoneta_name(),
             topology.namespace(),
-            topology.name(),
+            image,
+            topology.primary().name(),
             &topology.pgmoneta_pvc_name(),
-            &topology.pgmoneta_secret_name(),
+            &credentials.backup,
+            secrets::rotated_at(&self.manager, topology.namespace(), &credentials.backup)
+                .await?
+                .as_deref(),
         );
         self.manager.sync(pgopr, deployment).await?;
 
@@ -283,9 +609,6 @@ impl Cluster {
         self.manager
             .delete::<PersistentVolumeClaim>(&topology.pgmoneta_pvc_name(), topology.namespace())
             .await?;
-        self.manager
-            .delete::<Secret>(&topology.pgmoneta_secret_name(), topology.namespace())
-            .await?;
         self.manager
             .delete_cluster::<PersistentVolume>(&topology.pgmoneta_pv_name())
             .await?;
@@ -296,19 +619,19 @@ impl Cluster {
         &self,
         pgopr: &Arc<pgopr>,
         topology: &ClusterTopology,
+        images: &ClusterImages,
+        credentials: &Credentials,
     ) -> Result<(), Error> {
-        let secret = pgexporter::build_secret(
-            &topology.pgexporter_secret_name(),
-            topology.namespace(),
-            "pgexporter_pass",
-        );
-        self.manager.sync(pgopr, secret).await?;
-
+        secrets::sync_exporter_role(&self.manager, topology).await?;
         let deployment = pgexporter::build_deployment(
             &topology.pgexporter_name(),
             topology.namespace(),
-            topology.name(),
-            &topology.pgexporter_secret_name(),
+            &images.pgexporter,
+            topology.primary().name(),
+            &credentials.exporter,
+            secrets::rotated_at(&self.manager, topology.namespace(), &credentials.exporter)
+                .await?
+                .as_deref(),
             pgopr
                 .spec
                 .pgexporter
@@ -326,7 +649,8 @@ impl Cluster {
             .and_then(|s| s.monitoring.as_ref())
             .is_some()
         {
-            self.sync_pgexporter_monitoring(pgopr, topology).await?
+            self.sync_pgexporter_monitoring(pgopr, topology, &images.monitoring)
+                .await?
         } else {
             self.cleanup_pgexporter_monitoring(topology).await?
         }
@@ -338,9 +662,6 @@ impl Cluster {
         self.manager
             .delete::<Deployment>(&topology.pgexporter_name(), topology.namespace())
             .await?;
-        self.manager
-            .delete::<Secret>(&topology.pgexporter_secret_name(), topology.namespace())
-            .await?;
 
         Ok(())
     }
@@ -348,10 +669,12 @@ impl Cluster {
         &self,
         pgopr: &Arc<pgopr>,
         topology: &ClusterTopology,
+        image: &ContainerImage,
     ) -> Result<(), Error> {
         let deployment = pgexporter::build_monitoring_deployment(
             &topology.pgexporter_mon_name(),
             topology.namespace(),
+            image,
             &topology.pgexporter_name(),
             pgopr
                 .spec
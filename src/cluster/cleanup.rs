@@ -8,35 +8,117 @@
 
 use super::topology::{self, ClusterTopology};
 use crate::Error;
+use crate::credentials::Credentials;
 use crate::manager::{self, ResourceManager};
-use k8s_openapi::api::apps::v1::Deployment;
-use k8s_openapi::api::core::v1::{PersistentVolume, PersistentVolumeClaim, Secret, Service};
-use kube::{Api, ResourceExt, api::ListParams};
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::{PersistentVolume, PersistentVolumeClaim, Pod, Secret, Service};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::core::NamespaceResourceScope;
+use kube::{Api, Resource, ResourceExt, api::ListParams};
+use log::info;
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::BTreeSet;
+use std::fmt::Debug;
 
-/// Removes replica resources that are no longer needed based on the desired replica count.
+/// Removes the resources of replicas that are no longer members of the cluster.
 ///
 /// # Arguments
 /// - `manager` - The Kubernetes resource manager.
-/// - `name` - The base name of the cluster.
-/// - `namespace` - The namespace where resources reside.
-/// - `desired_replicas` - The number of replicas requested in the spec.
+/// - `topology` - The expected cluster topology, its primary is never removed.
 pub(super) async fn stale_replicas(
     manager: &ResourceManager,
-    name: &str,
-    namespace: &str,
-    desired_replicas: u32,
+    topology: &ClusterTopology,
 ) -> Result<(), Error> {
-    let deploy_api: Api<Deployment> = Api::namespaced(manager.get_client(), namespace);
-    for deployment in deploy_api.list(&ListParams::default()).await? {
-        let resource_name = deployment.name_any();
-        if topology::replica_ordinal(name, &resource_name).is_some_and(|i| i > desired_replicas) {
-            delete_replica_stack(manager, &resource_name, namespace).await?;
+    let members = topology.member_names();
+    for resource_name in member_workloads(manager, topology).await? {
+        if topology::replica_id(topology.name(), &resource_name).is_some()
+            && !members.contains(&resource_name)
+        {
+            delete_replica_stack(manager, &resource_name, topology.namespace()).await?;
         }
     }
 
     Ok(())
 }
 
+/// Removes the workload a member ran on before it switched workload kind.
+///
+/// Returns true once no pod of the old workload is left, so two servers never use the same
+/// data directory.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `namespace` - The namespace where resources reside.
+/// - `name` - Name of the member.
+/// - `pod_owner_kind` - Kind of the resource owning the pods of the old workload.
+pub(super) async fn retire_workload<K>(
+    manager: &ResourceManager,
+    namespace: &str,
+    name: &str,
+    pod_owner_kind: &str,
+) -> Result<bool, Error>
+where
+    K: Resource<Scope = NamespaceResourceScope> + Clone + Debug + Serialize + DeserializeOwned,
+    K::DynamicType: Default,
+{
+    let api: Api<K> = Api::namespaced(manager.get_client(), namespace);
+    if api.get_opt(name).await?.is_some() {
+        info!(
+            "Retiring {} resource: {}/{}",
+            K::kind(&Default::default()),
+            namespace,
+            name
+        );
+        manager.delete::<K>(name, namespace).await?;
+        return Ok(false);
+    }
+
+    let pod_api: Api<Pod> = Api::namespaced(manager.get_client(), namespace);
+    let selector = format!("app={}", name);
+    let pods = pod_api
+        .list(&ListParams::default().labels(&selector))
+        .await?;
+    Ok(!pods.iter().any(|pod| {
+        pod.owner_references()
+            .iter()
+            .any(|owner| owner.kind == pod_owner_kind)
+    }))
+}
+
+/// Names of the Deployments and StatefulSets owned by the cluster
+async fn member_workloads(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+) -> Result<BTreeSet<String>, Error> {
+    let deploy_api: Api<Deployment> = Api::namespaced(manager.get_client(), topology.namespace());
+    let sts_api: Api<StatefulSet> = Api::namespaced(manager.get_client(), topology.namespace());
+    // replica names may contain dashes, so another cluster could share the name prefix
+    let owned = |owners: &[OwnerReference]| {
+        owners
+            .iter()
+            .any(|owner| owner.kind == manager::KIND_PGOPR && owner.name == topology.name())
+    };
+
+    let mut names: BTreeSet<String> = deploy_api
+        .list(&ListParams::default())
+        .await?
+        .into_iter()
+        .filter(|deployment| owned(deployment.owner_references()))
+        .map(|deployment| deployment.name_any())
+        .collect();
+    names.extend(
+        sts_api
+            .list(&ListParams::default())
+            .await?
+            .into_iter()
+            .filter(|stateful_set| owned(stateful_set.owner_references()))
+            .map(|stateful_set| stateful_set.name_any()),
+    );
+
+    Ok(names)
+}
+
 /// Deletes all Kubernetes resources belonging to the cluster.
 ///
 /// # Arguments
@@ -46,24 +128,44 @@ pub(super) async fn all(
     manager: &ResourceManager,
     topology: &ClusterTopology,
 ) -> Result<(), Error> {
-    let deploy_api: Api<Deployment> = Api::namespaced(manager.get_client(), topology.namespace());
-    for deployment in deploy_api.list(&ListParams::default()).await? {
-        let resource_name = deployment.name_any();
-        if topology::replica_ordinal(topology.name(), &resource_name).is_some() {
+    for resource_name in member_workloads(manager, topology).await? {
+        if topology::replica_id(topology.name(), &resource_name).is_some() {
             delete_replica_stack(manager, &resource_name, topology.namespace()).await?;
         }
     }
 
-    let primary = topology.primary();
+    let bootstrap = topology.bootstrap();
     manager
-        .delete::<Service>(primary.name(), topology.namespace())
+        .delete::<Service>(bootstrap.name(), topology.namespace())
         .await?;
     manager
-        .delete::<Deployment>(primary.name(), topology.namespace())
+        .delete::<Deployment>(bootstrap.name(), topology.namespace())
         .await?;
     manager
-        .delete::<PersistentVolumeClaim>(&primary.pvc_name(), topology.namespace())
+        .delete::<StatefulSet>(bootstrap.name(), topology.namespace())
         .await?;
+    for volume in bootstrap.volumes() {
+        manager
+            .delete::<PersistentVolumeClaim>(&volume.pvc_name(), topology.namespace())
+            .await?;
+        manager
+            .delete::<PersistentVolumeClaim>(&volume.template_pvc_name(), topology.namespace())
+            .await?;
+    }
+
+    manager
+        .delete::<Job>(&topology.upgrade_name(), topology.namespace())
+        .await?;
+
+    for name in [
+        topology.read_write_name(),
+        topology.read_only_name(),
+        topology.read_name(),
+    ] {
+        manager
+            .delete::<Service>(&name, topology.namespace())
+            .await?;
+    }
 
     // pgmoneta resources
     manager
@@ -72,19 +174,20 @@ pub(super) async fn all(
     manager
         .delete::<PersistentVolumeClaim>(&topology.pgmoneta_pvc_name(), topology.namespace())
         .await?;
-    manager
-        .delete::<Secret>(&topology.pgmoneta_secret_name(), topology.namespace())
-        .await?;
     manager
         .delete::<Deployment>(&topology.pgexporter_name(), topology.namespace())
         .await?;
-    manager
-        .delete::<Secret>(&topology.pgexporter_secret_name(), topology.namespace())
-        .await?;
     manager
         .delete::<Deployment>(&topology.pgexporter_mon_name(), topology.namespace())
         .await?;
 
+    // generated credential secrets, referenced secrets belong to the user
+    for credential in Credentials::generated(topology.name()).all() {
+        manager
+            .delete::<Secret>(&credential.secret_name, topology.namespace())
+            .await?;
+    }
+
     // Cluster-scoped PV discovery and cleanup
     manager
         .delete_cluster_by_label::<PersistentVolume>(manager::LABEL_CLUSTER, topology.name())
@@ -103,11 +206,33 @@ async fn delete_replica_stack(
         .delete::<Deployment>(replica_name, namespace)
         .await?;
     manager
-        .delete::<PersistentVolumeClaim>(&topology::pvc_name(replica_name), namespace)
-        .await?;
-    manager
-        .delete_cluster::<PersistentVolume>(&topology::pv_name(replica_name))
+        .delete::<StatefulSet>(replica_name, namespace)
         .await?;
+    member_volumes(manager, replica_name, namespace).await
+}
+
+/// Deletes the claims and volumes of a member, its workload is recreated with empty ones.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `member_name` - Name of the member.
+/// - `namespace` - The namespace where resources reside.
+pub(super) async fn member_volumes(
+    manager: &ResourceManager,
+    member_name: &str,
+    namespace: &str,
+) -> Result<(), Error> {
+    for volume in topology::volumes(member_name) {
+        manager
+            .delete::<PersistentVolumeClaim>(&volume.pvc_name(), namespace)
+            .await?;
+        manager
+            .delete::<PersistentVolumeClaim>(&volume.template_pvc_name(), namespace)
+            .await?;
+        manager
+            .delete_cluster::<PersistentVolume>(&volume.pv_name())
+            .await?;
+    }
 
     Ok(())
 }
@@ -6,35 +6,141 @@
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use k8s_openapi::api::core::v1::ConfigMap;
-use kube::ResourceExt;
-use kube::api::ObjectMeta;
+use kube::api::{ListParams, ObjectMeta};
+use kube::{Api, ResourceExt};
+use log::info;
+use serde::Deserialize;
 
+use super::topology::ClusterTopology;
 use crate::Error;
 use crate::crd::v1::pgopr;
-use crate::manager::{self as k8s_manager, ResourceManager};
+use crate::manager::{
+    self as k8s_manager, LABEL_CLUSTER, LABEL_COMPONENT, LABEL_REVISION, ResourceManager,
+};
+use crate::{parameters, sql, workload};
 
 const CONFIG_FILE_NAME: &str = "postgresql.conf";
+const COMPONENT_CONFIG: &str = "config";
+
+/// Previous configuration ConfigMaps kept without `spec.configHistoryLimit`
+const DEFAULT_HISTORY_LIMIT: u32 = 3;
+
+/// Settings the operator writes with `ALTER SYSTEM` itself when re-pointing replicas,
+/// choosing the synchronous standbys and delaying replicas
+const REPLICATION_SETTINGS: [&str; 4] = [
+    "primary_conninfo",
+    "primary_slot_name",
+    "synchronous_standby_names",
+    "recovery_min_apply_delay",
+];
+
+/// Parameters the operator sets itself, the members cannot run without them
+const MANAGED_PARAMETERS: [&str; 13] = [
+    "port",
+    "listen_addresses",
+    "unix_socket_directories",
+    "data_directory",
+    "config_file",
+    "hba_file",
+    "ident_file",
+    "hot_standby",
+    "allow_alter_system",
+    "primary_conninfo",
+    "primary_slot_name",
+    "synchronous_standby_names",
+    "recovery_min_apply_delay",
+];
+
+/// Reloadable parameters holding a list of names, `ALTER SYSTEM` takes each as a value
+const NAME_LISTS: [&str; 4] = [
+    "search_path",
+    "temp_tablespaces",
+    "local_preload_libraries",
+    "session_preload_libraries",
+];
 
 pub struct ConfigResult {
     pub name: String,
+    /// Hash of all parameters
     pub hash: String,
+    /// Hash of the parameters that only take effect after a restart
+    pub restart_hash: String,
+}
+
+/// Settings of a running member
+#[derive(Deserialize)]
+struct MemberSettings {
+    /// Parameters of the configuration file the member was started with
+    file: BTreeMap<String, String>,
+    /// Parameters in `postgresql.auto.conf`
+    auto: BTreeMap<String, String>,
+    /// Built-in values of all parameters
+    boot: BTreeMap<String, String>,
+    /// Parameters whose change waits for a restart
+    pending: Vec<String>,
+    /// pg_hba.conf changed since the configuration was loaded
+    hba_changed: bool,
+}
+
+/// Checks the postgresql.conf parameters against the major version the cluster runs.
+///
+/// Returns the problems found, naming each invalid parameter.
+///
+/// # Arguments
+/// - `config` - The postgresql.conf parameters.
+/// - `version` - The PostgreSQL major version the cluster runs.
+pub(super) fn validate(config: &BTreeMap<String, String>, version: &str) -> Option<String> {
+    let mut problems = Vec::new();
+    for (name, value) in config {
+        let result = if MANAGED_PARAMETERS
+            .iter()
+            .any(|managed| managed.eq_ignore_ascii_case(name))
+        {
+            Err("managed by the operator".to_string())
+        } else if name.eq_ignore_ascii_case("wal_level") && value.eq_ignore_ascii_case("minimal") {
+            Err("the replicas need at least 'replica'".to_string())
+        } else {
+            parameters::validate(name, value, version)
+        };
+
+        if let Err(problem) = result {
+            problems.push(format!("{} ({})", name, problem));
+        }
+    }
+
+    (!problems.is_empty())
+        .then(|| format!("Invalid PostgreSQL parameters: {}", problems.join(", ")))
 }
 
 /// Ensures an immutable ConfigMap exists for the given configuration.
+///
+/// The ConfigMap is labelled with the next revision of the cluster configuration, a
+/// configuration used before takes the next revision again.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `owner` - The PgOpr resource owning the ConfigMap.
+/// - `config` - The postgresql.conf parameters.
+/// - `version` - The PostgreSQL major version the cluster runs.
 pub async fn sync_config(
     manager: &ResourceManager,
     owner: &pgopr,
     config: &BTreeMap<String, String>,
+    version: &str,
 ) -> Result<ConfigResult, crate::Error> {
-    validate_config_keys(config)?;
-
     let hash = config_hash(config);
+    let restart: BTreeMap<String, String> = config
+        .iter()
+        .filter(|(key, _)| parameters::requires_restart(key, version))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    let restart_hash = config_hash(&restart);
 
     let cluster_name = owner.name_any();
-    let cm_name = format!("{}-config-{}", cluster_name, hash);
+    let cm_name = config_map_name(&cluster_name, &hash);
 
     let mut config_file = String::new();
     for (key, value) in config {
@@ -48,10 +154,23 @@ pub async fn sync_config(
         .namespace()
         .unwrap_or_else(|| k8s_manager::DEFAULT_NAMESPACE.to_string());
 
+    let revisions = revisions(manager, &namespace, &cluster_name).await?;
+    let latest = revisions.first().map_or(0, |(_, revision)| *revision);
+    let revision = match revisions.iter().find(|(name, _)| *name == cm_name) {
+        Some((_, revision)) if *revision == latest => latest,
+        _ => latest + 1,
+    };
+
+    let mut labels = BTreeMap::new();
+    labels.insert(LABEL_CLUSTER.to_string(), cluster_name.clone());
+    labels.insert(LABEL_COMPONENT.to_string(), COMPONENT_CONFIG.to_string());
+    labels.insert(LABEL_REVISION.to_string(), revision.to_string());
+
     let cm = ConfigMap {
         metadata: ObjectMeta {
             name: Some(cm_name.clone()),
             namespace: Some(namespace.clone()),
+            labels: Some(labels),
             ..Default::default()
         },
         data: Some(data),
@@ -64,22 +183,184 @@ pub async fn sync_config(
     Ok(ConfigResult {
         name: cm_name,
         hash,
+        restart_hash,
     })
 }
 
-fn validate_config_keys(config: &BTreeMap<String, String>) -> Result<(), crate::Error> {
-    for key in config.keys() {
-        if key.is_empty() {
-            return Err(Error::UserInputError(format!(
-                "Invalid PostgreSQL config key: {}",
-                key
-            )));
+/// Deletes the configuration ConfigMaps superseded by newer revisions.
+///
+/// The current ConfigMap and the latest previous revisions up to the history limit are kept.
+/// ConfigMaps the member workloads still mount are kept as well, a member keeps its
+/// ConfigMap while only reloadable parameters change.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource defining the history limit.
+/// - `topology` - The expected cluster topology.
+/// - `current` - The ConfigMap of the spec, if any.
+/// - `in_use` - The ConfigMaps mounted by the member workloads.
+pub(super) async fn cleanup(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+    current: Option<&str>,
+    in_use: &BTreeSet<String>,
+) -> Result<(), Error> {
+    let limit = pgopr
+        .spec
+        .config_history_limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT);
+    let superseded = revisions(manager, topology.namespace(), topology.name())
+        .await?
+        .into_iter()
+        .filter(|(name, _)| Some(name.as_str()) != current)
+        .skip(limit as usize);
+
+    for (name, revision) in superseded {
+        if !in_use.contains(&name) {
+            info!(
+                "Deleting configuration revision {} of {}",
+                revision,
+                topology.name()
+            );
+            manager
+                .delete::<ConfigMap>(&name, topology.namespace())
+                .await?;
         }
     }
 
     Ok(())
 }
 
+/// The hash of the configuration held by a ConfigMap of the cluster
+///
+/// # Arguments
+/// - `cluster_name` - Name of the cluster.
+/// - `config_map` - Name of the ConfigMap.
+pub(super) fn config_map_hash<'a>(cluster_name: &str, config_map: &'a str) -> Option<&'a str> {
+    config_map.strip_prefix(&format!("{}-config-", cluster_name))
+}
+
+fn config_map_name(cluster_name: &str, hash: &str) -> String {
+    format!("{}-config-{}", cluster_name, hash)
+}
+
+/// The configuration ConfigMaps of a cluster with their revisions, the latest first
+async fn revisions(
+    manager: &ResourceManager,
+    namespace: &str,
+    cluster_name: &str,
+) -> Result<Vec<(String, u64)>, Error> {
+    let api: Api<ConfigMap> = Api::namespaced(manager.get_client(), namespace);
+    let selector = format!(
+        "{}={},{}={}",
+        LABEL_CLUSTER, cluster_name, LABEL_COMPONENT, COMPONENT_CONFIG
+    );
+    let mut revisions: Vec<(String, u64)> = api
+        .list(&ListParams::default().labels(&selector))
+        .await?
+        .iter()
+        .map(|config_map| {
+            let revision = config_map
+                .labels()
+                .get(LABEL_REVISION)
+                .and_then(|revision| revision.parse().ok())
+                .unwrap_or(0);
+            (config_map.name_any(), revision)
+        })
+        .collect();
+    revisions.sort_by(|left, right| right.1.cmp(&left.1).then_with(|| left.0.cmp(&right.0)));
+
+    Ok(revisions)
+}
+
+/// Applies the reloadable parameters to a running member and reloads it.
+///
+/// A member keeps the configuration file it was started with until a parameter requiring
+/// a restart changes. Reloadable parameters that differ from that file are written to
+/// `postgresql.auto.conf` with `ALTER SYSTEM`, and other settings made there are reset.
+/// The member is also reloaded once the kubelet updated its pg_hba.conf. Returns the
+/// parameters the member reports as waiting for a restart.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `namespace` - Namespace of the cluster.
+/// - `member` - Name of the member.
+/// - `config` - The postgresql.conf parameters.
+/// - `version` - The PostgreSQL major version the cluster runs.
+pub(super) async fn reload(
+    manager: &ResourceManager,
+    namespace: &str,
+    member: &str,
+    config: &BTreeMap<String, String>,
+    version: &str,
+) -> Result<Vec<String>, Error> {
+    let output = sql::execute(
+        manager.get_client(),
+        namespace,
+        member,
+        &[&settings_query()],
+    )
+    .await?;
+    let settings: MemberSettings = serde_json::from_str(&output)
+        .map_err(|err| Error::SqlError(format!("Unexpected settings of {}: {}", member, err)))?;
+
+    let desired: BTreeMap<String, &String> = config
+        .iter()
+        .map(|(key, value)| (key.to_lowercase(), value))
+        .collect();
+    let mut overrides = BTreeMap::new();
+    for (name, value) in &desired {
+        if !parameters::requires_restart(name, version)
+            && !settings
+                .file
+                .get(name)
+                .is_some_and(|file| same_value(name, file, value))
+        {
+            overrides.insert(name.clone(), (*value).clone());
+        }
+    }
+    // a parameter removed from the spec returns to its built-in value
+    for name in settings.file.keys() {
+        if !desired.contains_key(name)
+            && !parameters::requires_restart(name, version)
+            && let Some(boot) = settings.boot.get(name)
+        {
+            overrides.insert(name.clone(), boot.clone());
+        }
+    }
+
+    let mut statements = Vec::new();
+    for (name, value) in &overrides {
+        if !REPLICATION_SETTINGS.contains(&name.as_str())
+            && !settings
+                .auto
+                .get(name)
+                .is_some_and(|auto| same_value(name, auto, value))
+        {
+            statements.push(format!(
+                "ALTER SYSTEM SET {} = {}",
+                setting_name(name),
+                setting_value(name, value)
+            ));
+        }
+    }
+    for name in settings.auto.keys() {
+        if !overrides.contains_key(name) && !REPLICATION_SETTINGS.contains(&name.as_str()) {
+            statements.push(format!("ALTER SYSTEM RESET {}", setting_name(name)));
+        }
+    }
+
+    if !statements.is_empty() || settings.hba_changed {
+        info!("Reloading the configuration of {}", member);
+        statements.push("SELECT pg_reload_conf()".to_string());
+        let statements: Vec<&str> = statements.iter().map(String::as_str).collect();
+        sql::execute(manager.get_client(), namespace, member, &statements).await?;
+    }
+
+    Ok(settings.pending)
+}
+
 fn config_hash(config: &BTreeMap<String, String>) -> String {
     let mut hash = 0xcbf29ce484222325u64;
     for (key, value) in config {
@@ -99,6 +380,79 @@ fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
     hash
 }
 
+/// Reads the parameters of the configuration file a member was started with, those set
+/// with `ALTER SYSTEM` and those waiting for a restart, and whether pg_hba.conf changed.
+fn settings_query() -> String {
+    let config_file = sql::quote_literal(workload::CONFIG_MOUNT);
+    let hba_file = sql::quote_literal(workload::HBA_FILE);
+    format!(
+        "SELECT json_build_object(\
+            'file', (SELECT coalesce(json_object_agg(lower(name), setting), '{{}}') FROM pg_file_settings WHERE sourcefile = {config_file}), \
+            'auto', (SELECT coalesce(json_object_agg(lower(name), setting), '{{}}') FROM pg_file_settings WHERE sourcefile LIKE '%/postgresql.auto.conf'), \
+            'boot', (SELECT coalesce(json_object_agg(lower(name), boot_val), '{{}}') FROM pg_settings WHERE boot_val IS NOT NULL), \
+            'pending', (SELECT coalesce(json_agg(lower(name)), '[]') FROM pg_settings WHERE pending_restart), \
+            'hba_changed', coalesce((SELECT modification > pg_conf_load_time() FROM pg_stat_file({hba_file}, true)), false))"
+    )
+}
+
+fn same_value(name: &str, left: &str, right: &str) -> bool {
+    if NAME_LISTS.contains(&name) {
+        list_elements(left) == list_elements(right)
+    } else {
+        left == right
+    }
+}
+
+fn setting_name(name: &str) -> String {
+    name.split('.')
+        .map(sql::quote_ident)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn setting_value(name: &str, value: &str) -> String {
+    if !NAME_LISTS.contains(&name) {
+        return sql::quote_literal(value);
+    }
+
+    let elements = list_elements(value);
+    if elements.is_empty() {
+        return sql::quote_literal("");
+    }
+    elements
+        .iter()
+        .map(|element| sql::quote_literal(element))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Splits a list of names as written in postgresql.conf, such as `"$user", public`.
+fn list_elements(value: &str) -> Vec<String> {
+    let mut elements = Vec::new();
+    let mut element = String::new();
+    let mut quoted = false;
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                element.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                elements.push(element.trim().to_string());
+                element.clear();
+            }
+            c if quoted || !c.is_whitespace() || !element.is_empty() => element.push(c),
+            _ => {}
+        }
+    }
+    if !element.trim().is_empty() || !elements.is_empty() {
+        elements.push(element.trim().to_string());
+    }
+    elements
+}
+
 fn escape_config_value(value: &str) -> String {
     value.replace('\\', "\\\\").replace('\'', "''")
 }
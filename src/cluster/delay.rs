@@ -0,0 +1,189 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::topology::ClusterTopology;
+use crate::Error;
+use crate::crd::v1::{DelayedReplicaStatus, DelayedReplicasSpec, pgopr};
+use crate::manager::{self, ResourceManager};
+use crate::{parameters, sql};
+use kube::ResourceExt;
+use log::{debug, info};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+const APPLY_DELAY: &str = "recovery_min_apply_delay";
+
+/// The apply delay of a replica and how far its replay is behind
+const DELAY_QUERY: &str = "SELECT json_build_object(\
+    'auto', (SELECT setting FROM pg_file_settings WHERE name = 'recovery_min_apply_delay' \
+    AND sourcefile LIKE '%/postgresql.auto.conf' ORDER BY seqno DESC LIMIT 1), \
+    'apply_delay', current_setting('recovery_min_apply_delay'), \
+    'delay_seconds', extract(epoch FROM now() - pg_last_xact_replay_timestamp())::bigint, \
+    'paused', pg_is_wal_replay_paused())";
+
+/// The replay state of a replica
+#[derive(Deserialize)]
+struct Replay {
+    /// recovery_min_apply_delay in `postgresql.auto.conf`
+    auto: Option<String>,
+    apply_delay: String,
+    delay_seconds: Option<i64>,
+    paused: bool,
+}
+
+/// Checks that the delayed replicas are members and that their delay is valid.
+///
+/// # Arguments
+/// - `topology` - The expected cluster topology.
+/// - `delayed` - The delayed replicas of the spec.
+/// - `version` - The PostgreSQL major version the cluster runs.
+pub(super) fn validate(
+    topology: &ClusterTopology,
+    delayed: &DelayedReplicasSpec,
+    version: &str,
+) -> Option<String> {
+    let members = topology.member_names();
+    let unknown: Vec<&str> = delayed
+        .members
+        .iter()
+        .filter(|name| !members.contains(*name))
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        return Some(format!(
+            "delayedReplicas name unknown members: {}",
+            unknown.join(", ")
+        ));
+    }
+
+    parameters::validate(APPLY_DELAY, &delayed.apply_delay, version)
+        .err()
+        .map(|problem| {
+            format!(
+                "delayedReplicas.applyDelay '{}' is invalid: {}",
+                delayed.apply_delay, problem
+            )
+        })
+}
+
+/// Applies the delay and the paused replay to the replicas.
+///
+/// Delayed replicas get `recovery_min_apply_delay` through `ALTER SYSTEM`, other replicas
+/// lose it. Replay is paused on the delayed replicas listed in the `pgopr.io/replay-paused`
+/// annotation and resumed on all others. Returns the state of the running delayed replicas.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource defining the cluster.
+/// - `topology` - The expected cluster topology.
+pub(super) async fn sync(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+) -> Result<Vec<DelayedReplicaStatus>, Error> {
+    let namespace = topology.namespace();
+    let apply_delay = pgopr
+        .spec
+        .delayed_replicas
+        .as_ref()
+        .map(|delayed| delayed.apply_delay.as_str());
+    let paused = paused_replicas(pgopr);
+    let mut statuses = Vec::new();
+
+    for member in topology.replica_members() {
+        if topology.is_fenced(member.name()) {
+            continue;
+        }
+
+        let replay = match query(manager, namespace, member.name()).await {
+            Ok(replay) => replay,
+            Err(Error::SqlError(err)) => {
+                debug!("Unable to read the replay of {}: {}", member.name(), err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        let delay = apply_delay.filter(|_| topology.is_delayed(member.name()));
+        let pause = delay.is_some() && paused.contains(member.name());
+        let mut statements = Vec::new();
+        match delay {
+            Some(delay) if replay.auto.as_deref() != Some(delay) => {
+                info!("Delaying the replay of {} by {}", member.name(), delay);
+                statements.push(format!(
+                    "ALTER SYSTEM SET {} = {}",
+                    APPLY_DELAY,
+                    sql::quote_literal(delay)
+                ));
+                statements.push("SELECT pg_reload_conf()".to_string());
+            }
+            None if replay.auto.is_some() => {
+                info!("Removing the replay delay of {}", member.name());
+                statements.push(format!("ALTER SYSTEM RESET {}", APPLY_DELAY));
+                statements.push("SELECT pg_reload_conf()".to_string());
+            }
+            _ => {}
+        }
+        if pause != replay.paused {
+            info!(
+                "{} replay on {}",
+                if pause { "Pausing" } else { "Resuming" },
+                member.name()
+            );
+            statements.push(
+                if pause {
+                    "SELECT pg_wal_replay_pause()"
+                } else {
+                    "SELECT pg_wal_replay_resume()"
+                }
+                .to_string(),
+            );
+        }
+        if !statements.is_empty() {
+            let statements: Vec<&str> = statements.iter().map(String::as_str).collect();
+            sql::execute(manager.get_client(), namespace, member.name(), &statements).await?;
+        }
+
+        if let Some(delay) = delay {
+            statuses.push(DelayedReplicaStatus {
+                name: member.name().to_string(),
+                apply_delay: if replay.auto.as_deref() == Some(delay) {
+                    replay.apply_delay
+                } else {
+                    delay.to_string()
+                },
+                delay_seconds: replay.delay_seconds,
+                replay_paused: pause,
+            });
+        }
+    }
+
+    Ok(statuses)
+}
+
+/// The replicas whose replay is paused, from the `pgopr.io/replay-paused` annotation
+fn paused_replicas(pgopr: &pgopr) -> BTreeSet<String> {
+    pgopr
+        .annotations()
+        .get(manager::ANNOTATION_REPLAY_PAUSED)
+        .map(|paused| {
+            paused
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn query(manager: &ResourceManager, namespace: &str, member: &str) -> Result<Replay, Error> {
+    let output = sql::execute(manager.get_client(), namespace, member, &[DELAY_QUERY]).await?;
+    serde_json::from_str(&output)
+        .map_err(|err| Error::SqlError(format!("Unexpected replay of {}: {}", member, err)))
+}
@@ -0,0 +1,188 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::replication;
+use super::status;
+use super::topology::{ClusterMember, ClusterTopology};
+use crate::Error;
+use crate::crd::v1::{FailoverStatus, pgopr};
+use crate::manager::ResourceManager;
+use crate::sql;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use k8s_openapi::jiff::Timestamp;
+use log::{info, warn};
+
+const DEFAULT_GRACE_PERIOD_SECONDS: u32 = 30;
+
+/// Promotes the most advanced replica once the primary has been unavailable for longer
+/// than the grace period.
+///
+/// The grace period starts when a primary that was available is seen unavailable. The
+/// former primary is fenced so it cannot come back as a second primary, and the remaining
+/// replicas are re-pointed at the promoted member. The topology is updated in place.
+///
+/// Returns the failover state to record in the status.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource defining the cluster.
+/// - `topology` - The cluster topology, updated when a replica is promoted.
+pub(super) async fn run(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &mut ClusterTopology,
+) -> Result<FailoverStatus, Error> {
+    let status = pgopr.status.as_ref();
+    let mut state = status
+        .and_then(|status| status.failover.clone())
+        .unwrap_or_default();
+    let spec = pgopr.spec.failover.as_ref();
+
+    let primary = topology.primary();
+    // a primary restarting with a new image during a rolling update, or shutting down for a
+    // switchover, has not failed
+    let restarting = status
+        .and_then(|status| status.rollout.as_ref())
+        .and_then(|rollout| rollout.current.as_deref())
+        == Some(primary.name())
+        || state.switchover.is_some();
+    if !spec.and_then(|failover| failover.enabled).unwrap_or(true)
+        || restarting
+        || is_ready(manager, topology.namespace(), primary.name()).await?
+    {
+        state.primary_unavailable_since = None;
+        return Ok(state);
+    }
+
+    let now = Timestamp::now();
+    let Some(since) = state
+        .primary_unavailable_since
+        .as_ref()
+        .map(|since| since.0)
+    else {
+        // only a primary that was available before can fail, not one still starting up
+        if status
+            .and_then(|status| status.primary.as_ref())
+            .is_some_and(|primary| primary.available)
+        {
+            warn!("Primary {} is unavailable", primary.name());
+            state.primary_unavailable_since = Some(Time(now));
+        }
+        return Ok(state);
+    };
+
+    let grace_period = spec
+        .and_then(|failover| failover.grace_period_seconds)
+        .unwrap_or(DEFAULT_GRACE_PERIOD_SECONDS);
+    if now.as_second() - since.as_second() < i64::from(grace_period) {
+        return Ok(state);
+    }
+
+    let Some(candidate) = candidate(manager, topology).await? else {
+        warn!(
+            "Primary {} is unavailable and no replica can be promoted",
+            primary.name()
+        );
+        return Ok(state);
+    };
+
+    match sql::execute(
+        manager.get_client(),
+        topology.namespace(),
+        candidate.name(),
+        &["SELECT pg_promote()"],
+    )
+    .await
+    {
+        Ok(promoted) if promoted == "t" => {}
+        Ok(_) => {
+            warn!("Promotion of {} did not complete in time", candidate.name());
+            return Ok(state);
+        }
+        Err(Error::SqlError(err)) => {
+            warn!("Unable to promote {}: {}", candidate.name(), err);
+            return Ok(state);
+        }
+        Err(err) => return Err(err),
+    }
+
+    info!(
+        "Promoted {} to primary, fencing {}",
+        candidate.name(),
+        primary.name()
+    );
+    topology.promote(candidate.name());
+    topology.fence(primary.name());
+
+    state.primary_unavailable_since = None;
+    state.last_failover = Some(Time(now));
+    if !state.fenced.iter().any(|name| name == primary.name()) {
+        state.fenced.push(primary.name().to_string());
+    }
+
+    replication::follow_primary(manager, topology).await?;
+
+    Ok(state)
+}
+
+/// Picks the ready replica that has replayed the most WAL, delayed replicas are never
+/// promoted.
+async fn candidate(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+) -> Result<Option<ClusterMember>, Error> {
+    let mut best: Option<(u64, ClusterMember)> = None;
+
+    for member in topology.replica_members() {
+        if topology.is_fenced(member.name())
+            || topology.is_delayed(member.name())
+            || !is_ready(manager, topology.namespace(), member.name()).await?
+        {
+            continue;
+        }
+
+        let output = match sql::execute(
+            manager.get_client(),
+            topology.namespace(),
+            member.name(),
+            &["SELECT pg_is_in_recovery(), pg_last_wal_replay_lsn()"],
+        )
+        .await
+        {
+            Ok(output) => output,
+            Err(Error::SqlError(err)) => {
+                warn!("Unable to query {}: {}", member.name(), err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        let Some(lsn) = output.strip_prefix("t|").and_then(parse_lsn) else {
+            continue;
+        };
+        if best.as_ref().is_none_or(|(best_lsn, _)| lsn > *best_lsn) {
+            best = Some((lsn, member));
+        }
+    }
+
+    Ok(best.map(|(_, member)| member))
+}
+
+async fn is_ready(manager: &ResourceManager, namespace: &str, name: &str) -> Result<bool, Error> {
+    Ok(status::member_ready_replicas(manager, namespace, name)
+        .await?
+        .is_some_and(|ready| ready > 0))
+}
+
+/// Parses a WAL location such as `16/B374D848` into a comparable number.
+fn parse_lsn(lsn: &str) -> Option<u64> {
+    let (high, low) = lsn.split_once('/')?;
+    let high = u64::from_str_radix(high, 16).ok()?;
+    let low = u64::from_str_radix(low, 16).ok()?;
+    Some((high << 32) | low)
+}
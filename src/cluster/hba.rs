@@ -0,0 +1,215 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::secrets;
+use super::topology::ClusterTopology;
+use crate::Error;
+use crate::crd::v1::{HbaMethod, HbaRule, HbaType, pgopr};
+use crate::credentials::{self, Credential};
+use crate::manager::{LABEL_CLUSTER, LABEL_COMPONENT, ResourceManager};
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::api::ObjectMeta;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+const HBA_FILE_NAME: &str = "pg_hba.conf";
+const COMPONENT_HBA: &str = "hba";
+
+/// Keywords pg_hba.conf takes in place of a CIDR address
+const ADDRESS_KEYWORDS: [&str; 3] = ["all", "samehost", "samenet"];
+
+/// Checks the client authentication rules of the spec.
+///
+/// Returns the problems found, naming each invalid rule by its position.
+///
+/// # Arguments
+/// - `rules` - The rules of `spec.hba`.
+pub(super) fn validate(rules: &[HbaRule]) -> Option<String> {
+    let problems: Vec<String> = rules
+        .iter()
+        .enumerate()
+        .filter_map(|(index, rule)| {
+            validate_rule(rule)
+                .err()
+                .map(|problem| format!("rule {} ({})", index + 1, problem))
+        })
+        .collect();
+
+    (!problems.is_empty()).then(|| format!("Invalid pg_hba rules: {}", problems.join(", ")))
+}
+
+/// Writes pg_hba.conf into the ConfigMap mounted by all members.
+///
+/// The rules of the operator come first, so the rules of the spec cannot lock out the
+/// replicas, pgmoneta and pgexporter. Only the operator's own `postgres` connections through
+/// the local socket are covered, other local connections follow the rules of the spec.
+/// Without rules in the spec all addresses may connect with a password. The members reload
+/// the file once the kubelet updated it.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource owning the ConfigMap.
+/// - `topology` - The expected cluster topology.
+/// - `version` - The PostgreSQL major version the cluster runs.
+pub(super) async fn sync(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+    version: &str,
+) -> Result<(), Error> {
+    let credentials = topology.credentials();
+    let method = password_method(pgopr, version);
+    let replication = username(manager, topology, &credentials.replication).await?;
+    let backup = username(manager, topology, &credentials.backup).await?;
+    let exporter = username(manager, topology, &credentials.exporter).await?;
+
+    let mut lines = vec![
+        "# Managed by pgopr, change spec.hba of the cluster instead".to_string(),
+        // the operator runs psql in the pods as postgres through the local socket
+        rule_line("local", "all", credentials::SUPERUSER, None, "peer"),
+        rule_line("host", "replication", &replication, Some("all"), method),
+        rule_line("host", "replication", &backup, Some("all"), method),
+        rule_line("host", "postgres", &backup, Some("all"), method),
+        rule_line("host", "postgres", &exporter, Some("all"), method),
+    ];
+    match &pgopr.spec.hba {
+        Some(rules) => lines.extend(rules.iter().map(|rule| {
+            rule_line(
+                rule.type_.as_str(),
+                &rule.database,
+                &rule.user,
+                rule.cidr.as_deref(),
+                rule.method.as_str(),
+            )
+        })),
+        None => lines.extend([
+            rule_line("local", "all", "all", None, method),
+            rule_line("host", "all", "all", Some("all"), method),
+        ]),
+    }
+
+    let mut data = BTreeMap::new();
+    data.insert(HBA_FILE_NAME.to_string(), lines.join("\n") + "\n");
+
+    let mut labels = BTreeMap::new();
+    labels.insert(LABEL_CLUSTER.to_string(), topology.name().to_string());
+    labels.insert(LABEL_COMPONENT.to_string(), COMPONENT_HBA.to_string());
+
+    let cm = ConfigMap {
+        metadata: ObjectMeta {
+            name: Some(topology.hba_name()),
+            namespace: Some(topology.namespace().to_string()),
+            labels: Some(labels),
+            ..Default::default()
+        },
+        data: Some(data),
+        ..Default::default()
+    };
+    manager.sync(pgopr, cm).await?;
+
+    Ok(())
+}
+
+fn validate_rule(rule: &HbaRule) -> Result<(), String> {
+    for (field, value) in [("database", &rule.database), ("user", &rule.user)] {
+        if value.is_empty() {
+            return Err(format!("{} is empty", field));
+        }
+        if value
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '#')
+        {
+            return Err(format!("{} '{}' contains whitespace or '#'", field, value));
+        }
+    }
+
+    match (rule.type_, rule.cidr.as_deref()) {
+        (HbaType::Local, Some(_)) => return Err("local rules take no cidr".to_string()),
+        (HbaType::Local, None) => {}
+        (type_, None) => return Err(format!("{} rules need a cidr", type_.as_str())),
+        (_, Some(cidr)) => validate_cidr(cidr)?,
+    }
+
+    match rule.method {
+        HbaMethod::Peer if rule.type_ != HbaType::Local => {
+            Err("peer is only allowed for local rules".to_string())
+        }
+        HbaMethod::Cert if rule.type_ != HbaType::HostSsl => {
+            Err("cert is only allowed for hostssl rules".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_cidr(cidr: &str) -> Result<(), String> {
+    if ADDRESS_KEYWORDS.contains(&cidr) {
+        return Ok(());
+    }
+
+    let invalid = || format!("'{}' is not a CIDR address", cidr);
+    let (address, prefix) = cidr.split_once('/').ok_or_else(invalid)?;
+    let address: IpAddr = address.parse().map_err(|_| invalid())?;
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    match prefix.parse::<u8>() {
+        Ok(prefix) if prefix <= max_prefix => Ok(()),
+        _ => Err(invalid()),
+    }
+}
+
+/// The authentication method of the roles of the operator.
+///
+/// `md5` also accepts passwords stored as SCRAM, it is used while new passwords are stored
+/// as MD5, the default of PostgreSQL 13.
+fn password_method(pgopr: &pgopr, version: &str) -> &'static str {
+    let encryption = pgopr
+        .spec
+        .config
+        .iter()
+        .flatten()
+        .find(|(name, _)| name.eq_ignore_ascii_case("password_encryption"))
+        .map(|(_, value)| value.as_str());
+
+    match encryption {
+        Some(encryption) if encryption.eq_ignore_ascii_case("scram-sha-256") => {
+            HbaMethod::ScramSha256.as_str()
+        }
+        Some(_) => HbaMethod::Md5.as_str(),
+        None if version == "13" => HbaMethod::Md5.as_str(),
+        None => HbaMethod::ScramSha256.as_str(),
+    }
+}
+
+/// The quoted role name of a credential, the default name until its Secret exists
+async fn username(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    credential: &Credential,
+) -> Result<String, Error> {
+    let username = secrets::login(manager, topology.namespace(), credential)
+        .await?
+        .map(|(username, _)| username)
+        .unwrap_or_else(|| credential.default_username.to_string());
+    Ok(format!("\"{}\"", username))
+}
+
+fn rule_line(
+    type_: &str,
+    database: &str,
+    user: &str,
+    address: Option<&str>,
+    method: &str,
+) -> String {
+    format!(
+        "{:<12} {:<16} {:<16} {:<20} {}",
+        type_,
+        database,
+        user,
+        address.unwrap_or(""),
+        method
+    )
+}
@@ -0,0 +1,110 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::config;
+use super::topology;
+use crate::crd::v1::{PgOprSpec, ReplicaSpec};
+use crate::parameters;
+use std::collections::BTreeSet;
+
+/// Longest name of a Service, the members are reachable through one named after them
+const MAX_MEMBER_NAME: usize = 63;
+
+/// Checks the replica specs against the rest of the spec.
+///
+/// Returns the problems found, naming each invalid replica.
+///
+/// # Arguments
+/// - `cluster_name` - Name of the cluster.
+/// - `spec` - The spec of the cluster.
+/// - `version` - The PostgreSQL major version the cluster runs.
+pub(super) fn validate(cluster_name: &str, spec: &PgOprSpec, version: &str) -> Option<String> {
+    let replicas = spec.replica_specs.as_ref()?;
+    if let Some(count) = spec
+        .replicas
+        .filter(|count| *count as usize != replicas.len())
+    {
+        return Some(format!(
+            "replicas {} disagrees with the {} replicaSpecs",
+            count,
+            replicas.len()
+        ));
+    }
+
+    let mut names = BTreeSet::new();
+    let problems: Vec<String> = replicas
+        .iter()
+        .filter_map(|replica| {
+            let problem = if !names.insert(replica.name.as_str()) {
+                Err("the name is used twice".to_string())
+            } else {
+                validate_replica(cluster_name, spec, replica, version)
+            };
+            problem
+                .err()
+                .map(|problem| format!("{} ({})", replica.name, problem))
+        })
+        .collect();
+
+    (!problems.is_empty()).then(|| format!("Invalid replicaSpecs: {}", problems.join(", ")))
+}
+
+fn validate_replica(
+    cluster_name: &str,
+    spec: &PgOprSpec,
+    replica: &ReplicaSpec,
+    version: &str,
+) -> Result<(), String> {
+    let name = &replica.name;
+    let valid_label = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-');
+    if !valid_label {
+        return Err("the name takes lower case letters, digits and inner dashes".to_string());
+    }
+    // the WAL volume of a member is named `<member>-wal`
+    if name.ends_with(&format!("-{}", topology::WAL_NAME_SUFFIX)) {
+        return Err(format!(
+            "names ending in -{} are taken by the WAL volumes",
+            topology::WAL_NAME_SUFFIX
+        ));
+    }
+    if topology::replica_name(cluster_name, name).len() > MAX_MEMBER_NAME {
+        return Err(format!(
+            "the member name exceeds {} characters",
+            MAX_MEMBER_NAME
+        ));
+    }
+
+    if let Some(overrides) = &replica.config {
+        if let Some(problem) = config::validate(overrides, version) {
+            return Err(problem);
+        }
+        // the members share the configuration file holding the other parameters
+        let restart: Vec<&str> = overrides
+            .keys()
+            .filter(|name| parameters::requires_restart(name, version))
+            .map(String::as_str)
+            .collect();
+        if !restart.is_empty() {
+            return Err(format!(
+                "parameters needing a restart belong in spec.config: {}",
+                restart.join(", ")
+            ));
+        }
+    }
+
+    if replica.delayed.unwrap_or(false) && spec.delayed_replicas.is_none() {
+        return Err("delayed replicas need delayedReplicas.applyDelay".to_string());
+    }
+
+    Ok(())
+}
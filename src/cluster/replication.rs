@@ -0,0 +1,449 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::secrets;
+use super::topology::{ClusterMember, ClusterTopology};
+use crate::Error;
+use crate::manager::ResourceManager;
+use crate::{replica, sql};
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::PodTemplateSpec;
+use kube::Api;
+use log::{debug, info, warn};
+
+const POSTGRESQL_PORT: i32 = 5432;
+
+/// Checks that `spec.upstreams` names members and contains no cycle.
+///
+/// # Arguments
+/// - `topology` - The expected cluster topology.
+pub(super) fn validate(topology: &ClusterTopology) -> Option<String> {
+    let upstreams = topology.upstreams();
+    let members = topology.member_names();
+    let unknown: Vec<&str> = upstreams
+        .iter()
+        .flat_map(|(replica, upstream)| [replica, upstream])
+        .filter(|name| !members.contains(*name))
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        return Some(format!(
+            "upstreams name unknown members: {}",
+            unknown.join(", ")
+        ));
+    }
+
+    for start in upstreams.keys() {
+        let mut chain = vec![start.as_str()];
+        let mut current = start;
+        while let Some(upstream) = upstreams.get(current) {
+            if upstream == start {
+                chain.push(upstream);
+                return Some(format!("upstreams form a cycle: {}", chain.join(" -> ")));
+            }
+            // a cycle the chain runs into is reported starting from one of its members
+            if chain.contains(&upstream.as_str()) {
+                break;
+            }
+            chain.push(upstream);
+            current = upstream;
+        }
+    }
+
+    None
+}
+
+/// The upstream in the workload template of a replica.
+///
+/// A replica keeps the upstream it was created with, so a failover, a switchover or a change
+/// of `spec.upstreams` does not restart it, `follow_upstreams` re-points it instead. A new
+/// workload is cloned from the current upstream, a promoted member from none.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `topology` - The expected cluster topology.
+/// - `member` - The replica.
+pub(super) async fn template_upstream(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    member: &ClusterMember,
+) -> Result<Option<String>, Error> {
+    let deploy_api: Api<Deployment> = Api::namespaced(manager.get_client(), topology.namespace());
+    let template = match deploy_api.get_opt(member.name()).await? {
+        Some(deployment) => deployment.spec.map(|spec| spec.template),
+        None => {
+            let sts_api: Api<StatefulSet> =
+                Api::namespaced(manager.get_client(), topology.namespace());
+            sts_api
+                .get_opt(member.name())
+                .await?
+                .and_then(|stateful_set| stateful_set.spec)
+                .map(|spec| spec.template)
+        }
+    };
+    if let Some(upstream) = template.as_ref().and_then(primary_env) {
+        return Ok(Some(upstream));
+    }
+
+    let upstream = topology.upstream(member);
+    Ok((upstream.name() != member.name()).then(|| upstream.name().to_string()))
+}
+
+fn primary_env(template: &PodTemplateSpec) -> Option<String> {
+    template
+        .spec
+        .as_ref()?
+        .containers
+        .first()?
+        .env
+        .iter()
+        .flatten()
+        .find(|env| env.name == replica::PRIMARY_ENV)?
+        .value
+        .clone()
+}
+
+/// Points a replica at an upstream server by rewriting `primary_conninfo` and
+/// `primary_slot_name` and reloading.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `namespace` - Namespace of the cluster.
+/// - `member` - The replica to re-point.
+/// - `upstream` - Host name of the server to stream from.
+/// - `username` - The replication user.
+/// - `password` - The replication password.
+pub(super) async fn follow(
+    manager: &ResourceManager,
+    namespace: &str,
+    member: &ClusterMember,
+    upstream: &str,
+    username: &str,
+    password: &str,
+) -> Result<(), Error> {
+    let conninfo = conninfo(upstream, username, password, member.slot_name());
+
+    sql::execute(
+        manager.get_client(),
+        namespace,
+        member.name(),
+        &[
+            &format!(
+                "ALTER SYSTEM SET primary_conninfo = {}",
+                sql::quote_literal(&conninfo)
+            ),
+            &format!(
+                "ALTER SYSTEM SET primary_slot_name = {}",
+                sql::quote_literal(member.slot_name())
+            ),
+            "SELECT pg_reload_conf()",
+        ],
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Creates the slots of the remaining replicas on their upstream and re-points them.
+///
+/// Replicas stream from the new primary unless `spec.upstreams` names another member.
+/// Failures are only logged, `follow_upstreams` retries on the next reconcile.
+pub(super) async fn follow_primary(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+) -> Result<(), Error> {
+    let Some((username, password)) = secrets::login(
+        manager,
+        topology.namespace(),
+        &topology.credentials().replication,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+
+    for member in topology.replica_members() {
+        if topology.is_fenced(member.name()) {
+            continue;
+        }
+
+        let upstream = topology.upstream(&member);
+        let result = repoint(
+            manager,
+            topology.namespace(),
+            &member,
+            upstream.name(),
+            &username,
+            &password,
+        )
+        .await;
+        if let Err(err) = result {
+            warn!(
+                "Unable to re-point {} at {}: {}",
+                member.name(),
+                upstream.name(),
+                err
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-points the replicas whose `primary_conninfo` names another server than their upstream.
+///
+/// The slot of a replica on its upstream must exist first. The workload templates keep the
+/// upstream the replicas were created with, so this is how replicas follow a new primary or
+/// a change of `spec.upstreams` without a restart.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `topology` - The expected cluster topology.
+pub(super) async fn follow_upstreams(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+) -> Result<(), Error> {
+    let namespace = topology.namespace();
+    let mut login = None;
+
+    for member in topology.replica_members() {
+        if topology.is_fenced(member.name()) {
+            continue;
+        }
+
+        let current = match sql::execute(
+            manager.get_client(),
+            namespace,
+            member.name(),
+            &["SELECT current_setting('primary_conninfo')"],
+        )
+        .await
+        {
+            Ok(current) => current,
+            Err(Error::SqlError(err)) => {
+                debug!("Unable to read the upstream of {}: {}", member.name(), err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let upstream = topology.upstream(&member);
+        if points_at(&current, upstream.name()) {
+            continue;
+        }
+
+        if login.is_none() {
+            login = secrets::login(manager, namespace, &topology.credentials().replication).await?;
+        }
+        let Some((username, password)) = &login else {
+            return Ok(());
+        };
+
+        info!("Re-pointing {} at {}", member.name(), upstream.name());
+        match follow(
+            manager,
+            namespace,
+            &member,
+            upstream.name(),
+            username,
+            password,
+        )
+        .await
+        {
+            Ok(()) => {}
+            Err(Error::SqlError(err)) => {
+                warn!("Unable to re-point {}: {}", member.name(), err)
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+async fn repoint(
+    manager: &ResourceManager,
+    namespace: &str,
+    member: &ClusterMember,
+    upstream: &str,
+    username: &str,
+    password: &str,
+) -> Result<(), Error> {
+    create_slot(manager, namespace, upstream, member.slot_name()).await?;
+    follow(manager, namespace, member, upstream, username, password).await
+}
+
+/// Checks once whether a replica has replayed the WAL written by the primary so far.
+///
+/// A server that cannot be queried counts as not caught up. A delayed replica replays the
+/// WAL only after its delay, it counts as caught up once it received the WAL.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `namespace` - Namespace of the cluster.
+/// - `primary` - Name of the primary.
+/// - `member` - Name of the replica.
+/// - `delayed` - Whether the replica is delayed.
+pub(super) async fn is_caught_up(
+    manager: &ResourceManager,
+    namespace: &str,
+    primary: &str,
+    member: &str,
+    delayed: bool,
+) -> Result<bool, Error> {
+    let primary_lsn = match sql::execute(
+        manager.get_client(),
+        namespace,
+        primary,
+        &["SELECT pg_current_wal_lsn()"],
+    )
+    .await
+    {
+        Ok(lsn) => lsn,
+        Err(Error::SqlError(_)) => return Ok(false),
+        Err(err) => return Err(err),
+    };
+
+    match sql::execute(
+        manager.get_client(),
+        namespace,
+        member,
+        &[&format!(
+            "SELECT {}() >= {}::pg_lsn",
+            if delayed {
+                "pg_last_wal_receive_lsn"
+            } else {
+                "pg_last_wal_replay_lsn"
+            },
+            sql::quote_literal(&primary_lsn)
+        )],
+    )
+    .await
+    {
+        Ok(output) => Ok(output == "t"),
+        Err(Error::SqlError(_)) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Creates a physical replication slot on a server unless it already exists.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `namespace` - Namespace of the cluster.
+/// - `server` - Name of the member holding the slot.
+/// - `slot_name` - Name of the slot.
+pub(super) async fn create_slot(
+    manager: &ResourceManager,
+    namespace: &str,
+    server: &str,
+    slot_name: &str,
+) -> Result<(), Error> {
+    let slot_name = sql::quote_literal(slot_name);
+    sql::execute(
+        manager.get_client(),
+        namespace,
+        server,
+        &[&format!(
+            "SELECT pg_create_physical_replication_slot({slot_name}) WHERE NOT EXISTS (SELECT FROM pg_replication_slots WHERE slot_name = {slot_name})"
+        )],
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn conninfo(host: &str, username: &str, password: &str, application_name: &str) -> String {
+    format!(
+        "host={} port={} user={} password={} application_name={}",
+        conninfo_value(host),
+        POSTGRESQL_PORT,
+        conninfo_value(username),
+        conninfo_value(password),
+        conninfo_value(application_name)
+    )
+}
+
+/// Checks whether a `primary_conninfo` written by `follow` connects to a host
+fn points_at(conninfo: &str, host: &str) -> bool {
+    conninfo.starts_with(&format!("host={} ", conninfo_value(host)))
+}
+
+fn conninfo_value(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crd::v1::{PgOprSpec, pgopr};
+
+    fn topology(upstreams: &[(&str, &str)]) -> ClusterTopology {
+        let spec = PgOprSpec {
+            replicas: Some(3),
+            upstreams: Some(
+                upstreams
+                    .iter()
+                    .map(|(replica, upstream)| (replica.to_string(), upstream.to_string()))
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        ClusterTopology::from_pgopr(&pgopr::new("pg", spec))
+    }
+
+    #[test]
+    fn upstreams_may_chain() {
+        assert_eq!(
+            validate(&topology(&[
+                ("pg-replica-2", "pg-replica-1"),
+                ("pg-replica-3", "pg-replica-2"),
+            ])),
+            None
+        );
+        assert_eq!(validate(&topology(&[("pg-replica-1", "pg")])), None);
+    }
+
+    #[test]
+    fn upstreams_name_members() {
+        let problem = validate(&topology(&[("pg-replica-2", "pg-replica-4")])).unwrap();
+        assert!(problem.contains("pg-replica-4"), "{}", problem);
+    }
+
+    #[test]
+    fn upstreams_form_no_cycle() {
+        let problem = validate(&topology(&[
+            ("pg-replica-1", "pg-replica-2"),
+            ("pg-replica-2", "pg-replica-1"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            problem,
+            "upstreams form a cycle: pg-replica-1 -> pg-replica-2 -> pg-replica-1"
+        );
+
+        let problem = validate(&topology(&[("pg-replica-1", "pg-replica-1")])).unwrap();
+        assert_eq!(
+            problem,
+            "upstreams form a cycle: pg-replica-1 -> pg-replica-1"
+        );
+    }
+
+    #[test]
+    fn chains_into_a_cycle_report_the_cycle() {
+        let problem = validate(&topology(&[
+            ("pg-replica-1", "pg-replica-2"),
+            ("pg-replica-2", "pg-replica-3"),
+            ("pg-replica-3", "pg-replica-2"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            problem,
+            "upstreams form a cycle: pg-replica-2 -> pg-replica-3 -> pg-replica-2"
+        );
+    }
+}
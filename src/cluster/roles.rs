@@ -0,0 +1,73 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::topology::ClusterTopology;
+use crate::Error;
+use crate::manager::{LABEL_ROLE, ROLE_DELAYED, ROLE_PRIMARY, ROLE_REPLICA, ResourceManager};
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    Api, ResourceExt,
+    api::{ListParams, Patch, PatchParams},
+};
+use serde_json::json;
+
+/// Moves the `pgopr.io/role` label of the member pods to their current role.
+///
+/// The label is set on the pods rather than the Deployment templates, so a failover or
+/// switchover does not restart them. Delayed replicas are labelled apart, so the read-only
+/// Service does not select them, and fenced members lose the label.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `topology` - The expected cluster topology.
+pub(super) async fn sync(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+) -> Result<(), Error> {
+    let pod_api: Api<Pod> = Api::namespaced(manager.get_client(), topology.namespace());
+    let primary = topology.primary();
+
+    for member in topology.members() {
+        let role = if member.name() == primary.name() {
+            Some(ROLE_PRIMARY)
+        } else if topology.is_fenced(member.name()) {
+            None
+        } else if topology.is_delayed(member.name()) {
+            Some(ROLE_DELAYED)
+        } else {
+            Some(ROLE_REPLICA)
+        };
+
+        let selector = format!("app={}", member.name());
+        for pod in pod_api
+            .list(&ListParams::default().labels(&selector))
+            .await?
+        {
+            if pod.labels().get(LABEL_ROLE).map(String::as_str) == role {
+                continue;
+            }
+
+            let patch = json!({
+                "metadata": {
+                    "labels": {
+                        LABEL_ROLE: role
+                    }
+                }
+            });
+            pod_api
+                .patch(
+                    &pod.name_any(),
+                    &PatchParams::default(),
+                    &Patch::Merge(&patch),
+                )
+                .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,338 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::config::ConfigResult;
+use super::replication;
+use super::switchover;
+use super::topology::{ClusterMember, ClusterTopology};
+use crate::Error;
+use crate::catalog::ClusterImages;
+use crate::crd::v1::{FailoverStatus, PrimaryUpdateMethod, RolloutStatus, pgopr};
+use crate::manager::ResourceManager;
+use crate::workload;
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::{Pod, PodTemplateSpec};
+use kube::{Api, api::ListParams};
+use log::{info, warn};
+use std::collections::BTreeMap;
+
+/// Revision is the image and configuration file a member runs.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Revision {
+    pub(super) image: String,
+    /// The ConfigMap holding the configuration file
+    pub(super) config_map: Option<String>,
+    /// Hash of the parameters of the configuration file that require a restart
+    pub(super) config_hash: Option<String>,
+}
+
+impl Revision {
+    /// The revision after updating to another one. The configuration file is only replaced
+    /// when a parameter requiring a restart changed, reloadable parameters are applied to
+    /// the running server instead.
+    fn update(&self, desired: &Revision) -> Revision {
+        Revision {
+            image: desired.image.clone(),
+            config_map: if self.config_hash == desired.config_hash {
+                self.config_map.clone()
+            } else {
+                desired.config_map.clone()
+            },
+            config_hash: desired.config_hash.clone(),
+        }
+    }
+
+    fn is_outdated(&self, desired: &Revision) -> bool {
+        self.image != desired.image || self.config_hash != desired.config_hash
+    }
+}
+
+/// Rollout holds the revision each member runs during this reconcile.
+pub(super) struct Rollout {
+    revisions: BTreeMap<String, Revision>,
+    restarts: Vec<String>,
+    status: Option<RolloutStatus>,
+}
+
+impl Rollout {
+    /// The revision a member runs, its new revision unless it waits for its turn
+    ///
+    /// # Arguments
+    /// - `member` - The member
+    pub(super) fn revision(&self, member: &ClusterMember) -> &Revision {
+        &self.revisions[member.name()]
+    }
+
+    /// The members waiting for a restart to apply their configuration
+    pub(super) fn restarts(&self) -> &[String] {
+        &self.restarts
+    }
+
+    /// The progress of the rollout, if one is in progress
+    pub(super) fn status(&self) -> Option<RolloutStatus> {
+        self.status.clone()
+    }
+}
+
+/// Decides which members take their new image and configuration file in this reconcile.
+///
+/// New members and fenced members take their revision right away. Otherwise one replica
+/// is updated at a time, and the next one waits until all updated replicas are ready and
+/// have caught up with the primary. The primary is updated last, by a restart or by a
+/// switchover to an updated replica, which `switchover::run` continues on the next
+/// reconciles.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource defining the cluster.
+/// - `topology` - The cluster topology.
+/// - `failover` - The failover state recording a switchover.
+/// - `images` - The images of the cluster.
+/// - `config` - The configuration file of the cluster, if it has one.
+pub(super) async fn plan(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+    failover: &mut FailoverStatus,
+    images: &ClusterImages,
+    config: Option<&ConfigResult>,
+) -> Result<Rollout, Error> {
+    let primary = topology.primary();
+    let mut rollout = Rollout {
+        revisions: BTreeMap::new(),
+        restarts: Vec::new(),
+        status: None,
+    };
+    let mut updated = Vec::new();
+    let mut outdated = Vec::new();
+
+    for member in topology.members() {
+        // the workload keeps the flavor it was created with
+        let desired = Revision {
+            image: if member.is_bootstrap() {
+                images.primary.name.clone()
+            } else {
+                images.replica.name.clone()
+            },
+            config_map: config.map(|config| config.name.clone()),
+            config_hash: config.map(|config| config.restart_hash.clone()),
+        };
+        match member_revision(manager, topology.namespace(), member.name()).await? {
+            Some(current)
+                if current.is_outdated(&desired) && !topology.is_fenced(member.name()) =>
+            {
+                if current.config_hash != desired.config_hash {
+                    rollout.restarts.push(member.name().to_string());
+                }
+                rollout
+                    .revisions
+                    .insert(member.name().to_string(), current.clone());
+                outdated.push((member, current.update(&desired)));
+            }
+            current => {
+                let revision = current
+                    .map(|current| current.update(&desired))
+                    .unwrap_or(desired);
+                rollout
+                    .revisions
+                    .insert(member.name().to_string(), revision);
+                updated.push(member);
+            }
+        }
+    }
+    if outdated.is_empty() {
+        // a rollout ends once the primary runs a ready pod with its new revision
+        if pgopr
+            .status
+            .as_ref()
+            .is_some_and(|status| status.rollout.is_some())
+            && !runs_revision(manager, topology, &primary, rollout.revision(&primary)).await?
+        {
+            rollout.status = Some(RolloutStatus {
+                updated: updated
+                    .iter()
+                    .map(|member| member.name().to_string())
+                    .collect(),
+                pending: Vec::new(),
+                current: Some(primary.name().to_string()),
+            });
+        }
+        return Ok(rollout);
+    }
+
+    let mut current = None;
+    for member in &updated {
+        if member.name() == primary.name() || topology.is_fenced(member.name()) {
+            continue;
+        }
+        if !is_healthy(manager, topology, member, rollout.revision(member)).await? {
+            current = Some(member.name().to_string());
+            break;
+        }
+    }
+
+    if current.is_none() {
+        let index = outdated
+            .iter()
+            .position(|(member, _)| member.name() != primary.name())
+            .unwrap_or(0);
+        let (member, desired) = &outdated[index];
+        let target = updated.iter().find(|replica| {
+            replica.name() != primary.name() && !topology.is_fenced(replica.name())
+        });
+
+        if member.name() == primary.name()
+            && pgopr.spec.primary_update_method.unwrap_or_default()
+                == PrimaryUpdateMethod::Switchover
+            && let Some(target) = target
+        {
+            // the former primary is updated like any replica once the switchover completed
+            if failover.switchover.is_none() {
+                match switchover::start(manager, topology, target.name()).await {
+                    Ok(started) => {
+                        info!(
+                            "Switching over to {} to update {}",
+                            target.name(),
+                            member.name()
+                        );
+                        failover.switchover = Some(started);
+                    }
+                    Err(Error::SqlError(err)) | Err(Error::UserInputError(err)) => {
+                        warn!("Switchover to {} failed: {}", target.name(), err);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        } else {
+            info!("Updating {}", member.name());
+            rollout
+                .revisions
+                .insert(member.name().to_string(), desired.clone());
+        }
+        current = Some(member.name().to_string());
+    }
+
+    rollout.status = Some(RolloutStatus {
+        updated: updated
+            .iter()
+            .map(|member| member.name().to_string())
+            .collect(),
+        pending: outdated
+            .iter()
+            .map(|(member, _)| member.name().to_string())
+            .filter(|name| current.as_ref() != Some(name))
+            .collect(),
+        current,
+    });
+
+    Ok(rollout)
+}
+
+/// The revision in the workload of a member.
+async fn member_revision(
+    manager: &ResourceManager,
+    namespace: &str,
+    name: &str,
+) -> Result<Option<Revision>, Error> {
+    let deploy_api: Api<Deployment> = Api::namespaced(manager.get_client(), namespace);
+    if let Some(deployment) = deploy_api.get_opt(name).await? {
+        return Ok(deployment
+            .spec
+            .and_then(|spec| template_revision(&spec.template)));
+    }
+
+    let sts_api: Api<StatefulSet> = Api::namespaced(manager.get_client(), namespace);
+    Ok(sts_api
+        .get_opt(name)
+        .await?
+        .and_then(|stateful_set| stateful_set.spec)
+        .and_then(|spec| template_revision(&spec.template)))
+}
+
+fn template_revision(template: &PodTemplateSpec) -> Option<Revision> {
+    let spec = template.spec.as_ref()?;
+    let image = spec.containers.first()?.image.clone()?;
+
+    Some(Revision {
+        image,
+        config_map: spec
+            .volumes
+            .iter()
+            .flatten()
+            .find(|volume| volume.name == workload::CONFIG_VOLUME)
+            .and_then(|volume| volume.config_map.as_ref())
+            .map(|config_map| config_map.name.clone()),
+        config_hash: template
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.annotations.as_ref())
+            .and_then(|annotations| annotations.get(workload::HASH_CONFIG))
+            .cloned(),
+    })
+}
+
+/// Checks that a replica runs a ready pod with its revision and has caught up with the
+/// primary.
+async fn is_healthy(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    member: &ClusterMember,
+    revision: &Revision,
+) -> Result<bool, Error> {
+    if !runs_revision(manager, topology, member, revision).await? {
+        return Ok(false);
+    }
+
+    replication::is_caught_up(
+        manager,
+        topology.namespace(),
+        topology.primary().name(),
+        member.name(),
+        topology.is_delayed(member.name()),
+    )
+    .await
+}
+
+/// Checks that a member runs a ready pod with a revision.
+async fn runs_revision(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    member: &ClusterMember,
+    revision: &Revision,
+) -> Result<bool, Error> {
+    let pod_api: Api<Pod> = Api::namespaced(manager.get_client(), topology.namespace());
+    let selector = format!("app={}", member.name());
+    let pods = pod_api
+        .list(&ListParams::default().labels(&selector))
+        .await?;
+
+    Ok(pods.iter().any(|pod| {
+        pod.metadata.deletion_timestamp.is_none()
+            && pod
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.containers.first())
+                .and_then(|container| container.image.as_ref())
+                == Some(&revision.image)
+            && pod
+                .metadata
+                .annotations
+                .as_ref()
+                .and_then(|annotations| annotations.get(workload::HASH_CONFIG))
+                == revision.config_hash.as_ref()
+            && pod
+                .status
+                .as_ref()
+                .and_then(|status| status.conditions.as_ref())
+                .is_some_and(|conditions| {
+                    conditions
+                        .iter()
+                        .any(|condition| condition.type_ == "Ready" && condition.status == "True")
+                })
+    }))
+}
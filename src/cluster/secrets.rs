@@ -0,0 +1,394 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::replication;
+use super::topology::ClusterTopology;
+use crate::Error;
+use crate::crd::v1::pgopr;
+use crate::credentials::{self, Credential};
+use crate::manager::ResourceManager;
+use crate::sql;
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::jiff::Timestamp;
+use kube::{
+    Api, ResourceExt,
+    api::{Patch, PatchParams},
+};
+use log::{debug, info, warn};
+use serde_json::json;
+
+/// Annotation on the PgOpr resource requesting an immediate credential rotation
+const ANNOTATION_ROTATE: &str = "pgopr.io/rotate-credentials";
+/// Annotation on a generated Secret recording its last rotation
+pub(super) const ANNOTATION_ROTATED_AT: &str = "pgopr.io/rotated-at";
+/// Annotation on a generated Secret whose new password is not yet applied to the database
+const ANNOTATION_ROTATION_PENDING: &str = "pgopr.io/rotation-pending";
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Creates the generated credential Secrets of the cluster.
+///
+/// Existing Secrets are left untouched so the passwords stay stable across reconciles.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `owner` - The PgOpr resource owning the Secrets.
+/// - `topology` - The expected cluster topology.
+pub(super) async fn sync(
+    manager: &ResourceManager,
+    owner: &pgopr,
+    topology: &ClusterTopology,
+) -> Result<(), Error> {
+    for credential in topology.credentials().all() {
+        if !credential.generated {
+            continue;
+        }
+
+        let secret = credentials::build_secret(
+            &credential.secret_name,
+            topology.namespace(),
+            topology.name(),
+            credential.default_username,
+            &credentials::generate_password(),
+        );
+        manager.create(owner, secret).await?;
+    }
+
+    Ok(())
+}
+
+/// Checks that every Secret referenced by the spec exists and holds the expected keys.
+///
+/// Returns a message describing the first problem found, if any.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `topology` - The expected cluster topology.
+pub(super) async fn validate(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+) -> Result<Option<String>, Error> {
+    let secret_api: Api<Secret> = Api::namespaced(manager.get_client(), topology.namespace());
+
+    for credential in topology.credentials().all() {
+        if credential.generated {
+            continue;
+        }
+
+        let Some(secret) = secret_api.get_opt(&credential.secret_name).await? else {
+            return Ok(Some(format!(
+                "Secret {} does not exist",
+                credential.secret_name
+            )));
+        };
+
+        let data = secret.data.unwrap_or_default();
+        for key in [&credential.username_key, &credential.password_key] {
+            if !data.contains_key(key) {
+                return Ok(Some(format!(
+                    "Secret {} is missing the key {}",
+                    credential.secret_name, key
+                )));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Rotates the generated passwords that are due, or all of them when requested through the
+/// `pgopr.io/rotate-credentials` annotation.
+///
+/// The new password is stored in the Secret before `ALTER ROLE` runs on the primary, and the
+/// Secret stays marked as pending until the database accepted it, so an interrupted rotation
+/// is completed on the next reconcile.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `owner` - The PgOpr resource owning the Secrets.
+/// - `topology` - The expected cluster topology.
+pub(super) async fn rotate(
+    manager: &ResourceManager,
+    owner: &pgopr,
+    topology: &ClusterTopology,
+) -> Result<(), Error> {
+    let requested = owner.annotations().contains_key(ANNOTATION_ROTATE);
+    let interval = owner
+        .spec
+        .credential_rotation
+        .as_ref()
+        .and_then(|rotation| rotation.interval_days)
+        .map(|days| i64::from(days) * SECONDS_PER_DAY);
+
+    let secret_api: Api<Secret> = Api::namespaced(manager.get_client(), topology.namespace());
+    let now = Timestamp::now();
+
+    for credential in topology.credentials().all() {
+        if !credential.generated {
+            continue;
+        }
+
+        let Some(secret) = secret_api.get_opt(&credential.secret_name).await? else {
+            continue;
+        };
+
+        let pending = secret
+            .annotations()
+            .contains_key(ANNOTATION_ROTATION_PENDING);
+        let due = interval.is_some_and(|interval| {
+            last_rotation(&secret)
+                .is_some_and(|last| now.as_second() - last.as_second() >= interval)
+        });
+        if !(pending || requested || due) {
+            continue;
+        }
+
+        match rotate_credential(manager, topology, credential, &secret, pending).await {
+            Ok(()) => {}
+            Err(Error::SqlError(err)) => {
+                warn!(
+                    "Unable to rotate {}, retrying later: {}",
+                    credential.secret_name, err
+                );
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    if requested {
+        let pgopr_api: Api<pgopr> = Api::namespaced(manager.get_client(), topology.namespace());
+        let patch = json!({
+            "metadata": {
+                "annotations": {
+                    ANNOTATION_ROTATE: null
+                }
+            }
+        });
+        pgopr_api
+            .patch(
+                topology.name(),
+                &PatchParams::default(),
+                &Patch::Merge(&patch),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Creates the pgexporter role on the primary when it is missing.
+///
+/// The images only create the application, replication and backup roles, so the role gets
+/// the password of the exporter Secret and the `pg_monitor` role here. Later password changes
+/// go through the rotation.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `topology` - The expected cluster topology.
+pub(super) async fn sync_exporter_role(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+) -> Result<(), Error> {
+    let Some((username, password)) = login(
+        manager,
+        topology.namespace(),
+        &topology.credentials().exporter,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let statement = format!(
+        "DO $$BEGIN IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = {}) THEN CREATE ROLE {} LOGIN PASSWORD {} IN ROLE pg_monitor; END IF; END$$",
+        sql::quote_literal(&username),
+        sql::quote_ident(&username),
+        sql::quote_literal(&password)
+    );
+    let primary = topology.primary();
+    match sql::execute(
+        manager.get_client(),
+        topology.namespace(),
+        primary.name(),
+        &[&statement],
+    )
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(Error::SqlError(err)) => {
+            debug!(
+                "Unable to create role {} on {}: {}",
+                username,
+                primary.name(),
+                err
+            );
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the time of the last rotation of a credential, if it was ever rotated.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `namespace` - Namespace of the Secret.
+/// - `credential` - The credential to inspect.
+pub(super) async fn rotated_at(
+    manager: &ResourceManager,
+    namespace: &str,
+    credential: &Credential,
+) -> Result<Option<String>, Error> {
+    let secret_api: Api<Secret> = Api::namespaced(manager.get_client(), namespace);
+    Ok(secret_api
+        .get_opt(&credential.secret_name)
+        .await?
+        .and_then(|secret| secret.annotations().get(ANNOTATION_ROTATED_AT).cloned()))
+}
+
+/// Reads the username and password of a credential, if its Secret exists.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `namespace` - Namespace of the Secret.
+/// - `credential` - The credential to read.
+pub(super) async fn login(
+    manager: &ResourceManager,
+    namespace: &str,
+    credential: &Credential,
+) -> Result<Option<(String, String)>, Error> {
+    let secret_api: Api<Secret> = Api::namespaced(manager.get_client(), namespace);
+    Ok(secret_api
+        .get_opt(&credential.secret_name)
+        .await?
+        .and_then(|secret| {
+            Some((
+                value(&secret, &credential.username_key)?,
+                value(&secret, &credential.password_key)?,
+            ))
+        }))
+}
+
+/// Reads a value of a credential Secret.
+///
+/// # Arguments
+/// - `secret` - The Secret holding the credential.
+/// - `key` - The key to read.
+pub(super) fn value(secret: &Secret, key: &str) -> Option<String> {
+    secret
+        .data
+        .as_ref()
+        .and_then(|data| data.get(key))
+        .and_then(|bytes| String::from_utf8(bytes.0.clone()).ok())
+}
+
+async fn rotate_credential(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    credential: &Credential,
+    secret: &Secret,
+    pending: bool,
+) -> Result<(), Error> {
+    let secret_api: Api<Secret> = Api::namespaced(manager.get_client(), topology.namespace());
+    let username = value(secret, &credential.username_key)
+        .unwrap_or_else(|| credential.default_username.to_string());
+
+    let password = match value(secret, &credential.password_key) {
+        Some(password) if pending => password,
+        _ => {
+            let password = credentials::generate_password();
+            let patch = json!({
+                "metadata": {
+                    "annotations": {
+                        ANNOTATION_ROTATION_PENDING: "true"
+                    }
+                },
+                "stringData": {
+                    credential.password_key.as_str(): password
+                }
+            });
+            secret_api
+                .patch(
+                    &credential.secret_name,
+                    &PatchParams::default(),
+                    &Patch::Merge(&patch),
+                )
+                .await?;
+            password
+        }
+    };
+
+    let statement = format!(
+        "DO $$BEGIN IF EXISTS (SELECT FROM pg_roles WHERE rolname = {}) THEN ALTER ROLE {} PASSWORD {}; END IF; END$$",
+        sql::quote_literal(&username),
+        sql::quote_ident(&username),
+        sql::quote_literal(&password)
+    );
+    sql::execute(
+        manager.get_client(),
+        topology.namespace(),
+        topology.primary().name(),
+        &[&statement],
+    )
+    .await?;
+
+    if credential.secret_name == topology.credentials().replication.secret_name {
+        for member in topology.replica_members() {
+            if let Err(err) = replication::follow(
+                manager,
+                topology.namespace(),
+                &member,
+                topology.upstream(&member).name(),
+                &username,
+                &password,
+            )
+            .await
+            {
+                warn!(
+                    "Unable to update the replication password on {}: {}",
+                    member.name(),
+                    err
+                );
+            }
+        }
+    }
+
+    let patch = json!({
+        "metadata": {
+            "annotations": {
+                ANNOTATION_ROTATION_PENDING: null,
+                ANNOTATION_ROTATED_AT: Timestamp::now().to_string()
+            }
+        }
+    });
+    secret_api
+        .patch(
+            &credential.secret_name,
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .await?;
+
+    info!("Rotated the password of role {}", username);
+    Ok(())
+}
+
+fn last_rotation(secret: &Secret) -> Option<Timestamp> {
+    secret
+        .annotations()
+        .get(ANNOTATION_ROTATED_AT)
+        .and_then(|rotated_at| rotated_at.parse::<Timestamp>().ok())
+        .or_else(|| {
+            secret
+                .metadata
+                .creation_timestamp
+                .as_ref()
+                .map(|created| created.0)
+        })
+}
@@ -0,0 +1,273 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::replication;
+use super::topology::{ClusterMember, ClusterTopology};
+use crate::Error;
+use crate::crd::v1::{PgOprSpec, ReplicationSlotStatus, pgopr};
+use crate::manager::ResourceManager;
+use crate::{quantity, sql};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+/// Retained WAL of an inactive slot raising a warning without `spec.slotWalWarning`
+const DEFAULT_WAL_WARNING: u64 = 1024 * 1024 * 1024;
+
+/// The physical replication slots of a server with the WAL they retain, a replica retains
+/// the WAL it replayed
+const SLOTS_QUERY: &str = "SELECT coalesce(json_agg(json_build_object(\
+    'name', slot_name, \
+    'active', active, \
+    'retained_bytes', pg_wal_lsn_diff(CASE WHEN pg_is_in_recovery() \
+    THEN pg_last_wal_replay_lsn() ELSE pg_current_wal_lsn() END, restart_lsn)::bigint) \
+    ORDER BY slot_name), '[]') \
+    FROM pg_replication_slots WHERE slot_type = 'physical'";
+
+/// A physical replication slot of a server
+#[derive(Deserialize)]
+struct Slot {
+    name: String,
+    active: bool,
+    retained_bytes: Option<i64>,
+}
+
+/// The replication slots of the cluster after a sync
+pub(super) struct SyncedSlots {
+    /// The physical slots of the primary, nothing while the primary is not running
+    pub(super) primary: Option<Vec<ReplicationSlotStatus>>,
+    /// The slots of the members the operator manages, recorded in the status
+    pub(super) managed: Vec<String>,
+}
+
+/// Checks the slot settings of the spec.
+///
+/// # Arguments
+/// - `spec` - The spec of the cluster.
+pub(super) fn validate(spec: &PgOprSpec) -> Option<String> {
+    spec.slot_wal_warning
+        .as_deref()
+        .filter(|warning| quantity::bytes(warning).is_none())
+        .map(|warning| format!("slotWalWarning '{}' is not a quantity", warning))
+}
+
+/// Retained WAL of an inactive slot that raises a warning, in bytes
+///
+/// # Arguments
+/// - `spec` - The spec of the cluster.
+pub(super) fn wal_warning(spec: &PgOprSpec) -> u64 {
+    spec.slot_wal_warning
+        .as_deref()
+        .and_then(quantity::bytes)
+        .unwrap_or(DEFAULT_WAL_WARNING)
+}
+
+/// Creates the slots of the replicas on their upstream and drops those of removed members.
+///
+/// The primary and each replica another replica streams from hold the slots of the
+/// replicas streaming from them. A missing slot is only created once the server of its
+/// replica runs, the image creates it while cloning a new replica. The slots of the members
+/// are recorded in `status.managed_slots`, and only recorded slots that are inactive and no
+/// member streams through from the server are dropped, other slots are left alone. A slot
+/// stays recorded until no server holds it anymore.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource recording the managed slots.
+/// - `topology` - The expected cluster topology.
+pub(super) async fn sync(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+) -> Result<SyncedSlots, Error> {
+    let primary = topology.primary();
+    let replicas: Vec<ClusterMember> = topology
+        .replica_members()
+        .into_iter()
+        .filter(|member| !topology.is_fenced(member.name()))
+        .collect();
+    let member_slots: BTreeSet<String> = topology
+        .members()
+        .iter()
+        .map(|member| member.slot_name().to_string())
+        .collect();
+    let recorded: BTreeSet<String> = pgopr
+        .status
+        .iter()
+        .flat_map(|status| status.managed_slots.iter().cloned())
+        .collect();
+    let managed: BTreeSet<String> = member_slots.union(&recorded).cloned().collect();
+
+    let mut held = BTreeSet::new();
+    let mut complete = true;
+    for server in &replicas {
+        if !replicas
+            .iter()
+            .any(|member| topology.upstream(member).name() == server.name())
+        {
+            continue;
+        }
+        match sync_server(manager, topology, server, &replicas, &managed).await {
+            Ok(slots) => held.extend(slots.into_iter().map(|slot| slot.name)),
+            Err(Error::SqlError(err)) => {
+                debug!("Unable to sync the slots of {}: {}", server.name(), err);
+                complete = false;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    let slots = match sync_server(manager, topology, &primary, &replicas, &managed).await {
+        Ok(slots) => Some(slots),
+        Err(Error::SqlError(err)) => {
+            debug!("Unable to read the slots of {}: {}", primary.name(), err);
+            complete = false;
+            None
+        }
+        Err(err) => return Err(err),
+    };
+    held.extend(slots.iter().flatten().map(|slot| slot.name.clone()));
+
+    // a slot on a server that could not be read stays recorded
+    let managed = managed
+        .into_iter()
+        .filter(|name| member_slots.contains(name) || !complete || held.contains(name))
+        .collect();
+
+    Ok(SyncedSlots {
+        primary: slots.map(|slots| {
+            slots
+                .into_iter()
+                .map(|slot| ReplicationSlotStatus {
+                    name: slot.name,
+                    active: slot.active,
+                    retained_bytes: slot.retained_bytes,
+                })
+                .collect()
+        }),
+        managed,
+    })
+}
+
+/// Syncs the slots of the replicas streaming from one server and returns its slots.
+async fn sync_server(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    server: &ClusterMember,
+    replicas: &[ClusterMember],
+    managed: &BTreeSet<String>,
+) -> Result<Vec<Slot>, Error> {
+    let namespace = topology.namespace();
+    let mut slots = query(manager, namespace, server.name()).await?;
+    let downstream: Vec<&ClusterMember> = replicas
+        .iter()
+        .filter(|member| topology.upstream(member).name() == server.name())
+        .collect();
+    let mut changed = false;
+
+    for member in &downstream {
+        if slots.iter().any(|slot| slot.name == member.slot_name())
+            || !is_running(manager, namespace, member.name()).await?
+        {
+            continue;
+        }
+
+        info!(
+            "Creating replication slot {} for {} on {}",
+            member.slot_name(),
+            member.name(),
+            server.name()
+        );
+        match replication::create_slot(manager, namespace, server.name(), member.slot_name()).await
+        {
+            Ok(()) => changed = true,
+            Err(Error::SqlError(err)) => {
+                warn!("Unable to create slot {}: {}", member.slot_name(), err)
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    for slot in &slots {
+        if slot.active
+            || !managed.contains(&slot.name)
+            || downstream
+                .iter()
+                .any(|member| member.slot_name() == slot.name)
+        {
+            continue;
+        }
+
+        info!(
+            "Dropping replication slot {} on {}, no member streams through it",
+            slot.name,
+            server.name()
+        );
+        match drop_slot(manager, namespace, server.name(), &slot.name).await {
+            Ok(()) => changed = true,
+            Err(Error::SqlError(err)) => warn!("Unable to drop slot {}: {}", slot.name, err),
+            Err(err) => return Err(err),
+        }
+    }
+
+    if changed {
+        slots = query(manager, namespace, server.name()).await?;
+    }
+
+    Ok(slots)
+}
+
+async fn query(
+    manager: &ResourceManager,
+    namespace: &str,
+    server: &str,
+) -> Result<Vec<Slot>, Error> {
+    let output = sql::execute(manager.get_client(), namespace, server, &[SLOTS_QUERY]).await?;
+    serde_json::from_str(&output)
+        .map_err(|err| Error::SqlError(format!("Unexpected slots of {}: {}", server, err)))
+}
+
+/// Checks whether the server of a replica accepts queries, so it finished cloning.
+async fn is_running(
+    manager: &ResourceManager,
+    namespace: &str,
+    member: &str,
+) -> Result<bool, Error> {
+    match sql::execute(
+        manager.get_client(),
+        namespace,
+        member,
+        &["SELECT pg_is_in_recovery()"],
+    )
+    .await
+    {
+        Ok(output) => Ok(output == "t"),
+        Err(Error::SqlError(_)) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+async fn drop_slot(
+    manager: &ResourceManager,
+    namespace: &str,
+    server: &str,
+    slot_name: &str,
+) -> Result<(), Error> {
+    sql::execute(
+        manager.get_client(),
+        namespace,
+        server,
+        &[&format!(
+            "SELECT pg_drop_replication_slot(slot_name) FROM pg_replication_slots WHERE slot_name = {} AND NOT active",
+            sql::quote_literal(slot_name)
+        )],
+    )
+    .await?;
+
+    Ok(())
+}
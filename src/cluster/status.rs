@@ -6,18 +6,23 @@
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
 
+use super::secrets;
 use super::topology::ClusterTopology;
 use crate::Error;
 use crate::crd::v1::PgExporterStatus;
 use crate::crd::v1::{
-    DeploymentStatus, PgMonetaStatus, PgOprStatus, ServiceStatus, StorageStatus, pgopr,
+    ConfigStatus, CredentialStatus, DeploymentStatus, EndpointStatus, FailoverStatus,
+    PgMonetaStatus, PgOprStatus, ReplicationSlotStatus, ReplicationStatus, RolloutStatus,
+    ServiceStatus, StorageStatus, UpgradeStatus, pgopr,
 };
 use crate::manager::ResourceManager;
-use k8s_openapi::api::apps::v1::Deployment;
-use k8s_openapi::api::core::v1::{PersistentVolume, PersistentVolumeClaim, Pod, Service};
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::{PersistentVolume, PersistentVolumeClaim, Pod, Secret, Service};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, Time};
+use k8s_openapi::jiff::Timestamp;
 use kube::Resource;
 use kube::{Api, ResourceExt, api::ListParams};
+use log::warn;
 use std::collections::BTreeMap;
 
 // Kubernetes resource phase values
@@ -26,18 +31,32 @@ const PHASE_PENDING: &str = "Pending";
 const PHASE_RUNNING: &str = "Running";
 const PHASE_DEGRADED: &str = "Degraded";
 const PHASE_FAILED: &str = "Failed";
+const PHASE_UPGRADING: &str = "Upgrading";
+const PHASE_UPGRADE_FAILED: &str = "UpgradeFailed";
 
 // Kubernetes condition type
 const CONDITION_READY: &str = "Ready";
+const CONDITION_PENDING_RESTART: &str = "PendingRestart";
+const CONDITION_SLOTS_RETAINING_WAL: &str = "SlotsRetainingWal";
+const CONDITION_SYNCHRONOUS_DEGRADED: &str = "SynchronousReplicationDegraded";
 const CONDITION_STATUS_TRUE: &str = "True";
 const CONDITION_STATUS_FALSE: &str = "False";
 
+// PersistentVolumeClaim conditions of a volume expansion
+const RESIZE_CONDITIONS: [&str; 2] = ["Resizing", "FileSystemResizePending"];
+
 // Kubernetes reason
 const REASON_POD_FAILURE: &str = "PodFailure";
 const REASON_CLUSTER_READY: &str = "ClusterReady";
 const REASON_REPLICAS_NOT_READY: &str = "ReplicasNotReady";
 const REASON_PRIMARY_NOT_READY: &str = "PrimaryNotReady";
 const REASON_INVALID_SPEC: &str = "InvalidSpec";
+const REASON_INVALID_SECRET: &str = "InvalidSecret";
+const REASON_UPGRADING: &str = "Upgrading";
+const REASON_UPGRADE_FAILED: &str = "UpgradeFailed";
+const REASON_PARAMETERS_CHANGED: &str = "ParametersChanged";
+const REASON_INACTIVE_SLOTS: &str = "InactiveSlots";
+const REASON_STANDBYS_MISSING: &str = "StandbysMissing";
 
 /// Builds status for a PgOpr resource whose spec cannot be reconciled.
 ///
@@ -45,15 +64,109 @@ const REASON_INVALID_SPEC: &str = "InvalidSpec";
 /// - `pgopr` - The PgOpr resource defining the invalid desired state.
 /// - `message` - The reason the spec cannot be reconciled.
 pub(super) fn invalid_spec(pgopr: &pgopr, message: String) -> PgOprStatus {
+    failed(pgopr, REASON_INVALID_SPEC, message)
+}
+
+/// Builds status for a PgOpr resource referencing a missing or incomplete Secret.
+///
+/// # Arguments
+/// - `pgopr` - The PgOpr resource referencing the Secret.
+/// - `message` - The problem found with the Secret.
+pub(super) fn invalid_secret(pgopr: &pgopr, message: String) -> PgOprStatus {
+    failed(pgopr, REASON_INVALID_SECRET, message)
+}
+
+/// Builds status for a cluster in the middle of a major version upgrade.
+///
+/// # Arguments
+/// - `pgopr` - The PgOpr resource being upgraded.
+/// - `upgrade` - The upgrade in progress.
+/// - `message` - The current step of the upgrade.
+pub(super) fn upgrading(pgopr: &pgopr, upgrade: UpgradeStatus, message: String) -> PgOprStatus {
+    PgOprStatus {
+        phase: PHASE_UPGRADING.to_string(),
+        upgrade: Some(upgrade),
+        conditions: Some(vec![condition(
+            pgopr,
+            CONDITION_READY,
+            CONDITION_STATUS_FALSE,
+            REASON_UPGRADING,
+            message,
+        )]),
+        ..carry_over(pgopr)
+    }
+}
+
+/// Builds status for a cluster whose major version upgrade failed.
+///
+/// # Arguments
+/// - `pgopr` - The PgOpr resource being upgraded.
+/// - `upgrade` - The failed upgrade.
+/// - `message` - The reason the upgrade failed.
+pub(super) fn upgrade_failed(
+    pgopr: &pgopr,
+    upgrade: UpgradeStatus,
+    message: String,
+) -> PgOprStatus {
+    PgOprStatus {
+        phase: PHASE_UPGRADE_FAILED.to_string(),
+        upgrade: Some(upgrade),
+        conditions: Some(vec![condition(
+            pgopr,
+            CONDITION_READY,
+            CONDITION_STATUS_FALSE,
+            REASON_UPGRADE_FAILED,
+            message,
+        )]),
+        ..carry_over(pgopr)
+    }
+}
+
+/// Builds status recording a promotion before the rest of the cluster is reconciled.
+///
+/// Everything else is kept from the previous status, so the new primary is persisted even
+/// if the reconcile fails afterwards.
+///
+/// # Arguments
+/// - `pgopr` - The PgOpr resource defining the cluster.
+/// - `topology` - The cluster topology with the promoted primary.
+/// - `failover` - The failover state after the promotion.
+pub(super) fn promoted(
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+    failover: FailoverStatus,
+) -> PgOprStatus {
+    let mut status = pgopr.status.clone().unwrap_or_default();
+    status.current_primary = Some(topology.primary().name().to_string());
+    status.failover = Some(failover);
+    status
+}
+
+fn failed(pgopr: &pgopr, reason: &str, message: String) -> PgOprStatus {
     PgOprStatus {
         phase: PHASE_FAILED.to_string(),
         conditions: Some(vec![condition(
             pgopr,
             CONDITION_READY,
             CONDITION_STATUS_FALSE,
-            REASON_INVALID_SPEC,
+            reason,
             message,
         )]),
+        ..carry_over(pgopr)
+    }
+}
+
+/// Keeps the status fields that cannot be observed from the cluster resources.
+fn carry_over(pgopr: &pgopr) -> PgOprStatus {
+    let status = pgopr.status.as_ref();
+    PgOprStatus {
+        current_primary: status.and_then(|status| status.current_primary.clone()),
+        failover: status.and_then(|status| status.failover.clone()),
+        version: status.and_then(|status| status.version.clone()),
+        upgrade: status.and_then(|status| status.upgrade.clone()),
+        managed_slots: status
+            .map(|status| status.managed_slots.clone())
+            .unwrap_or_default(),
         ..Default::default()
     }
 }
@@ -64,13 +177,25 @@ pub(super) fn invalid_spec(pgopr: &pgopr, message: String) -> PgOprStatus {
 /// - `manager` - The Kubernetes resource manager.
 /// - `topology` - The expected cluster topology.
 /// - `pgopr` - The PgOpr resource defining the cluster identity and desired state.
+/// - `version` - The major version the cluster runs.
+/// - `rollout` - The rolling update of the member images in progress.
+/// - `failover` - The failover state of the cluster.
+/// - `config` - The configuration of the spec and the one each member runs.
 pub(super) async fn observe(
     manager: &ResourceManager,
     topology: &ClusterTopology,
     pgopr: &pgopr,
+    version: &str,
+    rollout: Option<RolloutStatus>,
+    failover: FailoverStatus,
+    config: ConfigStatus,
 ) -> Result<PgOprStatus, Error> {
     let mut status = PgOprStatus {
         phase: PHASE_PENDING.to_string(),
+        version: Some(version.to_string()),
+        rollout,
+        current_primary: Some(topology.primary().name().to_string()),
+        failover: Some(failover).filter(|failover| *failover != FailoverStatus::default()),
         ..Default::default()
     };
 
@@ -79,7 +204,10 @@ pub(super) async fn observe(
     observe_storage(manager, topology, &mut status).await?;
     observe_pgmoneta(manager, topology, pgopr, &mut status).await?;
     observe_pgexporter(manager, topology, pgopr, &mut status).await?;
+    observe_credentials(manager, topology, &mut status).await?;
     finalize(pgopr, topology, &mut status);
+    report_pending_restart(pgopr, &config, &mut status);
+    status.config = Some(config).filter(|config| config.config_map.is_some());
 
     Ok(status)
 }
@@ -89,25 +217,57 @@ async fn observe_deployments(
     topology: &ClusterTopology,
     status: &mut PgOprStatus,
 ) -> Result<(), Error> {
-    let deploy_api: Api<Deployment> = Api::namespaced(manager.get_client(), topology.namespace());
-
-    if let Ok(primary) = deploy_api.get(topology.name()).await {
-        let reason = pod_failure_reason(manager, topology.namespace(), topology.name()).await?;
-        status.primary = Some(deployment_status(topology.name(), &primary, reason));
+    let member = topology.primary();
+    if let Some(ready) = member_ready_replicas(manager, topology.namespace(), member.name()).await?
+    {
+        let reason = pod_failure_reason(manager, topology.namespace(), member.name()).await?;
+        status.primary = Some(member_status(member.name(), ready, reason));
     }
 
     for member in topology.replica_members() {
-        if let Ok(replica) = deploy_api.get(member.name()).await {
+        if let Some(ready) =
+            member_ready_replicas(manager, topology.namespace(), member.name()).await?
+        {
             let reason = pod_failure_reason(manager, topology.namespace(), member.name()).await?;
             status
                 .replicas
-                .push(deployment_status(member.name(), &replica, reason));
+                .push(member_status(member.name(), ready, reason));
         }
     }
 
     Ok(())
 }
 
+/// Ready pods of a member, read from its Deployment or StatefulSet.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `namespace` - The namespace of the member.
+/// - `name` - Name of the member.
+pub(super) async fn member_ready_replicas(
+    manager: &ResourceManager,
+    namespace: &str,
+    name: &str,
+) -> Result<Option<u32>, Error> {
+    let deploy_api: Api<Deployment> = Api::namespaced(manager.get_client(), namespace);
+    if let Some(deployment) = deploy_api.get_opt(name).await? {
+        return Ok(Some(
+            deployment
+                .status
+                .and_then(|s| s.ready_replicas)
+                .unwrap_or(0) as u32,
+        ));
+    }
+
+    let sts_api: Api<StatefulSet> = Api::namespaced(manager.get_client(), namespace);
+    Ok(sts_api.get_opt(name).await?.map(|stateful_set| {
+        stateful_set
+            .status
+            .and_then(|s| s.ready_replicas)
+            .unwrap_or(0) as u32
+    }))
+}
+
 async fn observe_services(
     manager: &ResourceManager,
     topology: &ClusterTopology,
@@ -120,12 +280,23 @@ async fn observe_services(
         .map(|service| (service.name_any(), service))
         .collect();
 
-    for name in topology.member_names() {
+    let role_names = [
+        topology.read_write_name(),
+        topology.read_only_name(),
+        topology.read_name(),
+    ];
+    for name in topology.member_names().into_iter().chain(role_names) {
         if let Some(service) = services_by_name.get(&name) {
             status.services.push(service_status(service));
         }
     }
 
+    status.endpoints = Some(EndpointStatus {
+        read_write: topology.read_write_name(),
+        read_only: topology.read_only_name(),
+        read: topology.read_name(),
+    });
+
     Ok(())
 }
 
@@ -290,6 +461,32 @@ async fn observe_pgexporter(
 
     Ok(())
 }
+async fn observe_credentials(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    status: &mut PgOprStatus,
+) -> Result<(), Error> {
+    let secret_api: Api<Secret> = Api::namespaced(manager.get_client(), topology.namespace());
+
+    for credential in topology.credentials().all() {
+        let secret = secret_api.get_opt(&credential.secret_name).await?;
+        status.credentials.push(CredentialStatus {
+            secret: credential.secret_name.clone(),
+            role: secret
+                .as_ref()
+                .and_then(|secret| secrets::value(secret, &credential.username_key)),
+            generated: credential.generated,
+            last_rotation: secret
+                .as_ref()
+                .and_then(|secret| secret.annotations().get(secrets::ANNOTATION_ROTATED_AT))
+                .and_then(|rotated_at| rotated_at.parse::<Timestamp>().ok())
+                .map(Time),
+        });
+    }
+
+    Ok(())
+}
+
 fn finalize(pgopr: &pgopr, topology: &ClusterTopology, status: &mut PgOprStatus) {
     let primary_ready = status.primary.as_ref().is_some_and(|p| p.available);
     let replicas_ready = status.replicas.iter().filter(|r| r.available).count() as u32;
@@ -303,7 +500,7 @@ fn finalize(pgopr: &pgopr, topology: &ClusterTopology, status: &mut PgOprStatus)
             REASON_POD_FAILURE,
             format!("A PostgreSQL pod is not ready: {}", reason),
         )]);
-    } else if primary_ready && replicas_ready == topology.replicas() {
+    } else if primary_ready && replicas_ready as usize == topology.replica_members().len() {
         status.phase = PHASE_RUNNING.to_string();
         status.conditions = Some(vec![condition(
             pgopr,
@@ -332,6 +529,127 @@ fn finalize(pgopr: &pgopr, topology: &ClusterTopology, status: &mut PgOprStatus)
     }
 }
 
+fn report_pending_restart(pgopr: &pgopr, config: &ConfigStatus, status: &mut PgOprStatus) {
+    let members: Vec<String> = config
+        .members
+        .iter()
+        .filter(|member| member.pending_restart)
+        .map(|member| {
+            if member.pending_parameters.is_empty() {
+                member.name.clone()
+            } else {
+                format!("{} ({})", member.name, member.pending_parameters.join(", "))
+            }
+        })
+        .collect();
+    if members.is_empty() {
+        return;
+    }
+
+    status
+        .conditions
+        .get_or_insert_with(Vec::new)
+        .push(condition(
+            pgopr,
+            CONDITION_PENDING_RESTART,
+            CONDITION_STATUS_TRUE,
+            REASON_PARAMETERS_CHANGED,
+            format!("Members waiting for a restart: {}", members.join(", ")),
+        ));
+}
+
+/// Adds the replication slots of the primary to the status.
+///
+/// Inactive slots retaining more WAL than the warning threshold raise the
+/// `SlotsRetainingWal` condition.
+///
+/// # Arguments
+/// - `pgopr` - The PgOpr resource defining the cluster.
+/// - `slots` - The physical replication slots of the primary.
+/// - `wal_warning` - Retained WAL of an inactive slot raising the condition, in bytes.
+/// - `status` - The status to report into.
+pub(super) fn report_slots(
+    pgopr: &pgopr,
+    slots: Vec<ReplicationSlotStatus>,
+    wal_warning: u64,
+    status: &mut PgOprStatus,
+) {
+    let retaining: Vec<String> = slots
+        .iter()
+        .filter(|slot| !slot.active)
+        .filter_map(|slot| {
+            let retained = u64::try_from(slot.retained_bytes?).ok()?;
+            (retained > wal_warning)
+                .then(|| format!("{} ({} MB)", slot.name, retained / (1024 * 1024)))
+        })
+        .collect();
+    status.replication_slots = slots;
+
+    if retaining.is_empty() {
+        return;
+    }
+    warn!(
+        "Inactive replication slots of {} retain WAL: {}",
+        pgopr.name_any(),
+        retaining.join(", ")
+    );
+    status
+        .conditions
+        .get_or_insert_with(Vec::new)
+        .push(condition(
+            pgopr,
+            CONDITION_SLOTS_RETAINING_WAL,
+            CONDITION_STATUS_TRUE,
+            REASON_INACTIVE_SLOTS,
+            format!(
+                "Inactive replication slots retaining WAL: {}",
+                retaining.join(", ")
+            ),
+        ));
+}
+
+/// Adds the synchronous replication of the primary to the status.
+///
+/// Fewer synchronous standbys than the spec asks for raise the
+/// `SynchronousReplicationDegraded` condition.
+///
+/// # Arguments
+/// - `pgopr` - The PgOpr resource defining the cluster.
+/// - `replication` - The synchronous replication of the primary.
+/// - `status` - The status to report into.
+pub(super) fn report_replication(
+    pgopr: &pgopr,
+    replication: ReplicationStatus,
+    status: &mut PgOprStatus,
+) {
+    let missing = replication.required as usize > replication.synchronous.len();
+    let message = format!(
+        "{} of {} synchronous standbys confirm commits",
+        replication.synchronous.len(),
+        replication.required
+    );
+    status.replication = Some(replication).filter(|replication| replication.required > 0);
+
+    if !missing {
+        return;
+    }
+    warn!(
+        "Synchronous replication of {}: {}",
+        pgopr.name_any(),
+        message
+    );
+    status
+        .conditions
+        .get_or_insert_with(Vec::new)
+        .push(condition(
+            pgopr,
+            CONDITION_SYNCHRONOUS_DEGRADED,
+            CONDITION_STATUS_TRUE,
+            REASON_STANDBYS_MISSING,
+            message,
+        ));
+}
+
 async fn pod_failure_reason(
     manager: &ResourceManager,
     namespace: &str,
@@ -389,6 +707,10 @@ fn deployment_status(
         .and_then(|s| s.ready_replicas)
         .unwrap_or(0) as u32;
 
+    member_status(name, ready_replicas, reason)
+}
+
+fn member_status(name: &str, ready_replicas: u32, reason: Option<String>) -> DeploymentStatus {
     DeploymentStatus {
         name: name.to_string(),
         ready_replicas,
@@ -408,6 +730,18 @@ fn service_status(service: &Service) -> ServiceStatus {
 }
 
 fn pvc_status(pvc: &PersistentVolumeClaim) -> StorageStatus {
+    let resize = pvc
+        .status
+        .as_ref()
+        .and_then(|s| s.conditions.as_ref())
+        .and_then(|conditions| {
+            conditions.iter().find(|condition| {
+                RESIZE_CONDITIONS.contains(&condition.type_.as_str())
+                    && condition.status == CONDITION_STATUS_TRUE
+            })
+        })
+        .map(|condition| condition.type_.clone());
+
     StorageStatus {
         name: pvc.name_any(),
         kind: PersistentVolumeClaim::kind(&()).to_string(),
@@ -415,6 +749,20 @@ fn pvc_status(pvc: &PersistentVolumeClaim) -> StorageStatus {
             .status
             .as_ref()
             .is_some_and(|s| s.phase.as_ref().is_some_and(|p| p == PHASE_BOUND)),
+        requested: pvc
+            .spec
+            .as_ref()
+            .and_then(|s| s.resources.as_ref())
+            .and_then(|r| r.requests.as_ref())
+            .and_then(|requests| requests.get("storage"))
+            .map(|quantity| quantity.0.clone()),
+        capacity: pvc
+            .status
+            .as_ref()
+            .and_then(|s| s.capacity.as_ref())
+            .and_then(|capacity| capacity.get("storage"))
+            .map(|quantity| quantity.0.clone()),
+        resize,
     }
 }
 
@@ -422,6 +770,14 @@ fn pv_status(pv: PersistentVolume) -> StorageStatus {
     StorageStatus {
         name: pv.name_any(),
         kind: PersistentVolume::kind(&()).to_string(),
+        requested: None,
+        capacity: pv
+            .spec
+            .as_ref()
+            .and_then(|s| s.capacity.as_ref())
+            .and_then(|capacity| capacity.get("storage"))
+            .map(|quantity| quantity.0.clone()),
+        resize: None,
         bound: pv
             .status
             .is_some_and(|s| s.phase.is_some_and(|p| p == PHASE_BOUND)),
@@ -0,0 +1,410 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::topology::{ClusterMember, ClusterTopology, DataClaim, MemberClaims, MemberVolume};
+use crate::Error;
+use crate::crd::v1::{PgMonetaSpec, WorkloadKind, pgopr};
+use crate::manager::ResourceManager;
+use crate::persistent::{self, StorageConfig};
+use k8s_openapi::api::apps::v1::StatefulSet;
+use k8s_openapi::api::core::v1::{ObjectReference, PersistentVolume, PersistentVolumeClaim};
+use k8s_openapi::api::storage::v1::StorageClass;
+use kube::{
+    Api, ResourceExt,
+    api::{Patch, PatchParams},
+};
+use log::info;
+use serde_json::json;
+
+const DEFAULT_PGMONETA_STORAGE_GI: u32 = 10;
+const GIB: u64 = 1 << 30;
+
+/// Checks that the claims can take the requested sizes.
+///
+/// Claims can only grow, and outside local mode only when their storage class allows
+/// volume expansion. Returns the problem found, if any.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource requesting the sizes.
+/// - `topology` - The expected cluster topology.
+pub(super) async fn validate(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+) -> Result<Option<String>, Error> {
+    let pvc_api: Api<PersistentVolumeClaim> =
+        Api::namespaced(manager.get_client(), topology.namespace());
+
+    let mut claims: Vec<(String, u32)> = Vec::new();
+    for member in topology.members() {
+        let data = member.data_volume();
+        claims.push((data.pvc_name(), topology.storage().size));
+        claims.push((data.template_pvc_name(), topology.storage().size));
+        if let Some(storage) = topology.wal_storage() {
+            let wal = member.wal_volume();
+            claims.push((wal.pvc_name(), storage.size));
+            claims.push((wal.template_pvc_name(), storage.size));
+        }
+    }
+    if let Some(spec) = &pgopr.spec.pgmoneta {
+        claims.push((topology.pgmoneta_pvc_name(), pgmoneta_size(spec)));
+    }
+
+    for (name, size) in claims {
+        let Some(claim) = pvc_api.get_opt(&name).await? else {
+            continue;
+        };
+        let Some(requested) = requested_bytes(&claim) else {
+            continue;
+        };
+
+        let desired = u64::from(size) * GIB;
+        if desired < requested {
+            return Ok(Some(format!(
+                "{} cannot shrink to {}Gi, volume claims can only grow",
+                name, size
+            )));
+        }
+        if desired > requested && !topology.is_local() && !expandable(manager, &claim).await? {
+            return Ok(Some(format!(
+                "The storage class of {} does not allow volume expansion",
+                name
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Syncs the storage of a member and returns the claims backing its volumes.
+///
+/// Existing claims are kept when the workload kind changes, so a member never loses its
+/// data. New members of a StatefulSet get their claims from the volume claim templates.
+/// Volumes are only created in local mode, otherwise the storage class provisions them.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource owning the claims.
+/// - `topology` - The expected cluster topology.
+/// - `member` - The member to sync the storage of.
+pub(super) async fn sync_member(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+    member: &ClusterMember,
+) -> Result<MemberClaims, Error> {
+    let templates = existing_templates(manager, topology, member).await?;
+
+    let data = sync_volume(
+        manager,
+        pgopr,
+        topology,
+        &member.data_volume(),
+        topology.storage(),
+        templates.as_deref(),
+    )
+    .await?;
+    let wal = match topology.wal_storage() {
+        Some(storage) => Some(
+            sync_volume(
+                manager,
+                pgopr,
+                topology,
+                &member.wal_volume(),
+                storage,
+                templates.as_deref(),
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
+    Ok(MemberClaims { data, wal })
+}
+
+/// The volume claim templates of a member StatefulSet.
+///
+/// The templates of an existing StatefulSet are kept, as they cannot change.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `topology` - The expected cluster topology.
+/// - `member` - The member running in the StatefulSet.
+/// - `claims` - The claims backing the volumes of the member.
+pub(super) async fn claim_templates(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    member: &ClusterMember,
+    claims: &MemberClaims,
+) -> Result<Vec<PersistentVolumeClaim>, Error> {
+    if let Some(templates) = existing_templates(manager, topology, member).await? {
+        return Ok(templates);
+    }
+
+    let mut templates = Vec::new();
+    if let DataClaim::Template(_) = claims.data {
+        templates.push(claim_template(
+            topology,
+            &member.data_volume(),
+            topology.storage(),
+        ));
+    }
+    if let (Some(DataClaim::Template(_)), Some(storage)) = (&claims.wal, topology.wal_storage()) {
+        templates.push(claim_template(topology, &member.wal_volume(), storage));
+    }
+
+    Ok(templates)
+}
+
+/// Syncs the backup volume of pgmoneta.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource owning the claim.
+/// - `topology` - The expected cluster topology.
+/// - `spec` - The pgmoneta configuration.
+pub(super) async fn sync_pgmoneta(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+    spec: &PgMonetaSpec,
+) -> Result<(), Error> {
+    let pvc_api: Api<PersistentVolumeClaim> =
+        Api::namespaced(manager.get_client(), topology.namespace());
+    let existing = pvc_api.get_opt(&topology.pgmoneta_pvc_name()).await?;
+    let storage = StorageConfig {
+        size: pgmoneta_size(spec),
+        ..topology.storage().clone()
+    }
+    .for_claim(existing.as_ref());
+
+    if let Some(host_path) = topology.host_path(&topology.pgmoneta_name()) {
+        let pv = persistent::build_pgmoneta_pv(
+            &topology.pgmoneta_pv_name(),
+            &storage,
+            &volume_path(manager, &topology.pgmoneta_pv_name(), host_path).await?,
+            topology.name(),
+            claim_reference(topology, &topology.pgmoneta_pvc_name()),
+        );
+        manager.sync_cluster(pv).await?;
+    }
+
+    let pvc = persistent::build_pvc(
+        &topology.pgmoneta_pvc_name(),
+        topology.namespace(),
+        &claim_storage(topology, &storage, existing.as_ref()),
+        &topology.pgmoneta_name(),
+    );
+    manager.sync(pgopr, pvc).await?;
+
+    Ok(())
+}
+
+/// Syncs one volume of a member and returns the claim backing it.
+async fn sync_volume(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+    volume: &MemberVolume,
+    storage: &StorageConfig,
+    templates: Option<&[PersistentVolumeClaim]>,
+) -> Result<DataClaim, Error> {
+    let pvc_api: Api<PersistentVolumeClaim> =
+        Api::namespaced(manager.get_client(), topology.namespace());
+    let volume_claim = pvc_api.get_opt(&volume.pvc_name()).await?;
+    let template_claim = pvc_api.get_opt(&volume.template_pvc_name()).await?;
+    let has_template = templates.is_none_or(|templates| {
+        templates
+            .iter()
+            .any(|template| template.metadata.name.as_deref() == Some(volume.volume()))
+    });
+
+    let claim = match topology.workload() {
+        _ if volume_claim.is_some() => DataClaim::Volume(volume.pvc_name()),
+        WorkloadKind::Deployment if template_claim.is_some() => {
+            DataClaim::Volume(volume.template_pvc_name())
+        }
+        WorkloadKind::Deployment => DataClaim::Volume(volume.pvc_name()),
+        // the templates of an existing StatefulSet cannot change
+        WorkloadKind::StatefulSet if !has_template => DataClaim::Volume(volume.pvc_name()),
+        WorkloadKind::StatefulSet => DataClaim::Template(volume.template_pvc_name()),
+    };
+    let existing = volume_claim.as_ref().or(template_claim.as_ref());
+    let storage = storage.for_claim(existing);
+
+    if let Some(host_path) = topology.host_path(volume.resource_name()) {
+        let pv = persistent::build_pv(
+            &volume.pv_name(),
+            &storage,
+            volume.member_name(),
+            &volume_path(manager, &volume.pv_name(), host_path).await?,
+            topology.name(),
+            claim_reference(topology, claim.name()),
+        );
+        manager.sync_cluster(pv).await?;
+    }
+
+    // claims created from a template belong to the StatefulSet
+    if claim.name() == volume.pvc_name() {
+        let pvc = persistent::build_pvc(
+            &volume.pvc_name(),
+            topology.namespace(),
+            &claim_storage(topology, &storage, existing),
+            volume.member_name(),
+        );
+        manager.sync(pgopr, pvc).await?;
+    } else if let Some(template_claim) = &template_claim
+        && !topology.is_local()
+    {
+        expand(manager, template_claim, storage.size).await?;
+    }
+
+    Ok(claim)
+}
+
+/// The volume claim templates of the StatefulSet of a member, if it exists.
+async fn existing_templates(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    member: &ClusterMember,
+) -> Result<Option<Vec<PersistentVolumeClaim>>, Error> {
+    let sts_api: Api<StatefulSet> = Api::namespaced(manager.get_client(), topology.namespace());
+    Ok(sts_api.get_opt(member.name()).await?.map(|stateful_set| {
+        stateful_set
+            .spec
+            .and_then(|spec| spec.volume_claim_templates)
+            .unwrap_or_default()
+    }))
+}
+
+fn claim_template(
+    topology: &ClusterTopology,
+    volume: &MemberVolume,
+    storage: &StorageConfig,
+) -> PersistentVolumeClaim {
+    let mut template = persistent::build_pvc(
+        volume.volume(),
+        topology.namespace(),
+        storage,
+        volume.member_name(),
+    );
+    template.metadata.namespace = None;
+    template
+}
+
+/// Grows a claim the operator does not apply itself.
+async fn expand(
+    manager: &ResourceManager,
+    claim: &PersistentVolumeClaim,
+    size: u32,
+) -> Result<(), Error> {
+    if requested_bytes(claim).is_none_or(|requested| requested >= u64::from(size) * GIB) {
+        return Ok(());
+    }
+
+    let namespace = claim.namespace().unwrap_or_default();
+    info!(
+        "Expanding PersistentVolumeClaim {}/{} to {}Gi",
+        namespace,
+        claim.name_any(),
+        size
+    );
+    let pvc_api: Api<PersistentVolumeClaim> = Api::namespaced(manager.get_client(), &namespace);
+    let patch = json!({
+        "spec": {
+            "resources": {
+                "requests": {
+                    "storage": format!("{}Gi", size)
+                }
+            }
+        }
+    });
+    pvc_api
+        .patch(
+            &claim.name_any(),
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Claims bound to hostPath volumes cannot be expanded, only their volume grows.
+fn claim_storage(
+    topology: &ClusterTopology,
+    storage: &StorageConfig,
+    existing: Option<&PersistentVolumeClaim>,
+) -> StorageConfig {
+    match existing.and_then(requested_bytes) {
+        Some(requested) if topology.is_local() => StorageConfig {
+            size: requested.div_ceil(GIB) as u32,
+            ..storage.clone()
+        },
+        _ => storage.clone(),
+    }
+}
+
+async fn expandable(
+    manager: &ResourceManager,
+    claim: &PersistentVolumeClaim,
+) -> Result<bool, Error> {
+    let Some(class_name) = claim
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.storage_class_name.as_ref())
+    else {
+        return Ok(false);
+    };
+
+    let class_api: Api<StorageClass> = Api::all(manager.get_client());
+    Ok(class_api
+        .get_opt(class_name)
+        .await?
+        .and_then(|class| class.allow_volume_expansion)
+        .unwrap_or(false))
+}
+
+fn requested_bytes(claim: &PersistentVolumeClaim) -> Option<u64> {
+    claim
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.resources.as_ref())
+        .and_then(|resources| resources.requests.as_ref())
+        .and_then(|requests| requests.get("storage"))
+        .and_then(persistent::quantity_bytes)
+}
+
+fn pgmoneta_size(spec: &PgMonetaSpec) -> u32 {
+    spec.storage.unwrap_or(DEFAULT_PGMONETA_STORAGE_GI)
+}
+
+/// The host path of a volume, existing volumes keep theirs.
+async fn volume_path(
+    manager: &ResourceManager,
+    pv_name: &str,
+    host_path: String,
+) -> Result<String, Error> {
+    let pv_api: Api<PersistentVolume> = Api::all(manager.get_client());
+    Ok(pv_api
+        .get_opt(pv_name)
+        .await?
+        .and_then(|pv| pv.spec)
+        .and_then(|spec| spec.host_path)
+        .map(|source| source.path)
+        .unwrap_or(host_path))
+}
+
+fn claim_reference(topology: &ClusterTopology, claim_name: &str) -> ObjectReference {
+    ObjectReference {
+        namespace: Some(topology.namespace().to_string()),
+        name: Some(claim_name.to_string()),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,408 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::replication;
+use super::secrets;
+use super::topology::{ClusterMember, ClusterTopology};
+use crate::Error;
+use crate::crd::v1::{FailoverStatus, SwitchoverPhase, SwitchoverStatus, pgopr};
+use crate::manager::{self, ResourceManager};
+use crate::sql;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use k8s_openapi::jiff::Timestamp;
+use kube::{
+    Api, ResourceExt,
+    api::{Patch, PatchParams},
+};
+use log::{info, warn};
+use serde_json::json;
+use std::time::Duration;
+
+/// Delay until the next reconcile while a switchover is in progress
+pub(super) const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Time a phase may take before the switchover is abandoned
+const PHASE_TIMEOUT_SECONDS: i64 = 60;
+
+/// Switches the primary role to the replica named in the `pgopr.io/switchover-to`
+/// annotation, and continues a switchover in progress.
+///
+/// A switchover moves through its phases over several reconciles, each reconcile checks the
+/// current phase once instead of waiting for it. The annotation is removed once the request
+/// was handled, also when the switchover was refused.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource requesting the switchover.
+/// - `topology` - The cluster topology, updated when the target is promoted.
+/// - `failover` - The failover state recording the switchover.
+pub(super) async fn run(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &mut ClusterTopology,
+    failover: &mut FailoverStatus,
+) -> Result<(), Error> {
+    if failover.switchover.is_none()
+        && let Some(target) = pgopr.annotations().get(manager::ANNOTATION_SWITCHOVER)
+    {
+        match start(manager, topology, target).await {
+            Ok(switchover) => failover.switchover = Some(switchover),
+            Err(Error::SqlError(err)) | Err(Error::UserInputError(err)) => {
+                warn!("Switchover to {} failed: {}", target, err);
+            }
+            Err(err) => return Err(err),
+        }
+
+        let pgopr_api: Api<pgopr> = Api::namespaced(manager.get_client(), topology.namespace());
+        let patch = json!({
+            "metadata": {
+                "annotations": {
+                    manager::ANNOTATION_SWITCHOVER: null
+                }
+            }
+        });
+        pgopr_api
+            .patch(
+                topology.name(),
+                &PatchParams::default(),
+                &Patch::Merge(&patch),
+            )
+            .await?;
+    }
+
+    resume(manager, topology, failover).await
+}
+
+/// Starts moving the primary role to a replica by shutting the primary down.
+///
+/// Returns the switchover to record in the failover state, `run` continues it.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `topology` - The cluster topology.
+/// - `target` - Name of the replica to promote.
+pub(super) async fn start(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    target: &str,
+) -> Result<SwitchoverStatus, Error> {
+    let namespace = topology.namespace();
+    let primary = topology.primary();
+    let Some(candidate) = topology
+        .replica_members()
+        .into_iter()
+        .find(|member| member.name() == target && !topology.is_fenced(member.name()))
+    else {
+        return Err(Error::UserInputError(format!(
+            "{} is not an active replica of {}",
+            target,
+            topology.name()
+        )));
+    };
+    if topology.is_delayed(candidate.name()) {
+        return Err(Error::UserInputError(format!(
+            "{} is a delayed replica",
+            target
+        )));
+    }
+    if secrets::login(manager, namespace, &topology.credentials().replication)
+        .await?
+        .is_none()
+    {
+        return Err(Error::UserInputError(
+            "Replication credentials are missing".to_string(),
+        ));
+    }
+
+    // the old primary streams from the target through its own slot afterwards
+    replication::create_slot(manager, namespace, candidate.name(), primary.slot_name()).await?;
+    demote(manager, topology, &primary, &candidate).await?;
+
+    info!("Switching over from {} to {}", primary.name(), target);
+    Ok(SwitchoverStatus {
+        target: target.to_string(),
+        phase: SwitchoverPhase::Demoting,
+        lsn: None,
+        since: Time(Timestamp::now()),
+    })
+}
+
+/// Advances the switchover in progress as far as its phases are complete.
+async fn resume(
+    manager: &ResourceManager,
+    topology: &mut ClusterTopology,
+    failover: &mut FailoverStatus,
+) -> Result<(), Error> {
+    while let Some(switchover) = failover.switchover.clone() {
+        let namespace = topology.namespace().to_string();
+        let primary = topology.primary();
+        let Some(target) = topology
+            .replica_members()
+            .into_iter()
+            .find(|member| member.name() == switchover.target)
+        else {
+            abandon(
+                manager,
+                &namespace,
+                &primary,
+                failover,
+                "the target is gone",
+            )
+            .await?;
+            return Ok(());
+        };
+        let timed_out =
+            Timestamp::now().as_second() - switchover.since.0.as_second() >= PHASE_TIMEOUT_SECONDS;
+
+        let next = match switchover.phase {
+            SwitchoverPhase::Demoting => {
+                // NULL on a server that is not in recovery
+                match sql::execute(
+                    manager.get_client(),
+                    &namespace,
+                    primary.name(),
+                    &["SELECT pg_last_wal_replay_lsn()"],
+                )
+                .await
+                {
+                    Ok(lsn) if !lsn.is_empty() => {
+                        failover.switchover = Some(SwitchoverStatus {
+                            phase: SwitchoverPhase::CatchingUp,
+                            lsn: Some(lsn),
+                            since: Time(Timestamp::now()),
+                            ..switchover
+                        });
+                        continue;
+                    }
+                    Ok(_) if timed_out => {
+                        abandon(manager, &namespace, &primary, failover, "did not shut down")
+                            .await?;
+                        return Ok(());
+                    }
+                    Err(Error::SqlError(_)) if timed_out => {
+                        abandon(manager, &namespace, &primary, failover, "did not restart").await?;
+                        return Ok(());
+                    }
+                    Ok(_) | Err(Error::SqlError(_)) => return Ok(()),
+                    Err(err) => return Err(err),
+                }
+            }
+            SwitchoverPhase::CatchingUp => {
+                let lsn = switchover.lsn.as_deref().unwrap_or_default();
+                if !is_caught_up(manager, &namespace, &target, lsn).await? {
+                    if timed_out {
+                        abandon(manager, &namespace, &primary, failover, "did not catch up")
+                            .await?;
+                    }
+                    return Ok(());
+                }
+                promote(manager, &namespace, &target).await?;
+                SwitchoverPhase::Promoting
+            }
+            SwitchoverPhase::Promoting => {
+                match sql::execute(
+                    manager.get_client(),
+                    &namespace,
+                    target.name(),
+                    &["SELECT pg_is_in_recovery()"],
+                )
+                .await
+                {
+                    Ok(output) if output == "f" => {}
+                    Ok(_) | Err(Error::SqlError(_)) => {
+                        // the old primary already follows the target, so it is not abandoned
+                        if timed_out {
+                            warn!("Promotion of {} is taking long, retrying", target.name());
+                            promote(manager, &namespace, &target).await?;
+                            failover.switchover = Some(SwitchoverStatus {
+                                since: Time(Timestamp::now()),
+                                ..switchover
+                            });
+                        }
+                        return Ok(());
+                    }
+                    Err(err) => return Err(err),
+                }
+
+                info!("Switched over to {}", target.name());
+                topology.promote(target.name());
+                replication::follow_primary(manager, topology).await?;
+                failover.last_switchover = Some(Time(Timestamp::now()));
+                failover.switchover = None;
+                return Ok(());
+            }
+        };
+
+        failover.switchover = Some(SwitchoverStatus {
+            phase: next,
+            since: Time(Timestamp::now()),
+            ..switchover
+        });
+    }
+
+    Ok(())
+}
+
+/// Gives up a switchover before the target was promoted, the old primary takes its role back.
+///
+/// A primary that did not shut down keeps running once its `standby.signal` is removed, one
+/// that restarted as a standby is promoted again.
+async fn abandon(
+    manager: &ResourceManager,
+    namespace: &str,
+    primary: &ClusterMember,
+    failover: &mut FailoverStatus,
+    reason: &str,
+) -> Result<(), Error> {
+    if let Some(switchover) = failover.switchover.take() {
+        warn!("Switchover to {} failed: {}", switchover.target, reason);
+    }
+    match restore_primary(manager, namespace, primary).await {
+        Ok(()) => Ok(()),
+        Err(Error::SqlError(err)) => {
+            warn!("Unable to restore {} as primary: {}", primary.name(), err);
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+async fn restore_primary(
+    manager: &ResourceManager,
+    namespace: &str,
+    primary: &ClusterMember,
+) -> Result<(), Error> {
+    let data_directory = sql::execute(
+        manager.get_client(),
+        namespace,
+        primary.name(),
+        &["SHOW data_directory"],
+    )
+    .await?;
+    sql::command(
+        manager.get_client(),
+        namespace,
+        primary.name(),
+        &["rm", "-f", &format!("{}/standby.signal", data_directory)],
+    )
+    .await?;
+    sql::execute(
+        manager.get_client(),
+        namespace,
+        primary.name(),
+        &["SELECT pg_promote(false) WHERE pg_is_in_recovery()"],
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Checks once whether the target has replayed the WAL written by the old primary up to its
+/// shutdown, a target that cannot be queried has not.
+async fn is_caught_up(
+    manager: &ResourceManager,
+    namespace: &str,
+    target: &ClusterMember,
+    lsn: &str,
+) -> Result<bool, Error> {
+    let caught_up = format!(
+        "SELECT pg_last_wal_replay_lsn() >= {}::pg_lsn",
+        sql::quote_literal(lsn)
+    );
+    match sql::execute(
+        manager.get_client(),
+        namespace,
+        target.name(),
+        &[&caught_up],
+    )
+    .await
+    {
+        Ok(output) => Ok(output == "t"),
+        Err(Error::SqlError(_)) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Shuts the primary down so it restarts as a standby of the target.
+///
+/// The old primary is pointed at the target, `standby.signal` is created in its data
+/// directory and the server is stopped, so it comes back in recovery when its container
+/// restarts. The stopped server accepts no writes, and a fast shutdown sends all WAL up to
+/// the shutdown checkpoint to the connected replicas before it ends.
+async fn demote(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    primary: &ClusterMember,
+    target: &ClusterMember,
+) -> Result<(), Error> {
+    let namespace = topology.namespace();
+    let (username, password) =
+        secrets::login(manager, namespace, &topology.credentials().replication)
+            .await?
+            .ok_or_else(|| Error::SqlError("Replication credentials are missing".to_string()))?;
+    replication::follow(
+        manager,
+        namespace,
+        primary,
+        target.name(),
+        &username,
+        &password,
+    )
+    .await?;
+
+    let data_directory = sql::execute(
+        manager.get_client(),
+        namespace,
+        primary.name(),
+        &["SHOW data_directory"],
+    )
+    .await?;
+    sql::command(
+        manager.get_client(),
+        namespace,
+        primary.name(),
+        &["touch", &format!("{}/standby.signal", data_directory)],
+    )
+    .await?;
+
+    // the exec session may end together with the server
+    if let Err(err) = sql::command(
+        manager.get_client(),
+        namespace,
+        primary.name(),
+        &["pg_ctl", "stop", "-D", &data_directory, "-m", "fast"],
+    )
+    .await
+    {
+        info!("Stopping {}: {}", primary.name(), err);
+    }
+
+    Ok(())
+}
+
+/// Requests the promotion of the target without waiting for it.
+async fn promote(
+    manager: &ResourceManager,
+    namespace: &str,
+    target: &ClusterMember,
+) -> Result<(), Error> {
+    match sql::execute(
+        manager.get_client(),
+        namespace,
+        target.name(),
+        &["SELECT pg_promote(false)"],
+    )
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(Error::SqlError(err)) => {
+            warn!("Unable to promote {}: {}", target.name(), err);
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
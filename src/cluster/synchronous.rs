@@ -0,0 +1,339 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::replication;
+use super::secrets;
+use super::topology::{ClusterMember, ClusterTopology};
+use crate::Error;
+use crate::crd::v1::{
+    PgOprSpec, ReplicationMode, ReplicationPreference, ReplicationSpec, ReplicationStatus,
+};
+use crate::manager::ResourceManager;
+use crate::sql;
+use log::{debug, info, warn};
+use serde::Deserialize;
+
+/// Standbys confirming each commit without `spec.replication.synchronousStandbys`
+const DEFAULT_SYNCHRONOUS_STANDBYS: u32 = 1;
+
+/// The synchronous standbys in effect and the standbys streaming through a slot
+const STANDBYS_QUERY: &str = "SELECT json_build_object(\
+    'standby_names', current_setting('synchronous_standby_names'), \
+    'standbys', (SELECT coalesce(json_agg(json_build_object(\
+    'slot', s.slot_name, \
+    'application_name', r.application_name, \
+    'streaming', r.state = 'streaming', \
+    'sync_state', r.sync_state)), '[]') \
+    FROM pg_replication_slots s JOIN pg_stat_replication r ON r.pid = s.active_pid))";
+
+#[derive(Deserialize)]
+struct Standbys {
+    standby_names: String,
+    standbys: Vec<Standby>,
+}
+
+/// A standby streaming from the primary
+#[derive(Deserialize)]
+struct Standby {
+    slot: String,
+    application_name: String,
+    streaming: bool,
+    sync_state: String,
+}
+
+/// Checks the replication settings of the spec.
+///
+/// With the durability preference the primary waits for the synchronous standbys, so the
+/// cluster needs at least as many replicas.
+///
+/// # Arguments
+/// - `spec` - The spec of the cluster.
+pub(super) fn validate(spec: &PgOprSpec) -> Option<String> {
+    let replication = spec.replication.as_ref()?;
+    if mode(replication) == ReplicationMode::Async {
+        return None;
+    }
+
+    let standbys = synchronous_standbys(replication);
+    let replicas = spec
+        .replica_specs
+        .as_ref()
+        .map(|replicas| replicas.len() as u32)
+        .or(spec.replicas)
+        .unwrap_or(0);
+    if standbys == 0 {
+        Some("replication.synchronousStandbys must be at least 1".to_string())
+    } else if preference(replication) == ReplicationPreference::Durability && standbys > replicas {
+        Some(format!(
+            "replication.synchronousStandbys {} exceeds the {} replicas",
+            standbys, replicas
+        ))
+    } else {
+        None
+    }
+}
+
+/// Sets `synchronous_standby_names` of the primary from the replicas of the topology.
+///
+/// Each replica is listed under the name of its slot. With the durability preference all
+/// replicas are listed and commits wait until enough of them confirm. With the availability
+/// preference only streaming replicas are listed and fewer standbys are required while
+/// replicas are missing, commits no longer wait once none streams. Streaming replicas
+/// reporting another application name are re-pointed at the primary to take their slot
+/// name. Returns the synchronous standbys, or nothing while the primary is not running.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `spec` - The spec of the cluster.
+/// - `topology` - The expected cluster topology.
+pub(super) async fn sync(
+    manager: &ResourceManager,
+    spec: &PgOprSpec,
+    topology: &ClusterTopology,
+) -> Result<Option<ReplicationStatus>, Error> {
+    let namespace = topology.namespace();
+    let primary = topology.primary();
+    let current = match query(manager, namespace, primary.name()).await {
+        Ok(current) => current,
+        Err(Error::SqlError(err)) => {
+            debug!("Unable to read the standbys of {}: {}", primary.name(), err);
+            return Ok(None);
+        }
+        Err(err) => return Err(err),
+    };
+
+    // cascading replicas stream from another replica and cannot confirm commits
+    let replicas: Vec<ClusterMember> = topology
+        .replica_members()
+        .into_iter()
+        .filter(|member| {
+            !topology.is_fenced(member.name()) && topology.upstream(member).name() == primary.name()
+        })
+        .collect();
+    let streaming: Vec<&Standby> = current
+        .standbys
+        .iter()
+        .filter(|standby| {
+            standby.streaming
+                && replicas
+                    .iter()
+                    .any(|member| member.slot_name() == standby.slot)
+        })
+        .collect();
+    rename_standbys(manager, topology, &replicas, &streaming).await?;
+
+    let default = ReplicationSpec::default();
+    let replication = spec.replication.as_ref().unwrap_or(&default);
+    let candidates: Vec<&str> = match preference(replication) {
+        ReplicationPreference::Durability => {
+            replicas.iter().map(|member| member.slot_name()).collect()
+        }
+        ReplicationPreference::Availability => streaming
+            .iter()
+            .filter(|standby| standby.application_name == standby.slot)
+            .map(|standby| standby.slot.as_str())
+            .collect(),
+    };
+    let wanted = match mode(replication) {
+        ReplicationMode::Async => 0,
+        _ => synchronous_standbys(replication).min(replicas.len() as u32),
+    };
+    let required = wanted.min(candidates.len() as u32);
+    let standby_names = standby_names(mode(replication), required, &candidates);
+
+    if standby_names != current.standby_names {
+        info!(
+            "Setting synchronous_standby_names of {} to '{}'",
+            primary.name(),
+            standby_names
+        );
+        let statement = if standby_names.is_empty() {
+            "ALTER SYSTEM RESET synchronous_standby_names".to_string()
+        } else {
+            format!(
+                "ALTER SYSTEM SET synchronous_standby_names = {}",
+                sql::quote_literal(&standby_names)
+            )
+        };
+        sql::execute(
+            manager.get_client(),
+            namespace,
+            primary.name(),
+            &[&statement, "SELECT pg_reload_conf()"],
+        )
+        .await?;
+    }
+
+    // the primary picks the synchronous standbys once it loaded the new list
+    let synchronous = current
+        .standbys
+        .iter()
+        .filter(|standby| matches!(standby.sync_state.as_str(), "sync" | "quorum"))
+        .filter_map(|standby| {
+            replicas
+                .iter()
+                .find(|member| member.slot_name() == standby.slot)
+                .map(|member| member.name().to_string())
+        })
+        .collect();
+
+    Ok(Some(ReplicationStatus {
+        standby_names,
+        required: wanted,
+        synchronous,
+    }))
+}
+
+/// Re-points streaming replicas whose application name differs from their slot name.
+async fn rename_standbys(
+    manager: &ResourceManager,
+    topology: &ClusterTopology,
+    replicas: &[ClusterMember],
+    streaming: &[&Standby],
+) -> Result<(), Error> {
+    let renamed: Vec<&ClusterMember> = replicas
+        .iter()
+        .filter(|member| {
+            streaming.iter().any(|standby| {
+                standby.slot == member.slot_name() && standby.application_name != standby.slot
+            })
+        })
+        .collect();
+    if renamed.is_empty() {
+        return Ok(());
+    }
+
+    let Some((username, password)) = secrets::login(
+        manager,
+        topology.namespace(),
+        &topology.credentials().replication,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let primary = topology.primary();
+    for member in renamed {
+        info!(
+            "Re-pointing {} to stream as {}",
+            member.name(),
+            member.slot_name()
+        );
+        match replication::follow(
+            manager,
+            topology.namespace(),
+            member,
+            primary.name(),
+            &username,
+            &password,
+        )
+        .await
+        {
+            Ok(()) => {}
+            Err(Error::SqlError(err)) => {
+                warn!("Unable to re-point {}: {}", member.name(), err)
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// The value of `synchronous_standby_names`, empty when commits do not wait
+fn standby_names(mode: ReplicationMode, required: u32, candidates: &[&str]) -> String {
+    let method = match mode {
+        ReplicationMode::Async => return String::new(),
+        ReplicationMode::Sync => "FIRST",
+        ReplicationMode::Quorum => "ANY",
+    };
+    if required == 0 {
+        return String::new();
+    }
+
+    let names: Vec<String> = candidates
+        .iter()
+        .map(|name| format!("\"{}\"", name))
+        .collect();
+    format!("{} {} ({})", method, required, names.join(", "))
+}
+
+fn mode(replication: &ReplicationSpec) -> ReplicationMode {
+    replication.mode.unwrap_or_default()
+}
+
+fn preference(replication: &ReplicationSpec) -> ReplicationPreference {
+    replication.preference.unwrap_or_default()
+}
+
+fn synchronous_standbys(replication: &ReplicationSpec) -> u32 {
+    replication
+        .synchronous_standbys
+        .unwrap_or(DEFAULT_SYNCHRONOUS_STANDBYS)
+}
+
+async fn query(
+    manager: &ResourceManager,
+    namespace: &str,
+    server: &str,
+) -> Result<Standbys, Error> {
+    let output = sql::execute(manager.get_client(), namespace, server, &[STANDBYS_QUERY]).await?;
+    serde_json::from_str(&output)
+        .map_err(|err| Error::SqlError(format!("Unexpected standbys of {}: {}", server, err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(
+        mode: ReplicationMode,
+        standbys: Option<u32>,
+        preference: ReplicationPreference,
+        replicas: u32,
+    ) -> PgOprSpec {
+        PgOprSpec {
+            replicas: Some(replicas),
+            replication: Some(ReplicationSpec {
+                mode: Some(mode),
+                synchronous_standbys: standbys,
+                preference: Some(preference),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn standby_names_follow_the_mode() {
+        let candidates = ["replica1", "replica_east"];
+        assert_eq!(
+            standby_names(ReplicationMode::Quorum, 1, &candidates),
+            "ANY 1 (\"replica1\", \"replica_east\")"
+        );
+        assert_eq!(
+            standby_names(ReplicationMode::Sync, 2, &candidates),
+            "FIRST 2 (\"replica1\", \"replica_east\")"
+        );
+        assert_eq!(standby_names(ReplicationMode::Async, 1, &candidates), "");
+        assert_eq!(standby_names(ReplicationMode::Quorum, 0, &[]), "");
+    }
+
+    #[test]
+    fn durability_needs_enough_replicas() {
+        use ReplicationMode::*;
+        use ReplicationPreference::*;
+
+        assert_eq!(validate(&spec(Quorum, Some(2), Durability, 2)), None);
+        assert_eq!(validate(&spec(Quorum, None, Durability, 1)), None);
+        assert!(validate(&spec(Quorum, Some(3), Durability, 2)).is_some());
+        assert_eq!(validate(&spec(Quorum, Some(3), Availability, 2)), None);
+        assert!(validate(&spec(Sync, Some(0), Availability, 2)).is_some());
+        assert_eq!(validate(&spec(Async, Some(0), Durability, 0)), None);
+    }
+}
@@ -6,34 +6,56 @@
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
 
-use crate::crd::v1::pgopr;
+use crate::crd::v1::{ReplicaSpec, WorkloadKind, pgopr};
+use crate::credentials::Credentials;
 use crate::manager;
+use crate::persistent::{STORAGE_CLASS_LOCAL, StorageConfig};
+use crate::workload;
 use kube::ResourceExt;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
-const PRIMARY_HOST_PATH: &str = "/tmp/kind";
-const REPLICA_HOST_PATH_PREFIX: &str = "/tmp/kind-replica-";
+const DEFAULT_LOCAL_PATH: &str = "/tmp/kind";
 const REPLICA_NAME_SEGMENT: &str = "replica";
+const BOOTSTRAP_SLOT_NAME: &str = "bootstrap";
 const PV_NAME_SUFFIX: &str = "pv-volume";
 const PVC_NAME_SUFFIX: &str = "pv-claim";
+pub(super) const WAL_NAME_SUFFIX: &str = "wal";
+
+/// Services selecting the members by role
+const READ_WRITE_SUFFIX: &str = "rw";
+const READ_ONLY_SUFFIX: &str = "ro";
+const READ_SUFFIX: &str = "r";
+
+/// Job running a major version upgrade
+const UPGRADE_SUFFIX: &str = "upgrade";
+
+/// ConfigMap holding pg_hba.conf
+const HBA_SUFFIX: &str = "hba";
 
 /// pgmoenta is a special resource type that is used to store pgmoneta data.
 const PGMONETA_SUFFIX: &str = "pgmoneta";
 const PGMONETA_PV_NAME_SUFFIX: &str = "pgmoneta-pv-volume";
 const PGMONETA_PVC_NAME_SUFFIX: &str = "pgmoneta-pv-claim";
-const PGMONETA_SECRET_SUFFIX: &str = "pgmoneta-secret";
 
 /// pgexporter is a special resource type that is used to store pgexporter data.
 const PGEXPORTER_SUFFIX: &str = "pgexporter";
-const PGEXPORTER_SECRET_SUFFIX: &str = "pgexporter-secret";
 const PGEXPORTER_MON_SUFFIX: &str = "pgexporter-mon";
 
 /// ClusterTopology centralizes names and desired members for a PostgreSQL cluster.
 pub(super) struct ClusterTopology {
     name: String,
     namespace: String,
-    storage: u32,
-    replicas: u32,
+    storage: StorageConfig,
+    wal_storage: Option<StorageConfig>,
+    local_path: Option<String>,
+    replica_ids: Vec<String>,
+    replica_specs: BTreeMap<String, ReplicaSpec>,
+    credentials: Credentials,
+    primary: String,
+    fenced: BTreeSet<String>,
+    upstreams: BTreeMap<String, String>,
+    delayed: BTreeSet<String>,
+    workload: WorkloadKind,
 }
 
 impl ClusterTopology {
@@ -42,13 +64,90 @@ impl ClusterTopology {
     /// # Arguments
     /// - `pgopr` - The PgOpr resource defining the desired cluster state.
     pub(super) fn from_pgopr(pgopr: &pgopr) -> Self {
+        let name = pgopr.name_any();
+        let status = pgopr.status.as_ref();
+        let local_path = pgopr.spec.local_storage.as_ref().map(|local| {
+            local
+                .path
+                .clone()
+                .unwrap_or_else(|| DEFAULT_LOCAL_PATH.to_string())
+        });
+        let storage = StorageConfig {
+            size: pgopr.spec.storage,
+            storage_class_name: pgopr
+                .spec
+                .storage_class_name
+                .clone()
+                .or_else(|| local_path.as_ref().map(|_| STORAGE_CLASS_LOCAL.to_string())),
+            access_mode: pgopr
+                .spec
+                .access_mode
+                .unwrap_or_default()
+                .as_str()
+                .to_string(),
+            volume_mode: pgopr
+                .spec
+                .volume_mode
+                .unwrap_or_default()
+                .as_str()
+                .to_string(),
+        };
+        let wal_storage = pgopr.spec.wal_storage.as_ref().map(|wal| StorageConfig {
+            size: wal.size,
+            storage_class_name: wal
+                .storage_class_name
+                .clone()
+                .or_else(|| storage.storage_class_name.clone()),
+            ..storage.clone()
+        });
+
+        // replicas without specs are named by their ordinal
+        let replica_ids: Vec<String> = match &pgopr.spec.replica_specs {
+            Some(specs) => specs.iter().map(|spec| spec.name.clone()).collect(),
+            None => (1..=pgopr.spec.replicas.unwrap_or(0))
+                .map(|ordinal| ordinal.to_string())
+                .collect(),
+        };
+        let replica_specs: BTreeMap<String, ReplicaSpec> = pgopr
+            .spec
+            .replica_specs
+            .iter()
+            .flatten()
+            .map(|spec| (replica_name(&name, &spec.name), spec.clone()))
+            .collect();
+
         Self {
-            name: pgopr.name_any(),
+            primary: status
+                .and_then(|status| status.current_primary.clone())
+                .unwrap_or_else(|| name.clone()),
+            fenced: status
+                .and_then(|status| status.failover.as_ref())
+                .map(|failover| failover.fenced.iter().cloned().collect())
+                .unwrap_or_default(),
+            name,
             namespace: pgopr
                 .namespace()
                 .unwrap_or_else(|| manager::DEFAULT_NAMESPACE.to_string()),
-            storage: pgopr.spec.storage,
-            replicas: pgopr.spec.replicas.unwrap_or(0),
+            storage,
+            wal_storage,
+            local_path,
+            replica_ids,
+            credentials: Credentials::from_pgopr(pgopr),
+            upstreams: pgopr.spec.upstreams.clone().unwrap_or_default(),
+            delayed: pgopr
+                .spec
+                .delayed_replicas
+                .iter()
+                .flat_map(|delayed| delayed.members.iter().cloned())
+                .chain(
+                    replica_specs
+                        .iter()
+                        .filter(|(_, spec)| spec.delayed.unwrap_or(false))
+                        .map(|(name, _)| name.clone()),
+                )
+                .collect(),
+            replica_specs,
+            workload: pgopr.spec.workload.unwrap_or_default(),
         }
     }
 
@@ -60,44 +159,185 @@ impl ClusterTopology {
         &self.namespace
     }
 
-    pub(super) fn storage(&self) -> u32 {
-        self.storage
+    pub(super) fn storage(&self) -> &StorageConfig {
+        &self.storage
     }
 
-    pub(super) fn replicas(&self) -> u32 {
-        self.replicas
+    /// Storage of the WAL volumes, absent when WAL stays in the data directory
+    pub(super) fn wal_storage(&self) -> Option<&StorageConfig> {
+        self.wal_storage.as_ref()
     }
 
+    /// Whether the operator creates hostPath volumes instead of a provisioner
+    pub(super) fn is_local(&self) -> bool {
+        self.local_path.is_some()
+    }
+
+    /// Directory on the node holding the hostPath volume of a resource, only in local mode
+    ///
+    /// # Arguments
+    /// - `resource_name` - Name of the member or component owning the volume.
+    pub(super) fn host_path(&self, resource_name: &str) -> Option<String> {
+        self.local_path.as_ref().map(|path| {
+            format!(
+                "{}/{}/{}",
+                path.trim_end_matches('/'),
+                self.namespace,
+                resource_name
+            )
+        })
+    }
+
+    /// The spec of a replica listed in `spec.replicaSpecs`
+    ///
+    /// # Arguments
+    /// - `name` - Name of the member.
+    pub(super) fn replica_spec(&self, name: &str) -> Option<&ReplicaSpec> {
+        self.replica_specs.get(name)
+    }
+
+    pub(super) fn workload(&self) -> WorkloadKind {
+        self.workload
+    }
+
+    /// All members of the cluster, starting with the member that bootstrapped it.
+    ///
+    /// A promoted replica removed from the spec stays a member while it is primary.
+    pub(super) fn members(&self) -> Vec<ClusterMember> {
+        let mut members = vec![self.bootstrap()];
+        members.extend(
+            self.replica_ids
+                .iter()
+                .map(|id| ClusterMember::replica(&self.name, id)),
+        );
+        if let Some(id) = replica_id(&self.name, &self.primary)
+            .filter(|id| !self.replica_ids.iter().any(|replica| replica == id))
+        {
+            members.push(ClusterMember::replica(&self.name, id));
+        }
+        members
+    }
+
+    /// The member that initialized the cluster
+    pub(super) fn bootstrap(&self) -> ClusterMember {
+        ClusterMember::bootstrap(self.name.clone())
+    }
+
+    /// The member currently running as primary
     pub(super) fn primary(&self) -> ClusterMember {
-        ClusterMember::primary(self.name.clone())
+        self.members()
+            .into_iter()
+            .find(|member| member.name == self.primary)
+            .unwrap_or_else(|| self.bootstrap())
     }
 
+    /// The members currently running as replica, including fenced ones
     pub(super) fn replica_members(&self) -> Vec<ClusterMember> {
-        (1..=self.replicas)
-            .map(|ordinal| ClusterMember::replica(&self.name, ordinal))
+        let primary = self.primary();
+        self.members()
+            .into_iter()
+            .filter(|member| member.name != primary.name)
             .collect()
     }
 
+    /// The member a replica streams from.
+    ///
+    /// A replica streams from the member `spec.upstreams` names for it, unless that member
+    /// is fenced, and from the primary otherwise.
+    ///
+    /// # Arguments
+    /// - `member` - The replica.
+    pub(super) fn upstream(&self, member: &ClusterMember) -> ClusterMember {
+        self.upstreams
+            .get(&member.name)
+            .filter(|upstream| **upstream != member.name && !self.is_fenced(upstream))
+            .and_then(|upstream| {
+                self.members()
+                    .into_iter()
+                    .find(|candidate| candidate.name == *upstream)
+            })
+            .unwrap_or_else(|| self.primary())
+    }
+
+    /// Replicas streaming from another replica, by the name of the replica, as given in the
+    /// spec
+    pub(super) fn upstreams(&self) -> &BTreeMap<String, String> {
+        &self.upstreams
+    }
+
     pub(super) fn member_names(&self) -> BTreeSet<String> {
-        let mut names = BTreeSet::new();
-        names.insert(self.name.clone());
-        for member in self.replica_members() {
-            names.insert(member.name().to_string());
-        }
-        names
+        self.members()
+            .into_iter()
+            .map(|member| member.name)
+            .collect()
+    }
+
+    /// Makes a member the primary of the topology.
+    ///
+    /// # Arguments
+    /// - `name` - Name of the promoted member.
+    pub(super) fn promote(&mut self, name: &str) {
+        self.primary = name.to_string();
+    }
+
+    /// Marks a member as fenced so its workload is scaled down.
+    ///
+    /// # Arguments
+    /// - `name` - Name of the fenced member.
+    pub(super) fn fence(&mut self, name: &str) {
+        self.fenced.insert(name.to_string());
+    }
+
+    pub(super) fn is_fenced(&self, name: &str) -> bool {
+        self.fenced.contains(name)
+    }
+
+    /// Whether a member applies the WAL only after a delay while it is a replica
+    pub(super) fn is_delayed(&self, name: &str) -> bool {
+        self.delayed.contains(name)
     }
 
     pub(super) fn pvc_names(&self) -> BTreeSet<String> {
-        self.member_names()
-            .into_iter()
-            .map(|name| pvc_name(&name))
+        self.members()
+            .iter()
+            .flat_map(ClusterMember::volumes)
+            .flat_map(|volume| [volume.pvc_name(), volume.template_pvc_name()])
             .collect()
     }
 
+    pub(super) fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
     pub(super) fn pv_selector(&self) -> String {
         format!("{}={}", manager::LABEL_CLUSTER, self.name)
     }
 
+    /// Service selecting the primary
+    pub(super) fn read_write_name(&self) -> String {
+        format!("{}-{}", self.name, READ_WRITE_SUFFIX)
+    }
+
+    /// Service selecting the replicas
+    pub(super) fn read_only_name(&self) -> String {
+        format!("{}-{}", self.name, READ_ONLY_SUFFIX)
+    }
+
+    /// Service selecting any member
+    pub(super) fn read_name(&self) -> String {
+        format!("{}-{}", self.name, READ_SUFFIX)
+    }
+
+    /// Job upgrading the primary to a new major version
+    pub(super) fn upgrade_name(&self) -> String {
+        format!("{}-{}", self.name, UPGRADE_SUFFIX)
+    }
+
+    /// ConfigMap holding the client authentication rules
+    pub(super) fn hba_name(&self) -> String {
+        format!("{}-{}", self.name, HBA_SUFFIX)
+    }
+
     pub fn pgmoneta_name(&self) -> String {
         format!("{}-{}", self.name, PGMONETA_SUFFIX)
     }
@@ -107,16 +347,10 @@ impl ClusterTopology {
     pub fn pgmoneta_pvc_name(&self) -> String {
         format!("{}-{}", self.name, PGMONETA_PVC_NAME_SUFFIX)
     }
-    pub fn pgmoneta_secret_name(&self) -> String {
-        format!("{}-{}", self.name, PGMONETA_SECRET_SUFFIX)
-    }
 
     pub fn pgexporter_name(&self) -> String {
         format!("{}-{}", self.name, PGEXPORTER_SUFFIX)
     }
-    pub fn pgexporter_secret_name(&self) -> String {
-        format!("{}-{}", self.name, PGEXPORTER_SECRET_SUFFIX)
-    }
 
     pub fn pgexporter_mon_name(&self) -> String {
         format!("{}-{}", self.name, PGEXPORTER_MON_SUFFIX)
@@ -124,27 +358,37 @@ impl ClusterTopology {
 }
 
 /// ClusterMember represents a primary or replica member in the cluster topology.
+///
+/// The bootstrap member initializes the cluster and is the primary until a failover
+/// promotes one of the replicas.
 pub(super) struct ClusterMember {
     name: String,
-    host_path: String,
-    slot_name: Option<String>,
+    slot_name: String,
+    bootstrap: bool,
 }
 
 impl ClusterMember {
-    fn primary(name: String) -> Self {
+    fn bootstrap(name: String) -> Self {
         Self {
             name,
-            host_path: PRIMARY_HOST_PATH.to_string(),
-            slot_name: None,
+            slot_name: BOOTSTRAP_SLOT_NAME.to_string(),
+            bootstrap: true,
         }
     }
 
-    fn replica(cluster_name: &str, ordinal: u32) -> Self {
-        let name = replica_name(cluster_name, ordinal);
+    fn replica(cluster_name: &str, id: &str) -> Self {
+        let name = replica_name(cluster_name, id);
+        // replicas named by their ordinal keep the slots they had before replicaSpecs, slot
+        // names only take lower case letters, digits and underscores
+        let slot_name = if id.chars().all(|c| c.is_ascii_digit()) {
+            format!("{}{}", REPLICA_NAME_SEGMENT, id)
+        } else {
+            format!("{}_{}", REPLICA_NAME_SEGMENT, id.replace('-', "_"))
+        };
         Self {
             name,
-            host_path: format!("{}{}", REPLICA_HOST_PATH_PREFIX, ordinal),
-            slot_name: Some(format!("{}{}", REPLICA_NAME_SEGMENT, ordinal)),
+            slot_name,
+            bootstrap: false,
         }
     }
 
@@ -152,39 +396,142 @@ impl ClusterMember {
         &self.name
     }
 
-    pub(super) fn host_path(&self) -> &str {
-        &self.host_path
+    /// The replication slot the member streams through while it is a replica
+    pub(super) fn slot_name(&self) -> &str {
+        &self.slot_name
     }
 
-    pub(super) fn slot_name(&self) -> Option<&str> {
-        self.slot_name.as_deref()
+    pub(super) fn is_bootstrap(&self) -> bool {
+        self.bootstrap
+    }
+
+    /// The volume holding the data directory
+    pub(super) fn data_volume(&self) -> MemberVolume {
+        MemberVolume::data(&self.name)
+    }
+
+    /// The volume holding the WAL when it is separated from the data directory
+    pub(super) fn wal_volume(&self) -> MemberVolume {
+        MemberVolume::wal(&self.name)
+    }
+
+    /// All volumes the member may have
+    pub(super) fn volumes(&self) -> [MemberVolume; 2] {
+        volumes(&self.name)
+    }
+}
+
+/// MemberVolume names the objects backing one volume of a member.
+pub(super) struct MemberVolume {
+    volume: &'static str,
+    resource_name: String,
+    member_name: String,
+}
+
+impl MemberVolume {
+    fn data(member_name: &str) -> Self {
+        Self {
+            volume: workload::DATA_VOLUME,
+            resource_name: member_name.to_string(),
+            member_name: member_name.to_string(),
+        }
+    }
+
+    fn wal(member_name: &str) -> Self {
+        Self {
+            volume: workload::WAL_VOLUME,
+            resource_name: format!("{}-{}", member_name, WAL_NAME_SUFFIX),
+            member_name: member_name.to_string(),
+        }
+    }
+
+    /// Name of the volume in the pod template
+    pub(super) fn volume(&self) -> &'static str {
+        self.volume
+    }
+
+    /// Name the volume objects and the local host path are derived from
+    pub(super) fn resource_name(&self) -> &str {
+        &self.resource_name
+    }
+
+    pub(super) fn member_name(&self) -> &str {
+        &self.member_name
     }
 
     pub(super) fn pv_name(&self) -> String {
-        pv_name(&self.name)
+        pv_name(&self.resource_name)
     }
 
     pub(super) fn pvc_name(&self) -> String {
-        pvc_name(&self.name)
+        pvc_name(&self.resource_name)
+    }
+
+    /// StatefulSets name their claims `<template>-<statefulset>-<ordinal>`, members run a
+    /// single pod
+    pub(super) fn template_pvc_name(&self) -> String {
+        format!("{}-{}-0", self.volume, self.member_name)
+    }
+}
+
+/// DataClaim is the claim backing a volume of a member.
+pub(super) enum DataClaim {
+    /// An existing claim mounted as volume
+    Volume(String),
+    /// A claim created from the volume claim template of a StatefulSet
+    Template(String),
+}
+
+impl DataClaim {
+    pub(super) fn name(&self) -> &str {
+        match self {
+            DataClaim::Volume(name) | DataClaim::Template(name) => name,
+        }
     }
 }
 
-pub(super) fn replica_ordinal(cluster_name: &str, resource_name: &str) -> Option<u32> {
+/// MemberClaims are the claims backing the volumes of a member.
+pub(super) struct MemberClaims {
+    pub(super) data: DataClaim,
+    pub(super) wal: Option<DataClaim>,
+}
+
+/// The ordinal or name of a replica from the name of its workload.
+///
+/// # Arguments
+/// - `cluster_name` - Name of the cluster.
+/// - `resource_name` - Name of the workload.
+pub(super) fn replica_id<'a>(cluster_name: &str, resource_name: &'a str) -> Option<&'a str> {
     let prefix = format!("{}-{}-", cluster_name, REPLICA_NAME_SEGMENT);
     resource_name
         .strip_prefix(&prefix)
-        .and_then(|suffix| suffix.split('-').next())
-        .and_then(|ordinal| ordinal.parse::<u32>().ok())
+        .filter(|id| !id.is_empty())
 }
 
-fn replica_name(cluster_name: &str, ordinal: u32) -> String {
-    format!("{}-{}-{}", cluster_name, REPLICA_NAME_SEGMENT, ordinal)
+/// Name of the member of a replica
+///
+/// # Arguments
+/// - `cluster_name` - Name of the cluster.
+/// - `id` - The ordinal or name of the replica.
+pub(super) fn replica_name(cluster_name: &str, id: &str) -> String {
+    format!("{}-{}-{}", cluster_name, REPLICA_NAME_SEGMENT, id)
 }
 
-pub(super) fn pv_name(resource_name: &str) -> String {
+fn pv_name(resource_name: &str) -> String {
     format!("{}-{}", resource_name, PV_NAME_SUFFIX)
 }
 
-pub(super) fn pvc_name(resource_name: &str) -> String {
+fn pvc_name(resource_name: &str) -> String {
     format!("{}-{}", resource_name, PVC_NAME_SUFFIX)
 }
+
+/// All volumes a member may have
+///
+/// # Arguments
+/// - `member_name` - Name of the member.
+pub(super) fn volumes(member_name: &str) -> [MemberVolume; 2] {
+    [
+        MemberVolume::data(member_name),
+        MemberVolume::wal(member_name),
+    ]
+}
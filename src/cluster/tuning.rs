@@ -0,0 +1,100 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use crate::crd::v1::PgOprSpec;
+use crate::quantity;
+use std::collections::BTreeMap;
+
+const MEGABYTE: u64 = 1024 * 1024;
+/// Upper bound of maintenance_work_mem, larger values do not speed up index builds
+const MAX_MAINTENANCE_WORK_MEM: u64 = 2 * 1024 * MEGABYTE;
+/// Lower bound of work_mem, the default of PostgreSQL
+const MIN_WORK_MEM: u64 = 4 * MEGABYTE;
+/// max_connections of PostgreSQL when the spec does not set it
+const DEFAULT_MAX_CONNECTIONS: u64 = 100;
+/// max_worker_processes of PostgreSQL, background workers of extensions count against it
+const DEFAULT_MAX_WORKER_PROCESSES: u64 = 8;
+
+/// The parameters of a cluster, the parameters derived from its resources beneath those of
+/// `spec.config`.
+///
+/// Returns nothing when the spec neither sets parameters nor limits.
+///
+/// # Arguments
+/// - `spec` - The spec of the cluster.
+pub(super) fn effective_config(spec: &PgOprSpec) -> Option<BTreeMap<String, String>> {
+    let mut config = tuned(spec);
+    if let Some(explicit) = &spec.config {
+        config.extend(explicit.clone());
+    }
+
+    (spec.config.is_some() || !config.is_empty()).then_some(config)
+}
+
+/// The parameters derived from the memory and CPU limits of the members, without those set
+/// in `spec.config`.
+///
+/// # Arguments
+/// - `spec` - The spec of the cluster.
+pub(super) fn tuned(spec: &PgOprSpec) -> BTreeMap<String, String> {
+    let limits = spec
+        .resources
+        .as_ref()
+        .and_then(|resources| resources.limits.as_ref());
+    let explicit = |name: &str| {
+        spec.config
+            .iter()
+            .flatten()
+            .find_map(|(key, value)| key.eq_ignore_ascii_case(name).then_some(value.as_str()))
+    };
+
+    let mut parameters = BTreeMap::new();
+
+    if let Some(memory) = limits
+        .and_then(|limits| limits.get("memory"))
+        .and_then(|memory| quantity::bytes(memory))
+    {
+        let max_connections = explicit("max_connections")
+            .and_then(|value| value.parse().ok())
+            .filter(|connections| *connections > 0)
+            .unwrap_or(DEFAULT_MAX_CONNECTIONS);
+        let shared_buffers = memory / 4;
+        // each connection may run a few sorts or hashes at once
+        let work_mem = (memory - shared_buffers) / (max_connections * 3);
+
+        parameters.insert("shared_buffers", megabytes(shared_buffers));
+        parameters.insert("effective_cache_size", megabytes(memory / 4 * 3));
+        parameters.insert("work_mem", megabytes(work_mem.max(MIN_WORK_MEM)));
+        parameters.insert(
+            "maintenance_work_mem",
+            megabytes((memory / 16).min(MAX_MAINTENANCE_WORK_MEM)),
+        );
+    }
+
+    if let Some(millicores) = limits
+        .and_then(|limits| limits.get("cpu"))
+        .and_then(|cpu| quantity::millicores(cpu))
+    {
+        let cores = millicores.div_ceil(1000).max(1);
+        parameters.insert(
+            "max_worker_processes",
+            cores.max(DEFAULT_MAX_WORKER_PROCESSES).to_string(),
+        );
+        parameters.insert("max_parallel_workers", cores.to_string());
+    }
+
+    parameters
+        .into_iter()
+        .filter(|(name, _)| explicit(name).is_none())
+        .map(|(name, value)| (name.to_string(), value))
+        .collect()
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{}MB", (bytes / MEGABYTE).max(1))
+}
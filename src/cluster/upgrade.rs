@@ -0,0 +1,226 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use super::cleanup;
+use super::storage;
+use super::topology::{ClusterTopology, DataClaim};
+use crate::Error;
+use crate::catalog::{ClusterImages, ImageCatalog};
+use crate::crd::v1::{UpgradeStatus, pgopr};
+use crate::manager::ResourceManager;
+use crate::upgrade_job::{self, UpgradeConfig};
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use k8s_openapi::jiff::Timestamp;
+use kube::Api;
+use log::info;
+
+/// Progress of a major version upgrade
+pub(super) enum Progress {
+    /// The upgrade is still running, with the current step
+    Running(String),
+    /// pg_upgrade failed, the data directory keeps the old version
+    Failed(String),
+    /// The data directory has the new version and the replicas are rebuilt from it
+    Complete,
+    /// The upgrade was rolled back to the old version before pg_upgrade completed
+    Aborted,
+}
+
+/// The upgrade needed to run the requested major version, if any.
+///
+/// An upgrade in progress is continued. Returns the problem when the requested version
+/// cannot be reached from the version of the data directory.
+///
+/// # Arguments
+/// - `pgopr` - The PgOpr resource defining the cluster.
+/// - `version` - The requested major version.
+pub(super) fn plan(pgopr: &pgopr, version: &str) -> Result<Option<UpgradeStatus>, String> {
+    let status = pgopr.status.as_ref();
+    if let Some(upgrade) = status.and_then(|status| status.upgrade.clone()) {
+        return Ok(Some(upgrade));
+    }
+
+    let Some(running) = status.and_then(|status| status.version.as_deref()) else {
+        return Ok(None);
+    };
+    if running == version {
+        return Ok(None);
+    }
+
+    match (running.parse::<u32>(), version.parse::<u32>()) {
+        (Ok(from), Ok(to)) if to > from => Ok(Some(UpgradeStatus {
+            from_version: running.to_string(),
+            to_version: version.to_string(),
+            started: Some(Time(Timestamp::now())),
+        })),
+        (Ok(_), Ok(_)) => Err(format!(
+            "PostgreSQL {} cannot be downgraded to {}",
+            running, version
+        )),
+        _ => Err(format!(
+            "PostgreSQL {} cannot be upgraded to {}",
+            running, version
+        )),
+    }
+}
+
+/// Advances a major version upgrade by one step.
+///
+/// All members are stopped, then a Job runs `pg_upgrade --link` against the data directory
+/// of the primary. Once it succeeded the volumes of the replicas are deleted, so they are
+/// rebuilt from the upgraded primary. Requesting the old version again rolls back an
+/// upgrade whose Job is not running.
+///
+/// # Arguments
+/// - `manager` - The Kubernetes resource manager.
+/// - `pgopr` - The PgOpr resource owning the Job.
+/// - `topology` - The expected cluster topology.
+/// - `upgrade` - The upgrade in progress.
+/// - `version` - The requested major version.
+/// - `catalog` - The image catalog providing the images of both versions.
+pub(super) async fn run(
+    manager: &ResourceManager,
+    pgopr: &pgopr,
+    topology: &ClusterTopology,
+    upgrade: &UpgradeStatus,
+    version: &str,
+    catalog: &ImageCatalog,
+) -> Result<Progress, Error> {
+    let job_api: Api<Job> = Api::namespaced(manager.get_client(), topology.namespace());
+    let job = job_api.get_opt(&topology.upgrade_name()).await?;
+
+    if version == upgrade.from_version {
+        if job.as_ref().is_some_and(is_active) {
+            return Ok(Progress::Running(
+                "pg_upgrade is running, the upgrade can be rolled back once it stopped".to_string(),
+            ));
+        }
+        if !job.as_ref().is_some_and(is_succeeded) {
+            info!(
+                "Rolling back the upgrade of {} to PostgreSQL {}",
+                topology.name(),
+                upgrade.from_version
+            );
+            manager
+                .delete::<Job>(&topology.upgrade_name(), topology.namespace())
+                .await?;
+            return Ok(Progress::Aborted);
+        }
+    }
+
+    let mut stopped = true;
+    for member in topology.members() {
+        stopped &= cleanup::retire_workload::<Deployment>(
+            manager,
+            topology.namespace(),
+            member.name(),
+            "ReplicaSet",
+        )
+        .await?;
+        stopped &= cleanup::retire_workload::<StatefulSet>(
+            manager,
+            topology.namespace(),
+            member.name(),
+            "StatefulSet",
+        )
+        .await?;
+    }
+    if !stopped {
+        return Ok(Progress::Running("Stopping the members".to_string()));
+    }
+
+    let Some(job) = job else {
+        let (Some(from), Some(to)) = (
+            catalog.images(&upgrade.from_version),
+            catalog.images(&upgrade.to_version),
+        ) else {
+            return Ok(Progress::Failed(format!(
+                "The image catalog lacks PostgreSQL {} or {}",
+                upgrade.from_version, upgrade.to_version
+            )));
+        };
+
+        // an image set in the spec belongs to the new version
+        let mut catalog_spec = pgopr.spec.clone();
+        catalog_spec.image_spec.image = None;
+        let from = ClusterImages::new(from, &catalog_spec);
+        let to = ClusterImages::new(to, &pgopr.spec);
+        let primary = topology.primary();
+        let claims = storage::sync_member(manager, pgopr, topology, &primary).await?;
+        let job = upgrade_job::build_job(
+            &topology.upgrade_name(),
+            topology.namespace(),
+            UpgradeConfig {
+                cluster_name: topology.name(),
+                from_version: &upgrade.from_version,
+                to_version: &upgrade.to_version,
+                from_image: &from.primary,
+                to_image: &to.primary,
+                claim_name: claims.data.name(),
+                wal_claim_name: claims.wal.as_ref().map(DataClaim::name),
+            },
+        );
+        info!(
+            "Upgrading {} from PostgreSQL {} to {}",
+            primary.name(),
+            upgrade.from_version,
+            upgrade.to_version
+        );
+        manager.create(pgopr, job).await?;
+        return Ok(Progress::Running("Running pg_upgrade".to_string()));
+    };
+
+    if is_failed(&job) {
+        return Ok(Progress::Failed(format!(
+            "pg_upgrade failed, see the logs of Job {}",
+            topology.upgrade_name()
+        )));
+    }
+    if !is_succeeded(&job) {
+        return Ok(Progress::Running("Running pg_upgrade".to_string()));
+    }
+
+    let primary = topology.primary();
+    for member in topology.members() {
+        if member.name() != primary.name() {
+            info!("Rebuilding {} from the upgraded primary", member.name());
+            cleanup::member_volumes(manager, member.name(), topology.namespace()).await?;
+        }
+    }
+    manager
+        .delete::<Job>(&topology.upgrade_name(), topology.namespace())
+        .await?;
+
+    Ok(Progress::Complete)
+}
+
+fn is_active(job: &Job) -> bool {
+    job.status
+        .as_ref()
+        .and_then(|status| status.active)
+        .unwrap_or(0)
+        > 0
+}
+
+fn is_succeeded(job: &Job) -> bool {
+    job.status
+        .as_ref()
+        .and_then(|status| status.succeeded)
+        .unwrap_or(0)
+        > 0
+}
+
+fn is_failed(job: &Job) -> bool {
+    job.status
+        .as_ref()
+        .and_then(|status| status.failed)
+        .unwrap_or(0)
+        > 0
+}
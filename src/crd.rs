@@ -5,8 +5,9 @@
  *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
+use k8s_openapi::api::core::v1::{Affinity, Toleration};
 use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
-use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, Time};
 use kube::CustomResource;
 use kube::{
     Api, Client, Error,
@@ -24,7 +25,9 @@ pub mod v1 {
     use super::*;
 
     /// The CustomDefinitionResource for the operator
-    #[derive(CustomResource, Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[derive(
+        CustomResource, Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default,
+    )]
     #[kube(
         group = "pgopr.io",
         version = "v1",
@@ -34,26 +37,355 @@ pub mod v1 {
         status = "PgOprStatus",
         namespaced
     )]
+    #[serde(rename_all = "camelCase")]
     pub struct PgOprSpec {
+        /// PostgreSQL major version, selects the images from the image catalog. Defaults to 18.
         pub version: Option<String>,
+        /// Image of the members and defaults for the images of the components
+        #[serde(flatten)]
+        pub image_spec: ImageSpec,
         /// General settings across all components
         pub storage: u32,
+        /// Storage class of the volume claims. The default class of the Kubernetes cluster
+        /// provisions the volumes when absent.
+        pub storage_class_name: Option<String>,
+        /// Access mode of the volume claims. Defaults to ReadWriteOnce.
+        pub access_mode: Option<AccessMode>,
+        /// Volume mode of the volume claims. Defaults to Filesystem.
+        pub volume_mode: Option<VolumeMode>,
+        /// hostPath volumes on the node, for development clusters without a provisioner
+        pub local_storage: Option<LocalStorageSpec>,
+        /// Separate volume per member holding the WAL
+        pub wal_storage: Option<WalStorageSpec>,
         /// Number of replicas in the star configuration
         pub replicas: Option<u32>,
-        /// CPU/Memory limits. Validated via schemars.
+        /// Replicas by name with their own settings, in place of `replicas`. A replica named
+        /// `<N>` is the member `replicas` creates as the N-th replica.
+        pub replica_specs: Option<Vec<ReplicaSpec>>,
+        /// Replicas streaming from another member instead of the primary, from the name of
+        /// the replica to the name of its upstream, such as
+        /// `mycluster-replica-3: mycluster-replica-1`
+        pub upstreams: Option<BTreeMap<String, String>>,
+        /// Replicas applying the WAL only after a delay, as protection against logical
+        /// mistakes such as a dropped table
+        pub delayed_replicas: Option<DelayedReplicasSpec>,
+        /// CPU/Memory limits and requests. Validated via schemars and at reconcile time.
         pub resources: Option<ResourceRequirements>,
         /// Postgres paramters. Delivered via versioned ConfigMaps.
         pub config: Option<BTreeMap<String, String>>,
+        /// Client authentication rules of pg_hba.conf, after the rules of the operator. Those
+        /// accept `postgres` through the local socket with peer authentication and the
+        /// replication, backup and pgexporter roles with a password. All addresses may
+        /// connect with a password when absent.
+        pub hba: Option<Vec<HbaRule>>,
+        /// Retained WAL of an inactive replication slot that raises the SlotsRetainingWal
+        /// condition, as a quantity such as `1Gi`. Defaults to 1Gi.
+        pub slot_wal_warning: Option<String>,
+        /// Synchronous replication to the replicas. Commits do not wait for the replicas
+        /// when absent.
+        pub replication: Option<ReplicationSpec>,
+        /// Number of previous configuration ConfigMaps kept besides the current one. Defaults
+        /// to 3.
+        pub config_history_limit: Option<u32>,
         /// pgmoneta backup configuration
         pub pgmoneta: Option<PgMonetaSpec>,
         /// pgexporter configuration
         pub pgexporter: Option<PgExporterSpec>,
+        /// Existing Secret with the superuser credentials
+        pub superuser_secret: Option<SecretReference>,
+        /// Existing Secret with the application user credentials
+        pub app_user_secret: Option<SecretReference>,
+        /// Existing Secret with the replication user credentials
+        pub replication_secret: Option<SecretReference>,
+        /// Existing Secret with the backup user credentials
+        pub backup_secret: Option<SecretReference>,
+        /// Existing Secret with the pgexporter user credentials
+        pub exporter_secret: Option<SecretReference>,
+        /// Rotation policy for the generated credentials
+        pub credential_rotation: Option<CredentialRotationSpec>,
+        /// Automatic failover of the primary
+        pub failover: Option<FailoverSpec>,
+        /// Workload running each member. Defaults to Deployment.
+        pub workload: Option<WorkloadKind>,
+        /// How the primary takes a new image once all replicas run it. Defaults to Restart.
+        pub primary_update_method: Option<PrimaryUpdateMethod>,
+    }
+
+    /// A client authentication rule of pg_hba.conf
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    pub struct HbaRule {
+        /// Connection type
+        #[serde(rename = "type")]
+        pub type_: HbaType,
+        /// Databases the rule matches, `all`, `replication` or a comma-separated list
+        pub database: String,
+        /// Users the rule matches, `all`, `+group` or a comma-separated list
+        pub user: String,
+        /// Client addresses in CIDR notation, `all`, `samehost` or `samenet`. Absent for
+        /// `local` rules.
+        pub cidr: Option<String>,
+        /// Authentication method
+        pub method: HbaMethod,
+    }
+
+    /// Connection type of a pg_hba.conf rule
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    pub enum HbaType {
+        Local,
+        Host,
+        HostSsl,
+        HostNoSsl,
+        HostGssEnc,
+        HostNoGssEnc,
+    }
+
+    impl HbaType {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                HbaType::Local => "local",
+                HbaType::Host => "host",
+                HbaType::HostSsl => "hostssl",
+                HbaType::HostNoSsl => "hostnossl",
+                HbaType::HostGssEnc => "hostgssenc",
+                HbaType::HostNoGssEnc => "hostnogssenc",
+            }
+        }
+    }
+
+    /// Authentication method of a pg_hba.conf rule
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema)]
+    pub enum HbaMethod {
+        #[serde(rename = "trust")]
+        Trust,
+        #[serde(rename = "reject")]
+        Reject,
+        #[serde(rename = "scram-sha-256")]
+        ScramSha256,
+        #[serde(rename = "md5")]
+        Md5,
+        #[serde(rename = "password")]
+        Password,
+        #[serde(rename = "gss")]
+        Gss,
+        #[serde(rename = "sspi")]
+        Sspi,
+        #[serde(rename = "ident")]
+        Ident,
+        #[serde(rename = "peer")]
+        Peer,
+        #[serde(rename = "ldap")]
+        Ldap,
+        #[serde(rename = "radius")]
+        Radius,
+        #[serde(rename = "cert")]
+        Cert,
+        #[serde(rename = "pam")]
+        Pam,
+    }
+
+    impl HbaMethod {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                HbaMethod::Trust => "trust",
+                HbaMethod::Reject => "reject",
+                HbaMethod::ScramSha256 => "scram-sha-256",
+                HbaMethod::Md5 => "md5",
+                HbaMethod::Password => "password",
+                HbaMethod::Gss => "gss",
+                HbaMethod::Sspi => "sspi",
+                HbaMethod::Ident => "ident",
+                HbaMethod::Peer => "peer",
+                HbaMethod::Ldap => "ldap",
+                HbaMethod::Radius => "radius",
+                HbaMethod::Cert => "cert",
+                HbaMethod::Pam => "pam",
+            }
+        }
+    }
+
+    /// How the primary is updated at the end of a rolling update
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema, Default)]
+    pub enum PrimaryUpdateMethod {
+        /// The primary is restarted with the new image
+        #[default]
+        Restart,
+        /// The primary role moves to an updated replica first
+        Switchover,
+    }
+
+    /// Kind of workload running a PostgreSQL member
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema, Default)]
+    pub enum WorkloadKind {
+        #[default]
+        Deployment,
+        StatefulSet,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema, Default)]
+    pub enum AccessMode {
+        #[default]
+        ReadWriteOnce,
+        ReadWriteOncePod,
+        ReadWriteMany,
+    }
+
+    impl AccessMode {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                AccessMode::ReadWriteOnce => "ReadWriteOnce",
+                AccessMode::ReadWriteOncePod => "ReadWriteOncePod",
+                AccessMode::ReadWriteMany => "ReadWriteMany",
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema, Default)]
+    pub enum VolumeMode {
+        #[default]
+        Filesystem,
+        Block,
+    }
+
+    impl VolumeMode {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                VolumeMode::Filesystem => "Filesystem",
+                VolumeMode::Block => "Block",
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    pub struct WalStorageSpec {
+        /// Size in GiB
+        pub size: u32,
+        /// Storage class of the WAL claims. Defaults to storageClassName.
+        pub storage_class_name: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    pub struct LocalStorageSpec {
+        /// Directory on the node holding the volumes, each cluster uses its own
+        /// subdirectories. Defaults to /tmp/kind.
+        pub path: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    pub struct FailoverSpec {
+        /// Promote a replica when the primary is unavailable. Defaults to true.
+        pub enabled: Option<bool>,
+        /// Seconds the primary may be unavailable before failing over. Defaults to 30.
+        pub grace_period_seconds: Option<u32>,
+    }
+
+    /// A replica with its own settings
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ReplicaSpec {
+        /// Name of the replica, the member is named `<cluster>-replica-<name>`. Names ending in
+        /// `-wal` are taken by the WAL volumes.
+        pub name: String,
+        /// CPU/Memory limits and requests. Defaults to `spec.resources`.
+        pub resources: Option<ResourceRequirements>,
+        /// Nodes the replica may run on
+        #[serde(flatten)]
+        pub placement: PodPlacement,
+        /// Reloadable PostgreSQL parameters of the replica, over those of `spec.config`
+        pub config: Option<BTreeMap<String, String>>,
+        /// Apply the WAL only after `delayedReplicas.applyDelay`
+        pub delayed: Option<bool>,
+    }
+
+    /// Nodes a pod may run on
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PodPlacement {
+        /// Labels of the nodes the pod may run on
+        pub node_selector: Option<BTreeMap<String, String>>,
+        /// Taints of the nodes the pod tolerates
+        pub tolerations: Option<Vec<Toleration>>,
+        /// Node and pod affinity of the pod
+        pub affinity: Option<Affinity>,
+    }
+
+    /// Replicas applying the WAL only after a delay
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DelayedReplicasSpec {
+        /// Names of the delayed replicas, besides those of `replicaSpecs` marked delayed
+        #[serde(default)]
+        pub members: Vec<String>,
+        /// recovery_min_apply_delay of the delayed replicas, such as `1h`
+        pub apply_delay: String,
+    }
+
+    /// Synchronous replication to the replicas
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ReplicationSpec {
+        /// How commits wait for the replicas. Defaults to async.
+        pub mode: Option<ReplicationMode>,
+        /// Number of replicas confirming each commit in sync and quorum mode. Defaults to 1.
+        pub synchronous_standbys: Option<u32>,
+        /// Whether commits wait for missing replicas or continue with fewer. Defaults to
+        /// durability.
+        pub preference: Option<ReplicationPreference>,
+    }
+
+    /// How commits wait for the replicas
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema, Default)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ReplicationMode {
+        /// Commits do not wait for the replicas
+        #[default]
+        Async,
+        /// Commits wait for the first replicas in the list
+        Sync,
+        /// Commits wait for any of the replicas
+        Quorum,
+    }
+
+    /// What synchronous replication gives up while replicas are missing
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema, Default)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ReplicationPreference {
+        /// Commits wait until enough replicas confirm them
+        #[default]
+        Durability,
+        /// Commits wait for the streaming replicas only, and for none once none streams
+        Availability,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CredentialRotationSpec {
+        /// Days between rotations of the generated passwords
+        pub interval_days: Option<u32>,
+    }
+
+    /// Reference to an existing Secret holding a username and password
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SecretReference {
+        /// Name of the Secret in the namespace of the cluster
+        pub name: String,
+        /// Key of the username. Defaults to `username`.
+        pub username_key: Option<String>,
+        /// Key of the password. Defaults to `password`.
+        pub password_key: Option<String>,
     }
 
     #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
     pub struct PgMonetaSpec {
         /// Storage size in GiB. Defaults to 10 if absent.
         pub storage: Option<u32>,
+        #[serde(flatten)]
+        pub image_spec: ImageSpec,
     }
 
     #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
@@ -62,27 +394,100 @@ pub mod v1 {
         pub resources: Option<ResourceRequirements>,
         // monitoring resources ResourceRequirements
         pub monitoring: Option<PgExporterMonitoringSpec>,
+        #[serde(flatten)]
+        pub image_spec: ImageSpec,
     }
 
     #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
     pub struct PgExporterMonitoringSpec {
         pub resources: Option<ResourceRequirements>,
+        #[serde(flatten)]
+        pub image_spec: ImageSpec,
+    }
+
+    /// Image of a component and how it is pulled
+    ///
+    /// The registry, pull policy and pull secrets of a component default to those of the
+    /// cluster.
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ImageSpec {
+        /// Image replacing the one from the image catalog
+        pub image: Option<String>,
+        /// Registry prefixed to images that do not name one
+        pub image_registry: Option<String>,
+        /// Pull policy of the image. Defaults to IfNotPresent.
+        pub image_pull_policy: Option<ImagePullPolicy>,
+        /// Secrets with the credentials of the registry
+        pub image_pull_secrets: Option<Vec<String>>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema, Default)]
+    pub enum ImagePullPolicy {
+        Always,
+        #[default]
+        IfNotPresent,
+        Never,
+    }
+
+    impl ImagePullPolicy {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                ImagePullPolicy::Always => "Always",
+                ImagePullPolicy::IfNotPresent => "IfNotPresent",
+                ImagePullPolicy::Never => "Never",
+            }
+        }
     }
 
     /// The status of the PgOpr resource
     #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
     pub struct PgOprStatus {
-        /// Current phase (e.g., Pending, Running, Failed)
+        /// Current phase (e.g., Pending, Running, Failed, Upgrading, UpgradeFailed)
         pub phase: String,
+        /// PostgreSQL major version of the data directory
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub version: Option<String>,
+        /// Major version upgrade in progress
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub upgrade: Option<UpgradeStatus>,
+        /// Member currently running as primary
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub current_primary: Option<String>,
+        /// Failover state of the cluster
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub failover: Option<FailoverStatus>,
         /// Status of the primary deployment
         #[serde(skip_serializing_if = "Option::is_none")]
         pub primary: Option<DeploymentStatus>,
         /// List of replica deployment statuses
         #[serde(default)]
         pub replicas: Vec<DeploymentStatus>,
+        /// Rolling update of the member images in progress
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub rollout: Option<RolloutStatus>,
+        /// Configuration of the spec and the one each member runs
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub config: Option<ConfigStatus>,
+        /// Physical replication slots on the primary
+        #[serde(default)]
+        pub replication_slots: Vec<ReplicationSlotStatus>,
+        /// Replication slots of the members, the operator drops them once inactive and no
+        /// member streams through them. Other slots are left alone.
+        #[serde(default)]
+        pub managed_slots: Vec<String>,
+        /// Synchronous replication of the primary
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub replication: Option<ReplicationStatus>,
+        /// Replicas applying the WAL only after a delay
+        #[serde(default)]
+        pub delayed_replicas: Vec<DelayedReplicaStatus>,
         /// List of service statuses
         #[serde(default)]
         pub services: Vec<ServiceStatus>,
+        /// Services selecting the members by role
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub endpoints: Option<EndpointStatus>,
         /// List of storage statuses
         #[serde(default)]
         pub storage: Vec<StorageStatus>,
@@ -95,6 +500,144 @@ pub mod v1 {
         /// Status of pgexporter
         #[serde(skip_serializing_if = "Option::is_none")]
         pub pgexporter: Option<PgExporterStatus>,
+        /// List of credential statuses
+        #[serde(default)]
+        pub credentials: Vec<CredentialStatus>,
+    }
+
+    /// Failover state of the cluster
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+    pub struct FailoverStatus {
+        /// Time the primary was first seen unavailable
+        pub primary_unavailable_since: Option<Time>,
+        /// Time of the last promotion of a replica
+        pub last_failover: Option<Time>,
+        /// Time of the last planned switchover
+        pub last_switchover: Option<Time>,
+        /// Planned switchover in progress
+        pub switchover: Option<SwitchoverStatus>,
+        /// Former primaries kept scaled down to avoid a split brain
+        #[serde(default)]
+        pub fenced: Vec<String>,
+    }
+
+    /// Planned switchover, continued on each reconcile until the target is promoted
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    pub struct SwitchoverStatus {
+        /// Replica taking over the primary role
+        pub target: String,
+        /// Step the switchover waits for
+        pub phase: SwitchoverPhase,
+        /// Last WAL location of the old primary, read once it restarted as a standby
+        pub lsn: Option<String>,
+        /// Time the phase started
+        pub since: Time,
+    }
+
+    /// Step of a planned switchover
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, JsonSchema)]
+    pub enum SwitchoverPhase {
+        /// The primary shuts down to restart as a standby of the target
+        Demoting,
+        /// The target replays the WAL of the old primary up to its shutdown
+        CatchingUp,
+        /// The target is promoted
+        Promoting,
+    }
+
+    /// Rolling update of the member images, replicas first and the primary last
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+    pub struct RolloutStatus {
+        /// Members running their new image
+        #[serde(default)]
+        pub updated: Vec<String>,
+        /// Members still running their old image
+        #[serde(default)]
+        pub pending: Vec<String>,
+        /// Member being updated, the next one waits until it is ready and caught up
+        pub current: Option<String>,
+    }
+
+    /// Configuration of the spec and the one each member runs
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+    pub struct ConfigStatus {
+        /// ConfigMap holding the configuration of the spec
+        pub config_map: Option<String>,
+        /// Hash of the configuration of the spec
+        pub hash: Option<String>,
+        /// Parameters derived from the resource limits, spec.config takes precedence
+        #[serde(default)]
+        pub tuned: BTreeMap<String, String>,
+        /// Configuration of each member
+        #[serde(default)]
+        pub members: Vec<MemberConfigStatus>,
+    }
+
+    /// Configuration a member runs
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    pub struct MemberConfigStatus {
+        pub name: String,
+        /// Hash of the configuration in effect, absent while the member is not running
+        pub hash: Option<String>,
+        /// The member needs a restart to run the configuration of the spec
+        pub pending_restart: bool,
+        /// Parameters the member reports as waiting for a restart
+        #[serde(default)]
+        pub pending_parameters: Vec<String>,
+    }
+
+    /// A physical replication slot on the primary
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    pub struct ReplicationSlotStatus {
+        pub name: String,
+        /// A server streams through the slot
+        pub active: bool,
+        /// WAL kept on the primary for the slot, in bytes
+        pub retained_bytes: Option<i64>,
+    }
+
+    /// Synchronous replication of the primary
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    pub struct ReplicationStatus {
+        /// synchronous_standby_names of the primary, empty when commits do not wait
+        pub standby_names: String,
+        /// Number of replicas the spec asks to confirm each commit
+        pub required: u32,
+        /// Replicas the primary currently waits for
+        #[serde(default)]
+        pub synchronous: Vec<String>,
+    }
+
+    /// A replica applying the WAL only after a delay
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    pub struct DelayedReplicaStatus {
+        pub name: String,
+        /// recovery_min_apply_delay in effect
+        pub apply_delay: String,
+        /// Seconds since the last replayed transaction was committed on the primary
+        pub delay_seconds: Option<i64>,
+        /// Replay is paused through `pgopr pause`
+        pub replay_paused: bool,
+    }
+
+    /// Major version upgrade of the cluster
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    pub struct UpgradeStatus {
+        /// Major version the data directory is upgraded from
+        pub from_version: String,
+        /// Major version the data directory is upgraded to
+        pub to_version: String,
+        /// Time the upgrade started
+        pub started: Option<Time>,
+    }
+
+    /// Status of a database credential
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    pub struct CredentialStatus {
+        pub secret: String,
+        pub role: Option<String>,
+        pub generated: bool,
+        pub last_rotation: Option<Time>,
     }
 
     #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
@@ -115,9 +658,33 @@ pub mod v1 {
         pub reason: Option<String>,
         pub message: Option<String>,
     }
+    /// CPU and memory of a container, with the quantities as in Kubernetes such as `2` or `16Gi`.
+    ///
+    /// Limits and requests take at most 8 entries each, the three resources and hugepage
+    /// sizes, which bounds the cost the API server estimates for the validation rules.
     #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[schemars(extend("x-kubernetes-validations" = [{
+        "rule": "!has(self.limits) || !has(self.requests) || self.requests.all(name, !(name in self.limits) || !isQuantity(self.requests[name]) || !isQuantity(self.limits[name]) || quantity(self.requests[name]).compareTo(quantity(self.limits[name])) <= 0)",
+        "message": "requests cannot exceed limits"
+    }]))]
     pub struct ResourceRequirements {
+        /// Upper bounds of cpu, memory, ephemeral-storage and hugepages-<size>
+        #[schemars(extend("maxProperties" = 8, "x-kubernetes-validations" = [
+            {
+                "rule": "self.all(name, name in ['cpu', 'memory', 'ephemeral-storage'] || name.startsWith('hugepages-'))",
+                "message": "resources are cpu, memory, ephemeral-storage or hugepages-<size>"
+            },
+            { "rule": "self.all(name, isQuantity(self[name]))", "message": "invalid quantity" }
+        ]))]
         pub limits: Option<BTreeMap<String, String>>,
+        /// Guaranteed amounts of cpu, memory, ephemeral-storage and hugepages-<size>
+        #[schemars(extend("maxProperties" = 8, "x-kubernetes-validations" = [
+            {
+                "rule": "self.all(name, name in ['cpu', 'memory', 'ephemeral-storage'] || name.startsWith('hugepages-'))",
+                "message": "resources are cpu, memory, ephemeral-storage or hugepages-<size>"
+            },
+            { "rule": "self.all(name, isQuantity(self[name]))", "message": "invalid quantity" }
+        ]))]
         pub requests: Option<BTreeMap<String, String>>,
     }
 
@@ -141,12 +708,70 @@ pub mod v1 {
         pub ready: bool,
     }
 
+    /// Names of the Services selecting the members by role
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    pub struct EndpointStatus {
+        /// Service selecting the primary
+        pub read_write: String,
+        /// Service selecting the replicas
+        pub read_only: String,
+        /// Service selecting any member
+        pub read: String,
+    }
+
     /// Status of a Storage resource (PV or PVC)
     #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
     pub struct StorageStatus {
         pub name: String,
         pub kind: String,
         pub bound: bool,
+        /// Size requested by the claim
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub requested: Option<String>,
+        /// Size of the volume
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub capacity: Option<String>,
+        /// Expansion in progress, Resizing or FileSystemResizePending. Cleared once the
+        /// capacity reached the requested size.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub resize: Option<String>,
+    }
+
+    /// Cluster-wide overrides of the built-in PostgreSQL image catalog
+    #[derive(
+        CustomResource, Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default,
+    )]
+    #[kube(
+        group = "pgopr.io",
+        version = "v1",
+        kind = "PgOprImageCatalog",
+        plural = "pgoprimagecatalogs",
+        derive = "PartialEq"
+    )]
+    #[serde(rename_all = "camelCase")]
+    pub struct PgOprImageCatalogSpec {
+        /// Images per PostgreSQL major version
+        pub images: Vec<CatalogEntry>,
+    }
+
+    /// Images of one PostgreSQL major version
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CatalogEntry {
+        /// PostgreSQL major version, e.g. "16"
+        pub version: String,
+        #[serde(flatten)]
+        pub images: ImageOverride,
+    }
+
+    /// Images replacing the catalog defaults, absent images keep the default
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ImageOverride {
+        pub primary: Option<String>,
+        pub replica: Option<String>,
+        pub pgmoneta: Option<String>,
+        pub pgexporter: Option<String>,
     }
 
     /// The general settings
@@ -159,39 +784,57 @@ pub mod v1 {
     }
 }
 
-/// Create or update the CustomResourceDefinition object
+/// Name of the PgOpr CustomResourceDefinition
+const CRD_PGOPR: &str = "pgoprs.pgopr.io";
+/// Name of the PgOprImageCatalog CustomResourceDefinition
+const CRD_IMAGE_CATALOG: &str = "pgoprimagecatalogs.pgopr.io";
+
+/// Create or update the CustomResourceDefinition objects
 ///
 /// # Arguments
 /// - `client` - The Kubernetes client
 pub async fn crd_deploy(client: Client) -> Result<CustomResourceDefinition, Error> {
-    let crd: CustomResourceDefinition = v1::pgopr::crd();
+    apply_crd(client.clone(), v1::PgOprImageCatalog::crd()).await?;
+    apply_crd(client, v1::pgopr::crd()).await
+}
+
+/// Create or update a CustomResourceDefinition object and wait until it is established
+///
+/// # Arguments
+/// - `client` - The Kubernetes client
+/// - `crd` - The CustomResourceDefinition
+async fn apply_crd(
+    client: Client,
+    crd: CustomResourceDefinition,
+) -> Result<CustomResourceDefinition, Error> {
     trace!("{:#?}", crd);
+    let name = crd.metadata.name.clone().unwrap_or_default();
 
     let crd_api: Api<CustomResourceDefinition> = Api::all(client.clone());
     let result: Result<CustomResourceDefinition, Error> =
         match crd_api.create(&PostParams::default(), &crd).await {
             Ok(crd) => {
-                info!("Created CRD");
+                info!("Created CRD {}", name);
                 Ok(crd)
             }
             Err(Error::Api(err)) if err.code == 409 => {
                 let patch = Patch::Merge(&crd);
                 let crd = crd_api
-                    .patch("pgoprs.pgopr.io", &PatchParams::default(), &patch)
+                    .patch(&name, &PatchParams::default(), &patch)
                     .await?;
-                info!("Updated CRD");
+                info!("Updated CRD {}", name);
                 Ok(crd)
             }
             Err(err) => Err(err),
         };
 
-    let establish = await_condition(crd_api, "pgoprs.pgopr.io", conditions::is_crd_established());
+    let establish = await_condition(crd_api, &name, conditions::is_crd_established());
     let _ = tokio::time::timeout(std::time::Duration::from_secs(10), establish).await;
 
     result
 }
 
-/// Delete the CustomResourceDefinition object
+/// Delete the CustomResourceDefinition objects
 ///
 /// # Arguments:
 /// - `client` - The Kubernetes client
@@ -199,14 +842,23 @@ pub async fn crd_deploy(client: Client) -> Result<CustomResourceDefinition, Erro
 /// Note: It is assumed the deployment exists for simplicity. Otherwise returns an Error.
 pub async fn crd_undeploy(client: Client) -> Result<(), Error> {
     let api: Api<CustomResourceDefinition> = Api::all(client);
+    match api.delete(CRD_PGOPR, &DeleteParams::default()).await {
+        Ok(_) => {
+            info!("Deleted CRD");
+        }
+
+        Err(e) => return Err(e),
+    }
+
+    // installations from older versions do not have the catalog
     match api
-        .delete("pgoprs.pgopr.io", &DeleteParams::default())
+        .delete(CRD_IMAGE_CATALOG, &DeleteParams::default())
         .await
     {
         Ok(_) => {
-            info!("Deleted CRD");
+            info!("Deleted image catalog CRD");
         }
-
+        Err(Error::Api(err)) if err.code == 404 => {}
         Err(e) => return Err(e),
     }
 
@@ -217,4 +869,9 @@ pub async fn crd_undeploy(client: Client) -> Result<(), Error> {
 pub fn crd_generate() {
     let data = serde_yaml::to_string(&v1::pgopr::crd()).expect("Can't serialize pgopr-crd.yaml");
     fs::write("pgopr-crd.yaml", data).expect("Unable to write file: pgopr-crd.yaml");
+
+    let data = serde_yaml::to_string(&v1::PgOprImageCatalog::crd())
+        .expect("Can't serialize pgopr-image-catalog-crd.yaml");
+    fs::write("pgopr-image-catalog-crd.yaml", data)
+        .expect("Unable to write file: pgopr-image-catalog-crd.yaml");
 }
@@ -0,0 +1,220 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use crate::crd::v1::{SecretReference, pgopr};
+use crate::manager::LABEL_CLUSTER;
+use k8s_openapi::api::core::v1::{EnvVar, EnvVarSource, Secret, SecretKeySelector};
+use kube::ResourceExt;
+use kube::api::ObjectMeta;
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use std::collections::BTreeMap;
+
+/// Keys used by the operator-generated basic-auth Secrets
+pub const USERNAME_KEY: &str = "username";
+pub const PASSWORD_KEY: &str = "password";
+
+const SECRET_TYPE_BASIC_AUTH: &str = "kubernetes.io/basic-auth";
+const PASSWORD_LENGTH: usize = 32;
+
+/// Database roles created by the PostgreSQL images
+pub const SUPERUSER: &str = "postgres";
+pub const APP_USER: &str = "myuser";
+pub const REPLICATION_USER: &str = "repl_user";
+pub const BACKUP_USER: &str = "backup_user";
+pub const EXPORTER_USER: &str = "pgexporter";
+
+const APP_SECRET_SUFFIX: &str = "app-credentials";
+const REPLICATION_SECRET_SUFFIX: &str = "replication-credentials";
+const BACKUP_SECRET_SUFFIX: &str = "backup-credentials";
+const EXPORTER_SECRET_SUFFIX: &str = "exporter-credentials";
+
+/// Credential references the Secret holding the username and password of a database role.
+pub struct Credential {
+    /// Whether the Secret is generated and owned by the operator
+    pub generated: bool,
+    /// The role name stored in a generated Secret
+    pub default_username: &'static str,
+    /// Name of the Secret
+    pub secret_name: String,
+    /// Key of the username inside the Secret
+    pub username_key: String,
+    /// Key of the password inside the Secret
+    pub password_key: String,
+}
+
+impl Credential {
+    fn generated(cluster_name: &str, suffix: &str, default_username: &'static str) -> Self {
+        Self {
+            generated: true,
+            default_username,
+            secret_name: format!("{}-{}", cluster_name, suffix),
+            username_key: USERNAME_KEY.to_string(),
+            password_key: PASSWORD_KEY.to_string(),
+        }
+    }
+
+    fn referenced(reference: &SecretReference, default_username: &'static str) -> Self {
+        Self {
+            generated: false,
+            default_username,
+            secret_name: reference.name.clone(),
+            username_key: reference
+                .username_key
+                .clone()
+                .unwrap_or_else(|| USERNAME_KEY.to_string()),
+            password_key: reference
+                .password_key
+                .clone()
+                .unwrap_or_else(|| PASSWORD_KEY.to_string()),
+        }
+    }
+
+    /// Builds an environment variable reading the username from the Secret.
+    ///
+    /// # Arguments
+    /// - `name` - Name of the environment variable
+    pub fn username_env(&self, name: &str) -> EnvVar {
+        secret_env(name, &self.secret_name, &self.username_key)
+    }
+
+    /// Builds an environment variable reading the password from the Secret.
+    ///
+    /// # Arguments
+    /// - `name` - Name of the environment variable
+    pub fn password_env(&self, name: &str) -> EnvVar {
+        secret_env(name, &self.secret_name, &self.password_key)
+    }
+}
+
+/// Credentials groups the database roles used by a cluster.
+pub struct Credentials {
+    pub superuser: Option<Credential>,
+    pub app: Credential,
+    pub replication: Credential,
+    pub backup: Credential,
+    pub exporter: Credential,
+}
+
+impl Credentials {
+    /// Builds the references to the operator-generated Secrets of a cluster.
+    ///
+    /// # Arguments
+    /// - `cluster_name` - The name of the cluster
+    pub fn generated(cluster_name: &str) -> Self {
+        Self {
+            superuser: None,
+            app: Credential::generated(cluster_name, APP_SECRET_SUFFIX, APP_USER),
+            replication: Credential::generated(
+                cluster_name,
+                REPLICATION_SECRET_SUFFIX,
+                REPLICATION_USER,
+            ),
+            backup: Credential::generated(cluster_name, BACKUP_SECRET_SUFFIX, BACKUP_USER),
+            exporter: Credential::generated(cluster_name, EXPORTER_SECRET_SUFFIX, EXPORTER_USER),
+        }
+    }
+
+    /// Builds the credential references of a cluster, preferring the Secrets named in the spec.
+    ///
+    /// # Arguments
+    /// - `pgopr` - The PgOpr resource defining the cluster
+    pub fn from_pgopr(pgopr: &pgopr) -> Self {
+        let spec = &pgopr.spec;
+        let mut credentials = Self::generated(&pgopr.name_any());
+
+        credentials.superuser = spec
+            .superuser_secret
+            .as_ref()
+            .map(|reference| Credential::referenced(reference, SUPERUSER));
+        if let Some(reference) = &spec.app_user_secret {
+            credentials.app = Credential::referenced(reference, APP_USER);
+        }
+        if let Some(reference) = &spec.replication_secret {
+            credentials.replication = Credential::referenced(reference, REPLICATION_USER);
+        }
+        if let Some(reference) = &spec.backup_secret {
+            credentials.backup = Credential::referenced(reference, BACKUP_USER);
+        }
+        if let Some(reference) = &spec.exporter_secret {
+            credentials.exporter = Credential::referenced(reference, EXPORTER_USER);
+        }
+
+        credentials
+    }
+
+    /// All credentials of the cluster
+    pub fn all(&self) -> Vec<&Credential> {
+        let mut all = Vec::new();
+        if let Some(superuser) = &self.superuser {
+            all.push(superuser);
+        }
+        all.extend([&self.app, &self.replication, &self.backup, &self.exporter]);
+        all
+    }
+}
+
+/// Generates a random alphanumeric password
+pub fn generate_password() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(PASSWORD_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Builds a basic-auth secret for a database role
+///
+/// # Arguments
+/// - `name` - Name of the secret
+/// - `namespace` - Namespace
+/// - `cluster_name` - The name of the cluster owning the role
+/// - `username` - The role name
+/// - `password` - The role password
+pub fn build_secret(
+    name: &str,
+    namespace: &str,
+    cluster_name: &str,
+    username: &str,
+    password: &str,
+) -> Secret {
+    let mut labels: BTreeMap<String, String> = BTreeMap::new();
+    labels.insert("app".to_owned(), name.to_owned());
+    labels.insert(LABEL_CLUSTER.to_string(), cluster_name.to_string());
+
+    let mut string_data = BTreeMap::new();
+    string_data.insert(USERNAME_KEY.to_string(), username.to_string());
+    string_data.insert(PASSWORD_KEY.to_string(), password.to_string());
+
+    Secret {
+        metadata: ObjectMeta {
+            name: Some(name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(labels),
+            ..ObjectMeta::default()
+        },
+        type_: Some(SECRET_TYPE_BASIC_AUTH.to_string()),
+        string_data: Some(string_data),
+        ..Secret::default()
+    }
+}
+
+fn secret_env(name: &str, secret_name: &str, key: &str) -> EnvVar {
+    EnvVar {
+        name: name.to_string(),
+        value_from: Some(EnvVarSource {
+            secret_key_ref: Some(SecretKeySelector {
+                name: secret_name.to_string(),
+                key: key.to_string(),
+                ..SecretKeySelector::default()
+            }),
+            ..EnvVarSource::default()
+        }),
+        ..EnvVar::default()
+    }
+}
@@ -5,14 +5,17 @@
  *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
-use crate::crd::v1::PgOprSpec;
+use crate::cluster;
 use crate::crd::v1::pgopr;
+use crate::crd::v1::{LocalStorageSpec, PgOprSpec, ReplicaSpec};
 use crate::k8s;
+use crate::manager;
 use kube::{
     Api, Client,
     api::{DeleteParams, Patch, PatchParams, PostParams},
 };
 use log::error;
+use std::collections::BTreeSet;
 
 const DEFAULT_CLUSTER_NAME: &str = "postgresql";
 const DEFAULT_NAMESPACE: &str = "default";
@@ -49,13 +52,11 @@ async fn create_cluster(
     let mut cluster = pgopr::new(
         name,
         PgOprSpec {
-            version: None,
             storage: DEFAULT_STORAGE_GI,
+            // development clusters such as kind have no provisioner
+            local_storage: Some(LocalStorageSpec { path: None }),
             replicas: Some(replicas),
-            resources: None,
-            config: None,
-            pgmoneta: None,
-            pgexporter: None,
+            ..Default::default()
         },
     );
     cluster.metadata.namespace = Some(namespace.to_string());
@@ -90,6 +91,46 @@ async fn patch_replicas(
         .map_err(crate::Error::from)
 }
 
+/// Replaces the replica specs on an existing PgOpr resource, keeping the count in line.
+///
+/// # Arguments
+/// - `client` - Kubernetes client to modify the PgOpr resource with.
+/// - `name` - Name of the PgOpr resource to modify.
+/// - `namespace` - Namespace where the PgOpr resource resides.
+/// - `replica_specs` - Desired replicas.
+async fn patch_replica_specs(
+    client: Client,
+    name: &str,
+    namespace: &str,
+    replica_specs: &[ReplicaSpec],
+) -> Result<pgopr, crate::Error> {
+    let api: Api<pgopr> = Api::namespaced(client, namespace);
+    let patch = serde_json::json!({
+        "spec": {
+            "replicas": replica_specs.len(),
+            "replicaSpecs": replica_specs
+        }
+    });
+
+    api.patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await
+        .map_err(crate::Error::from)
+}
+
+/// A replica spec without settings of its own
+///
+/// # Arguments
+/// - `name` - Name of the replica.
+fn replica_spec(name: String) -> ReplicaSpec {
+    ReplicaSpec {
+        name,
+        resources: None,
+        placement: Default::default(),
+        config: None,
+        delayed: None,
+    }
+}
+
 /// Gets the PgOpr resource.
 ///
 /// # Arguments
@@ -325,10 +366,21 @@ pub async fn handle_provision_replica() {
     let client: Client = k8s::k8s_client().await;
     match get_cluster(client.clone(), DEFAULT_CLUSTER_NAME, DEFAULT_NAMESPACE).await {
         Ok(current) => {
-            let replicas = current.spec.replicas.unwrap_or(0) + 1;
-            if let Err(err) =
+            let result = if let Some(mut specs) = current.spec.replica_specs {
+                // the new replica takes the next ordinal as its name
+                let next = specs
+                    .iter()
+                    .filter_map(|spec| spec.name.parse::<u32>().ok())
+                    .max()
+                    .unwrap_or(0)
+                    + 1;
+                specs.push(replica_spec(next.to_string()));
+                patch_replica_specs(client, DEFAULT_CLUSTER_NAME, DEFAULT_NAMESPACE, &specs).await
+            } else {
+                let replicas = current.spec.replicas.unwrap_or(0) + 1;
                 patch_replicas(client, DEFAULT_CLUSTER_NAME, DEFAULT_NAMESPACE, replicas).await
-            {
+            };
+            if let Err(err) = result {
                 error!("Unable to patch PgOpr replicas: {:?}", err);
             }
         }
@@ -347,18 +399,142 @@ pub async fn handle_provision_replica() {
 }
 
 /// Removes the replica database components through a PgOpr resource.
-pub async fn handle_retire_replica() {
+///
+/// A named replica is removed from the replica specs, the replicas of a count become replica
+/// specs named by their ordinal first. Without a name the last replica is removed.
+///
+/// # Arguments
+/// - `name` - Name of the replica to remove, or its member name.
+pub async fn handle_retire_replica(name: Option<&str>) {
     super::print_header();
     let client: Client = k8s::k8s_client().await;
-    match get_cluster(client.clone(), DEFAULT_CLUSTER_NAME, DEFAULT_NAMESPACE).await {
-        Ok(current) => {
+    let current = match get_cluster(client.clone(), DEFAULT_CLUSTER_NAME, DEFAULT_NAMESPACE).await {
+        Ok(current) => current,
+        Err(err) => {
+            error!("Unable to get PgOpr resource: {:?}", err);
+            return;
+        }
+    };
+
+    let result = match (name, current.spec.replica_specs) {
+        (None, None) => {
             let replicas = current.spec.replicas.unwrap_or(0).saturating_sub(1);
-            if let Err(err) =
-                patch_replicas(client, DEFAULT_CLUSTER_NAME, DEFAULT_NAMESPACE, replicas).await
-            {
-                error!("Unable to patch PgOpr replicas: {:?}", err);
+            patch_replicas(client, DEFAULT_CLUSTER_NAME, DEFAULT_NAMESPACE, replicas).await
+        }
+        (name, specs) => {
+            let mut specs = specs.unwrap_or_else(|| {
+                (1..=current.spec.replicas.unwrap_or(0))
+                    .map(|ordinal| replica_spec(ordinal.to_string()))
+                    .collect()
+            });
+            let prefix = format!("{}-replica-", DEFAULT_CLUSTER_NAME);
+            let position = match name {
+                Some(name) => {
+                    let name = name.strip_prefix(&prefix).unwrap_or(name);
+                    specs.iter().position(|spec| spec.name == name)
+                }
+                None => specs.len().checked_sub(1),
+            };
+            let Some(position) = position else {
+                match name {
+                    Some(name) => error!("No replica {} to retire", name),
+                    None => error!("No replica to retire"),
+                }
+                return;
+            };
+            specs.remove(position);
+            patch_replica_specs(client, DEFAULT_CLUSTER_NAME, DEFAULT_NAMESPACE, &specs).await
+        }
+    };
+    if let Err(err) = result {
+        error!("Unable to patch PgOpr replicas: {:?}", err);
+    }
+}
+
+/// Requests a switchover to a replica through the PgOpr resource.
+///
+/// # Arguments
+/// - `target` - Name of the replica to promote.
+pub async fn handle_switchover(target: &str) {
+    super::print_header();
+    let client: Client = k8s::k8s_client().await;
+    let api: Api<pgopr> = Api::namespaced(client, DEFAULT_NAMESPACE);
+    let patch = serde_json::json!({
+        "metadata": {
+            "annotations": {
+                manager::ANNOTATION_SWITCHOVER: target
             }
         }
-        Err(err) => error!("Unable to get PgOpr resource: {:?}", err),
+    });
+    if let Err(err) = api
+        .patch(
+            DEFAULT_CLUSTER_NAME,
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .await
+    {
+        error!("Unable to request switchover: {:?}", err);
+    }
+}
+
+/// Pauses or resumes WAL replay on a delayed replica through the PgOpr resource.
+///
+/// # Arguments
+/// - `replica` - Member or `replicaSpecs` name of the delayed replica.
+/// - `pause` - Whether replay is paused or resumed.
+pub async fn handle_replay(replica: &str, pause: bool) {
+    super::print_header();
+    let client: Client = k8s::k8s_client().await;
+    let current = match get_cluster(client.clone(), DEFAULT_CLUSTER_NAME, DEFAULT_NAMESPACE).await {
+        Ok(current) => current,
+        Err(err) => {
+            error!("Unable to get PgOpr resource: {:?}", err);
+            return;
+        }
+    };
+    let Some(replica) = cluster::delayed_replica(&current, replica) else {
+        error!("{} is not a delayed replica", replica);
+        return;
+    };
+
+    let mut paused: BTreeSet<String> = current
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(manager::ANNOTATION_REPLAY_PAUSED))
+        .map(|paused| {
+            paused
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    if pause {
+        paused.insert(replica);
+    } else {
+        paused.remove(&replica);
+    }
+
+    let value = (!paused.is_empty()).then(|| paused.into_iter().collect::<Vec<_>>().join(","));
+    let api: Api<pgopr> = Api::namespaced(client, DEFAULT_NAMESPACE);
+    let patch = serde_json::json!({
+        "metadata": {
+            "annotations": {
+                manager::ANNOTATION_REPLAY_PAUSED: value
+            }
+        }
+    });
+    if let Err(err) = api
+        .patch(
+            DEFAULT_CLUSTER_NAME,
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .await
+    {
+        error!("Unable to request replay change: {:?}", err);
     }
 }
@@ -6,9 +6,13 @@
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
 
-use crate::workload::{DeploymentConfig, PG18_PRIMARY_IMAGE, PG18_REPLICA_IMAGE};
+use crate::catalog::{self, ImageSet};
+use crate::credentials::Credentials;
+use crate::persistent::StorageConfig;
+use crate::workload::{self, ContainerImage, DeploymentConfig};
 use crate::{crd, persistent, primary, replica, services};
 use clap::ArgMatches;
+use k8s_openapi::api::core::v1::ObjectReference;
 use std::fs;
 
 /// Handles the 'generate' subcommand logic for various resource types.
@@ -27,17 +31,29 @@ pub fn handle_generate(sub_matches: &ArgMatches) {
                 .expect("Unable to write file: pgopr-service.yaml");
         }
         "persistent" => {
+            let storage = StorageConfig {
+                size: 5,
+                storage_class_name: Some(persistent::STORAGE_CLASS_LOCAL.to_string()),
+                access_mode: "ReadWriteOnce".to_string(),
+                volume_mode: "Filesystem".to_string(),
+            };
             let pv = persistent::build_pv(
                 "postgresql-pv-volume",
-                5,
+                &storage,
                 "postgresql",
-                "/tmp/kind",
+                "/tmp/kind/default/postgresql",
                 "postgresql",
+                ObjectReference {
+                    namespace: Some("default".to_string()),
+                    name: Some("postgresql-pv-claim".to_string()),
+                    ..Default::default()
+                },
             );
             let data = serde_yaml::to_string(&pv).expect("Can't serialize pgopr-pv.yaml");
             fs::write("pgopr-pv.yaml", data).expect("Unable to write file: pgopr-pv.yaml");
 
-            let pvc = persistent::build_pvc("postgresql-pv-claim", "default", 5, "postgresql");
+            let pvc =
+                persistent::build_pvc("postgresql-pv-claim", "default", &storage, "postgresql");
             let data = serde_yaml::to_string(&pvc).expect("Can't serialize pgopr-pvc.yaml");
             fs::write("pgopr-pvc.yaml", data).expect("Unable to write file: pgopr-pvc.yaml");
         }
@@ -46,10 +62,19 @@ pub fn handle_generate(sub_matches: &ArgMatches) {
                 "postgresql",
                 "default",
                 DeploymentConfig {
-                    image: PG18_PRIMARY_IMAGE,
+                    cluster_name: "postgresql",
+                    image: &ContainerImage::new(
+                        &ImageSet::builtin(catalog::DEFAULT_VERSION).primary,
+                    ),
                     resources: None,
                     config_map_name: None,
+                    hba_config_map: None,
                     config_hash: None,
+                    credentials: &Credentials::generated("postgresql"),
+                    fenced: false,
+                    claim_name: "postgresql-pv-claim",
+                    wal_claim_name: None,
+                    placement: None,
                 },
             );
             let data = serde_yaml::to_string(&p).expect("Can't serialize pgopr-primary.yaml");
@@ -59,14 +84,23 @@ pub fn handle_generate(sub_matches: &ArgMatches) {
         "replica" => {
             let r = replica::build(
                 "postgresql-replica",
-                "postgresql",
+                Some("postgresql"),
                 "default",
                 "replica1",
                 DeploymentConfig {
-                    image: PG18_REPLICA_IMAGE,
+                    cluster_name: "postgresql",
+                    image: &ContainerImage::new(
+                        &ImageSet::builtin(catalog::DEFAULT_VERSION).replica,
+                    ),
                     resources: None,
                     config_map_name: None,
+                    hba_config_map: None,
                     config_hash: None,
+                    credentials: &Credentials::generated("postgresql"),
+                    fenced: false,
+                    claim_name: "postgresql-replica-pv-claim",
+                    wal_claim_name: None,
+                    placement: None,
                 },
             );
             let data = serde_yaml::to_string(&r).expect("Can't serialize pgopr-replica.yaml");
@@ -80,6 +114,7 @@ pub fn handle_generate(sub_matches: &ArgMatches) {
             let m = crate::pgexporter::build_monitoring_deployment(
                 "postgresql-pgexporter-mon",
                 "default",
+                &ContainerImage::new(workload::PGEXPORTER_MON_IMAGE),
                 "postgresql-pgexporter",
                 None,
             );
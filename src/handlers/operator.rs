@@ -7,6 +7,8 @@
  */
 use crate::{ContextData, k8s, on_error, pgopr, reconcile};
 use futures::StreamExt;
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::batch::v1::Job;
 use kube::{
     Api, Client,
     runtime::{Controller, watcher},
@@ -23,7 +25,18 @@ pub async fn run_operator() {
     let context: Arc<ContextData> = Arc::new(ContextData::new(client.clone()));
 
     // Start the controller
+    // owned workloads trigger a reconcile so role labels follow restarted pods quickly, and
+    // the upgrade Job so an upgrade continues as soon as pg_upgrade finished
     Controller::new(crd_api.clone(), watcher::Config::default())
+        .owns(
+            Api::<Deployment>::all(client.clone()),
+            watcher::Config::default(),
+        )
+        .owns(
+            Api::<StatefulSet>::all(client.clone()),
+            watcher::Config::default(),
+        )
+        .owns(Api::<Job>::all(client), watcher::Config::default())
         .run(reconcile, on_error, context)
         .for_each(|reconciliation_result| async move {
             match reconciliation_result {
@@ -18,18 +18,25 @@ use tokio::time::Duration;
 
 use crate::crd::v1::pgopr;
 
+mod catalog;
 mod cluster;
 pub mod crd;
+mod credentials;
 mod finalizer;
 pub mod handlers;
 mod k8s;
 mod manager;
+mod parameters;
 mod persistent;
 mod pgexporter;
 mod pgmoneta;
 mod primary;
+mod quantity;
 mod replica;
 mod services;
+mod settings;
+mod sql;
+mod upgrade_job;
 mod workload;
 
 /// Context injected with each `reconcile` and `on_error` method invocation
@@ -114,7 +121,12 @@ fn cli() -> Command {
                 .subcommand(
                     Command::new("replica")
                         .about("Retire a replica instance")
-                        .display_order(2),
+                        .display_order(2)
+                        .arg(
+                            Arg::new("name")
+                                .long("name")
+                                .help("The replica to retire, the last one when absent"),
+                        ),
                 )
                 .subcommand(
                     Command::new("pgmoneta")
@@ -137,6 +149,39 @@ fn cli() -> Command {
                 .about("Uninstall the operator")
                 .display_order(4),
         )
+        .subcommand(
+            Command::new("switchover")
+                .about("Switch the primary role to a replica")
+                .display_order(5)
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .required(true)
+                        .help("The replica to promote"),
+                ),
+        )
+        .subcommand(
+            Command::new("pause")
+                .about("Pause WAL replay on a delayed replica")
+                .display_order(6)
+                .arg(
+                    Arg::new("replica")
+                        .long("replica")
+                        .required(true)
+                        .help("The delayed replica"),
+                ),
+        )
+        .subcommand(
+            Command::new("resume")
+                .about("Resume WAL replay on a delayed replica")
+                .display_order(7)
+                .arg(
+                    Arg::new("replica")
+                        .long("replica")
+                        .required(true)
+                        .help("The delayed replica"),
+                ),
+        )
         .subcommand(
             Command::new("completion")
                 .about("Generate a shell completion file")
@@ -224,10 +269,14 @@ async fn main() {
         }
 
         Some(("retire", sub_matches)) => {
-            let (name, _) = sub_matches.subcommand().unwrap_or(("primary", sub_matches));
+            let (name, component_matches) =
+                sub_matches.subcommand().unwrap_or(("primary", sub_matches));
             match name {
                 "primary" => handlers::cluster::handle_retire_primary().await,
-                "replica" => handlers::cluster::handle_retire_replica().await,
+                "replica" => {
+                    let replica = component_matches.get_one::<String>("name");
+                    handlers::cluster::handle_retire_replica(replica.map(String::as_str)).await
+                }
                 "pgmoneta" => handlers::cluster::handle_retire_pgmoneta().await,
                 "pgexporter" => handlers::cluster::handle_retire_pgexporter().await,
                 "grafana" => handlers::cluster::handle_retire_grafana().await,
@@ -239,6 +288,24 @@ async fn main() {
             handlers::cluster::handle_uninstall().await;
         }
 
+        Some(("switchover", sub_matches)) => {
+            if let Some(target) = sub_matches.get_one::<String>("to") {
+                handlers::cluster::handle_switchover(target).await;
+            }
+        }
+
+        Some(("pause", sub_matches)) => {
+            if let Some(replica) = sub_matches.get_one::<String>("replica") {
+                handlers::cluster::handle_replay(replica, true).await;
+            }
+        }
+
+        Some(("resume", sub_matches)) => {
+            if let Some(replica) = sub_matches.get_one::<String>("replica") {
+                handlers::cluster::handle_replay(replica, false).await;
+            }
+        }
+
         _ => {
             handlers::operator::run_operator().await;
         }
@@ -273,9 +340,9 @@ async fn reconcile(pgopr: Arc<pgopr>, context: Arc<ContextData>) -> Result<Actio
     }
 
     // sync with the cluster manager and update status
-    cluster.reconcile_state(pgopr.clone()).await?;
+    let requeue = cluster.reconcile_state(pgopr.clone()).await?;
 
-    Ok(Action::requeue(Duration::from_secs(30)))
+    Ok(Action::requeue(requeue))
 }
 /// The on_error callback
 ///
@@ -303,4 +370,12 @@ pub enum Error {
     /// Error on unsupported PostgreSQL version
     #[error("Unsupported PostgreSQL version: {0}")]
     UnsupportedPostgresVersion(String),
+
+    /// Error in the operator configuration file
+    #[error("Invalid operator configuration: {0}")]
+    ConfigurationError(String),
+
+    /// Error while running SQL inside a PostgreSQL pod
+    #[error("SQL execution failed: {0}")]
+    SqlError(String),
 }
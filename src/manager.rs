@@ -11,7 +11,7 @@ use crate::crd::v1::pgopr;
 use kube::core::{ClusterResourceScope, NamespaceResourceScope};
 use kube::{
     Api, Client, Resource,
-    api::{DeleteParams, Patch, PatchParams, ResourceExt},
+    api::{DeleteParams, Patch, PatchParams, PostParams, ResourceExt},
 };
 use log::info;
 use serde::{Serialize, de::DeserializeOwned};
@@ -25,6 +25,18 @@ pub const API_GROUP: &str = "pgopr.io";
 pub const KIND_PGOPR: &str = "pgopr";
 pub const VERSION_PGOPR: &str = "v1";
 pub const LABEL_COMPONENT: &str = "pgopr.io/component";
+/// Label on member pods holding their current replication role
+pub const LABEL_ROLE: &str = "pgopr.io/role";
+pub const ROLE_PRIMARY: &str = "primary";
+pub const ROLE_REPLICA: &str = "replica";
+/// Role of delayed replicas, the read-only Service does not select them
+pub const ROLE_DELAYED: &str = "delayed";
+/// Label on configuration ConfigMaps holding their revision, counting up with each change
+pub const LABEL_REVISION: &str = "pgopr.io/revision";
+/// Annotation on a PgOpr resource naming the replica to switch over to
+pub const ANNOTATION_SWITCHOVER: &str = "pgopr.io/switchover-to";
+/// Annotation on a PgOpr resource listing the delayed replicas whose replay is paused
+pub const ANNOTATION_REPLAY_PAUSED: &str = "pgopr.io/replay-paused";
 
 /// ResourceManager handles Kubernetes API writes for managed resources.
 pub struct ResourceManager {
@@ -74,6 +86,45 @@ impl ResourceManager {
             .map_err(Error::from)
     }
 
+    /// Creates a namespaced Kubernetes resource unless it already exists.
+    ///
+    /// An existing resource is returned untouched, so generated content is never overwritten.
+    ///
+    /// # Arguments
+    /// - `owner` - The PgOpr resource that owns the Kubernetes resource.
+    /// - `resource` - The Kubernetes resource to create.
+    pub async fn create<K>(&self, owner: &pgopr, mut resource: K) -> Result<K, Error>
+    where
+        K: Resource<Scope = NamespaceResourceScope> + Clone + Debug + Serialize + DeserializeOwned,
+        K::DynamicType: Default,
+    {
+        let name = resource.name_any();
+        let namespace = resource
+            .namespace()
+            .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string());
+
+        if let Some(owner_ref) = owner.controller_owner_ref(&()) {
+            resource.meta_mut().owner_references = Some(vec![owner_ref]);
+        }
+
+        let api: Api<K> = Api::namespaced(self.client.clone(), &namespace);
+        match api.create(&PostParams::default(), &resource).await {
+            Ok(created) => {
+                info!(
+                    "Created {} resource: {}/{}",
+                    K::kind(&Default::default()),
+                    namespace,
+                    name
+                );
+                Ok(created)
+            }
+            Err(kube::Error::Api(err)) if err.code == 409 => {
+                api.get(&name).await.map_err(Error::from)
+            }
+            Err(err) => Err(Error::from(err)),
+        }
+    }
+
     /// Syncs a cluster-scoped resource using Server-Side Apply.
     ///
     /// # Arguments
@@ -109,7 +160,8 @@ impl ResourceManager {
         K::DynamicType: Default,
     {
         let api: Api<K> = Api::namespaced(self.client.clone(), namespace);
-        match api.delete(name, &DeleteParams::default()).await {
+        // Jobs orphan their pods unless the propagation is requested
+        match api.delete(name, &DeleteParams::background()).await {
             Ok(_) => Ok(()),
             Err(kube::Error::Api(err)) if err.code == 404 => Ok(()),
             Err(err) => Err(Error::from(err)),
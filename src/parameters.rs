@@ -0,0 +1,1166 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use ParameterContext::*;
+use Unit::*;
+
+const INT_MAX: i64 = i32::MAX as i64;
+
+/// ParameterContext tells when a change of a parameter takes effect, as in the `context`
+/// column of `pg_settings`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterContext {
+    /// Fixed when the server is built or initialized
+    Internal,
+    /// Needs a restart of the server
+    Postmaster,
+    /// Applied by a reload
+    Sighup,
+    /// Applied by a reload to new sessions, superusers may set it at connection start
+    SuperuserBackend,
+    /// Applied by a reload, superusers may set it in their session
+    Superuser,
+    /// Applied by a reload, any user may set it in their session
+    User,
+}
+
+impl ParameterContext {
+    /// Checks whether a change only takes effect after a restart of the server
+    pub fn requires_restart(self) -> bool {
+        matches!(self, Internal | Postmaster)
+    }
+}
+
+/// Unit is the base unit of a numeric parameter, values without a unit are taken in it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Unit {
+    /// Numbers without a unit
+    Plain,
+    Bytes,
+    Kilobytes,
+    /// Pages of 8kB
+    Blocks,
+    Megabytes,
+    Milliseconds,
+    Seconds,
+    Minutes,
+}
+
+/// Memory units accepted in values, in bytes
+const MEMORY_UNITS: [(&str, f64); 5] = [
+    ("B", 1.0),
+    ("kB", 1024.0),
+    ("MB", 1048576.0),
+    ("GB", 1073741824.0),
+    ("TB", 1099511627776.0),
+];
+
+/// Time units accepted in values, in milliseconds
+const TIME_UNITS: [(&str, f64); 6] = [
+    ("us", 0.001),
+    ("ms", 1.0),
+    ("s", 1000.0),
+    ("min", 60000.0),
+    ("h", 3600000.0),
+    ("d", 86400000.0),
+];
+
+impl Unit {
+    /// The units a value may be given in and the size of the base unit in them
+    fn units(self) -> Option<(&'static [(&'static str, f64)], f64)> {
+        match self {
+            Plain => None,
+            Bytes => Some((&MEMORY_UNITS, 1.0)),
+            Kilobytes => Some((&MEMORY_UNITS, 1024.0)),
+            Blocks => Some((&MEMORY_UNITS, 8192.0)),
+            Megabytes => Some((&MEMORY_UNITS, 1048576.0)),
+            Milliseconds => Some((&TIME_UNITS, 1.0)),
+            Seconds => Some((&TIME_UNITS, 1000.0)),
+            Minutes => Some((&TIME_UNITS, 60000.0)),
+        }
+    }
+
+    /// Formats a number of the unit
+    fn format(self, number: impl std::fmt::Display) -> String {
+        match self {
+            Plain => number.to_string(),
+            Bytes => format!("{}B", number),
+            Kilobytes => format!("{}kB", number),
+            Blocks => format!("{} pages of 8kB", number),
+            Megabytes => format!("{}MB", number),
+            Milliseconds => format!("{}ms", number),
+            Seconds => format!("{}s", number),
+            Minutes => format!("{}min", number),
+        }
+    }
+}
+
+/// ParameterType is the kind of values a parameter takes.
+#[derive(Clone, Copy, Debug)]
+enum ParameterType {
+    Boolean,
+    Integer {
+        min: i64,
+        max: i64,
+        unit: Unit,
+    },
+    Real {
+        min: f64,
+        max: f64,
+        unit: Unit,
+    },
+    /// One of the values, case insensitive, and the boolean spellings if allowed
+    Enumeration {
+        values: &'static [&'static str],
+        booleans: bool,
+    },
+    String,
+}
+
+/// Parameter is a postgresql.conf setting and the major versions that have it.
+struct Parameter {
+    name: &'static str,
+    context: ParameterContext,
+    kind: ParameterType,
+    since: u32,
+    until: Option<u32>,
+}
+
+impl Parameter {
+    const fn new(name: &'static str, context: ParameterContext, kind: ParameterType) -> Self {
+        Self {
+            name,
+            context,
+            kind,
+            since: 13,
+            until: None,
+        }
+    }
+
+    const fn boolean(name: &'static str, context: ParameterContext) -> Self {
+        Self::new(name, context, ParameterType::Boolean)
+    }
+
+    const fn integer(
+        name: &'static str,
+        context: ParameterContext,
+        min: i64,
+        max: i64,
+        unit: Unit,
+    ) -> Self {
+        Self::new(name, context, ParameterType::Integer { min, max, unit })
+    }
+
+    const fn real(
+        name: &'static str,
+        context: ParameterContext,
+        min: f64,
+        max: f64,
+        unit: Unit,
+    ) -> Self {
+        Self::new(name, context, ParameterType::Real { min, max, unit })
+    }
+
+    const fn enumeration(
+        name: &'static str,
+        context: ParameterContext,
+        values: &'static [&'static str],
+    ) -> Self {
+        Self::new(
+            name,
+            context,
+            ParameterType::Enumeration {
+                values,
+                booleans: false,
+            },
+        )
+    }
+
+    const fn string(name: &'static str, context: ParameterContext) -> Self {
+        Self::new(name, context, ParameterType::String)
+    }
+
+    /// Also accepts the spellings of a boolean, such as `true` or `0`
+    const fn or_bool(mut self) -> Self {
+        if let ParameterType::Enumeration { values, .. } = self.kind {
+            self.kind = ParameterType::Enumeration {
+                values,
+                booleans: true,
+            };
+        }
+        self
+    }
+
+    /// The first major version with the parameter
+    const fn since(mut self, version: u32) -> Self {
+        self.since = version;
+        self
+    }
+
+    /// The last major version with the parameter
+    const fn until(mut self, version: u32) -> Self {
+        self.until = Some(version);
+        self
+    }
+
+    fn exists_in(&self, version: Option<u32>) -> bool {
+        version.is_none_or(|version| {
+            version >= self.since && self.until.is_none_or(|until| version <= until)
+        })
+    }
+
+    /// Checks a value the way the server parses it, returns the problem found
+    fn check(&self, value: &str) -> Result<(), String> {
+        match self.kind {
+            ParameterType::Boolean => {
+                if is_boolean(value) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not a boolean", value))
+                }
+            }
+            ParameterType::Integer { min, max, unit } => {
+                let number = parse_number(value, unit)?.round();
+                if number < min as f64 || number > max as f64 {
+                    return Err(format!(
+                        "'{}' is outside {} to {}",
+                        value,
+                        unit.format(min),
+                        unit.format(max)
+                    ));
+                }
+                Ok(())
+            }
+            ParameterType::Real { min, max, unit } => {
+                let number = parse_number(value, unit)?;
+                if number < min && max == f64::MAX {
+                    return Err(format!("'{}' is below {}", value, unit.format(min)));
+                }
+                if number < min || number > max {
+                    return Err(format!(
+                        "'{}' is outside {} to {}",
+                        value,
+                        unit.format(min),
+                        unit.format(max)
+                    ));
+                }
+                Ok(())
+            }
+            ParameterType::Enumeration { values, booleans } => {
+                if values.iter().any(|v| v.eq_ignore_ascii_case(value))
+                    || (booleans && is_boolean(value))
+                {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not one of {}", value, values.join(", ")))
+                }
+            }
+            ParameterType::String => Ok(()),
+        }
+    }
+}
+
+/// The parameters of PostgreSQL 13 through 18, without developer options
+const PARAMETERS: &[Parameter] = &[
+    // file locations
+    Parameter::string("data_directory", Postmaster),
+    Parameter::string("config_file", Postmaster),
+    Parameter::string("hba_file", Postmaster),
+    Parameter::string("ident_file", Postmaster),
+    Parameter::string("external_pid_file", Postmaster),
+    // connections and authentication
+    Parameter::string("listen_addresses", Postmaster),
+    Parameter::integer("port", Postmaster, 1, 65535, Plain),
+    Parameter::integer("max_connections", Postmaster, 1, 262143, Plain),
+    Parameter::integer("reserved_connections", Postmaster, 0, 262143, Plain).since(16),
+    Parameter::integer(
+        "superuser_reserved_connections",
+        Postmaster,
+        0,
+        262143,
+        Plain,
+    ),
+    Parameter::string("unix_socket_directories", Postmaster),
+    Parameter::string("unix_socket_group", Postmaster),
+    Parameter::integer("unix_socket_permissions", Postmaster, 0, 511, Plain),
+    Parameter::boolean("bonjour", Postmaster),
+    Parameter::string("bonjour_name", Postmaster),
+    Parameter::integer("tcp_keepalives_idle", User, 0, INT_MAX, Seconds),
+    Parameter::integer("tcp_keepalives_interval", User, 0, INT_MAX, Seconds),
+    Parameter::integer("tcp_keepalives_count", User, 0, INT_MAX, Plain),
+    Parameter::integer("tcp_user_timeout", User, 0, INT_MAX, Milliseconds),
+    Parameter::integer(
+        "client_connection_check_interval",
+        User,
+        0,
+        INT_MAX,
+        Milliseconds,
+    )
+    .since(14),
+    Parameter::integer("authentication_timeout", Sighup, 1, 600, Seconds),
+    Parameter::enumeration("password_encryption", User, &["md5", "scram-sha-256"]),
+    Parameter::integer("scram_iterations", User, 1, INT_MAX, Plain).since(16),
+    Parameter::boolean("md5_password_warnings", User).since(18),
+    Parameter::string("oauth_validator_libraries", Sighup).since(18),
+    Parameter::string("krb_server_keyfile", Sighup),
+    Parameter::boolean("krb_caseins_users", Sighup),
+    Parameter::boolean("gss_accept_delegation", Sighup).since(16),
+    Parameter::boolean("db_user_namespace", Sighup).until(16),
+    Parameter::boolean("ssl", Sighup),
+    Parameter::string("ssl_ca_file", Sighup),
+    Parameter::string("ssl_cert_file", Sighup),
+    Parameter::string("ssl_crl_file", Sighup),
+    Parameter::string("ssl_crl_dir", Sighup).since(14),
+    Parameter::string("ssl_key_file", Sighup),
+    Parameter::string("ssl_ciphers", Sighup),
+    Parameter::string("ssl_tls13_ciphers", Sighup).since(18),
+    Parameter::boolean("ssl_prefer_server_ciphers", Sighup),
+    Parameter::string("ssl_ecdh_curve", Sighup),
+    Parameter::string("ssl_groups", Sighup).since(18),
+    Parameter::enumeration(
+        "ssl_min_protocol_version",
+        Sighup,
+        &["TLSv1", "TLSv1.1", "TLSv1.2", "TLSv1.3"],
+    ),
+    Parameter::enumeration(
+        "ssl_max_protocol_version",
+        Sighup,
+        &["", "TLSv1", "TLSv1.1", "TLSv1.2", "TLSv1.3"],
+    ),
+    Parameter::string("ssl_dh_params_file", Sighup),
+    Parameter::string("ssl_passphrase_command", Sighup),
+    Parameter::boolean("ssl_passphrase_command_supports_reload", Sighup),
+    // resource usage
+    Parameter::integer("shared_buffers", Postmaster, 16, INT_MAX / 2, Blocks),
+    Parameter::enumeration("huge_pages", Postmaster, &["off", "on", "try"]).or_bool(),
+    Parameter::integer("huge_page_size", Postmaster, 0, INT_MAX, Kilobytes).since(14),
+    Parameter::integer("temp_buffers", User, 100, INT_MAX / 2, Blocks),
+    Parameter::integer("max_prepared_transactions", Postmaster, 0, 262143, Plain),
+    Parameter::integer("work_mem", User, 64, INT_MAX, Kilobytes),
+    Parameter::real("hash_mem_multiplier", User, 1.0, 1000.0, Plain),
+    Parameter::integer("maintenance_work_mem", User, 1024, INT_MAX, Kilobytes),
+    Parameter::integer("autovacuum_work_mem", Sighup, -1, INT_MAX, Kilobytes),
+    Parameter::integer("vacuum_buffer_usage_limit", User, 0, 16777216, Kilobytes).since(16),
+    Parameter::integer("logical_decoding_work_mem", User, 64, INT_MAX, Kilobytes),
+    Parameter::integer("max_stack_depth", Superuser, 100, INT_MAX, Kilobytes),
+    Parameter::enumeration("shared_memory_type", Postmaster, &["mmap", "sysv"]),
+    Parameter::enumeration(
+        "dynamic_shared_memory_type",
+        Postmaster,
+        &["posix", "sysv", "mmap"],
+    ),
+    Parameter::integer(
+        "min_dynamic_shared_memory",
+        Postmaster,
+        0,
+        INT_MAX,
+        Megabytes,
+    )
+    .since(14),
+    Parameter::integer("temp_file_limit", Superuser, -1, INT_MAX, Kilobytes),
+    Parameter::integer("max_notify_queue_pages", Postmaster, 64, INT_MAX, Plain).since(17),
+    Parameter::integer("max_files_per_process", Postmaster, 64, INT_MAX, Plain),
+    Parameter::integer("commit_timestamp_buffers", Postmaster, 0, 131072, Blocks).since(17),
+    Parameter::integer("multixact_member_buffers", Postmaster, 16, 131072, Blocks).since(17),
+    Parameter::integer("multixact_offset_buffers", Postmaster, 16, 131072, Blocks).since(17),
+    Parameter::integer("notify_buffers", Postmaster, 16, 131072, Blocks).since(17),
+    Parameter::integer("serializable_buffers", Postmaster, 16, 131072, Blocks).since(17),
+    Parameter::integer("subtransaction_buffers", Postmaster, 0, 131072, Blocks).since(17),
+    Parameter::integer("transaction_buffers", Postmaster, 0, 131072, Blocks).since(17),
+    Parameter::real("vacuum_cost_delay", User, 0.0, 100.0, Milliseconds),
+    Parameter::integer("vacuum_cost_page_hit", User, 0, 10000, Plain),
+    Parameter::integer("vacuum_cost_page_miss", User, 0, 10000, Plain),
+    Parameter::integer("vacuum_cost_page_dirty", User, 0, 10000, Plain),
+    Parameter::integer("vacuum_cost_limit", User, 1, 10000, Plain),
+    Parameter::integer("bgwriter_delay", Sighup, 10, 10000, Milliseconds),
+    Parameter::integer("bgwriter_lru_maxpages", Sighup, 0, INT_MAX / 2, Plain),
+    Parameter::real("bgwriter_lru_multiplier", Sighup, 0.0, 10.0, Plain),
+    Parameter::integer("bgwriter_flush_after", Sighup, 0, 256, Blocks),
+    Parameter::integer("backend_flush_after", User, 0, 256, Blocks),
+    Parameter::integer("effective_io_concurrency", User, 0, 1000, Plain),
+    Parameter::integer("maintenance_io_concurrency", User, 0, 1000, Plain),
+    Parameter::integer("io_combine_limit", User, 1, 32, Blocks)
+        .since(17)
+        .until(17),
+    Parameter::integer("io_combine_limit", User, 1, 128, Blocks).since(18),
+    Parameter::integer("io_max_combine_limit", Postmaster, 1, 128, Blocks).since(18),
+    Parameter::enumeration("io_method", Postmaster, &["sync", "worker", "io_uring"]).since(18),
+    Parameter::integer("io_max_concurrency", Postmaster, -1, 1024, Plain).since(18),
+    Parameter::integer("io_workers", Sighup, 1, 32, Plain).since(18),
+    Parameter::enumeration("file_copy_method", User, &["copy", "clone"]).since(18),
+    Parameter::integer("max_worker_processes", Postmaster, 0, 262143, Plain),
+    Parameter::integer("max_parallel_workers_per_gather", User, 0, 1024, Plain),
+    Parameter::integer("max_parallel_maintenance_workers", User, 0, 1024, Plain),
+    Parameter::integer("max_parallel_workers", User, 0, 1024, Plain),
+    Parameter::boolean("parallel_leader_participation", User),
+    Parameter::integer("old_snapshot_threshold", Postmaster, -1, 86400, Minutes).until(16),
+    // write ahead log
+    Parameter::enumeration("wal_level", Postmaster, &["minimal", "replica", "logical"]),
+    Parameter::boolean("fsync", Sighup),
+    Parameter::enumeration(
+        "synchronous_commit",
+        User,
+        &["local", "remote_write", "remote_apply", "on", "off"],
+    )
+    .or_bool(),
+    Parameter::enumeration(
+        "wal_sync_method",
+        Sighup,
+        &["fsync", "fdatasync", "open_sync", "open_datasync"],
+    ),
+    Parameter::boolean("full_page_writes", Sighup),
+    Parameter::boolean("wal_log_hints", Postmaster),
+    Parameter::boolean("wal_compression", Superuser).until(14),
+    Parameter::enumeration(
+        "wal_compression",
+        Superuser,
+        &["pglz", "lz4", "zstd", "on", "off"],
+    )
+    .or_bool()
+    .since(15),
+    Parameter::boolean("wal_init_zero", Superuser),
+    Parameter::boolean("wal_recycle", Superuser),
+    Parameter::integer("wal_buffers", Postmaster, -1, 262143, Blocks),
+    Parameter::integer("wal_writer_delay", Sighup, 1, 10000, Milliseconds),
+    Parameter::integer("wal_writer_flush_after", Sighup, 0, INT_MAX, Blocks),
+    Parameter::integer("wal_skip_threshold", User, 0, INT_MAX, Kilobytes),
+    Parameter::integer("commit_delay", Superuser, 0, 100000, Plain),
+    Parameter::integer("commit_siblings", User, 0, 1000, Plain),
+    Parameter::integer("checkpoint_timeout", Sighup, 30, 86400, Seconds),
+    Parameter::real("checkpoint_completion_target", Sighup, 0.0, 1.0, Plain),
+    Parameter::integer("checkpoint_flush_after", Sighup, 0, 256, Blocks),
+    Parameter::integer("checkpoint_warning", Sighup, 0, INT_MAX, Seconds),
+    Parameter::integer("max_wal_size", Sighup, 2, INT_MAX, Megabytes),
+    Parameter::integer("min_wal_size", Sighup, 2, INT_MAX, Megabytes),
+    Parameter::enumeration("archive_mode", Postmaster, &["always", "on", "off"]).or_bool(),
+    Parameter::string("archive_command", Sighup),
+    Parameter::string("archive_library", Sighup).since(15),
+    Parameter::integer("archive_timeout", Sighup, 0, INT_MAX / 2, Seconds),
+    Parameter::string("restore_command", Postmaster).until(13),
+    Parameter::string("restore_command", Sighup).since(14),
+    Parameter::string("archive_cleanup_command", Sighup),
+    Parameter::string("recovery_end_command", Sighup),
+    Parameter::string("recovery_target", Postmaster),
+    Parameter::string("recovery_target_name", Postmaster),
+    Parameter::string("recovery_target_time", Postmaster),
+    Parameter::string("recovery_target_xid", Postmaster),
+    Parameter::string("recovery_target_lsn", Postmaster),
+    Parameter::boolean("recovery_target_inclusive", Postmaster),
+    Parameter::string("recovery_target_timeline", Postmaster),
+    Parameter::enumeration(
+        "recovery_target_action",
+        Postmaster,
+        &["pause", "promote", "shutdown"],
+    ),
+    Parameter::enumeration("recovery_prefetch", Sighup, &["off", "on", "try"])
+        .or_bool()
+        .since(15),
+    Parameter::integer(
+        "wal_decode_buffer_size",
+        Postmaster,
+        65536,
+        1073741823,
+        Bytes,
+    )
+    .since(15),
+    Parameter::boolean("summarize_wal", Sighup).since(17),
+    Parameter::integer("wal_summary_keep_time", Sighup, 0, INT_MAX / 60, Minutes).since(17),
+    // replication
+    Parameter::integer("max_wal_senders", Postmaster, 0, 262143, Plain),
+    Parameter::integer("max_replication_slots", Postmaster, 0, 262143, Plain),
+    Parameter::integer("wal_keep_size", Sighup, 0, INT_MAX, Megabytes),
+    Parameter::integer("max_slot_wal_keep_size", Sighup, -1, INT_MAX, Megabytes),
+    Parameter::integer("idle_replication_slot_timeout", Sighup, 0, INT_MAX, Seconds).since(18),
+    Parameter::integer("wal_sender_timeout", User, 0, INT_MAX, Milliseconds),
+    Parameter::boolean("track_commit_timestamp", Postmaster),
+    Parameter::string("synchronous_standby_names", Sighup),
+    Parameter::string("synchronized_standby_slots", Sighup).since(17),
+    Parameter::integer("vacuum_defer_cleanup_age", Sighup, 0, 1000000, Plain).until(15),
+    Parameter::string("primary_conninfo", Sighup),
+    Parameter::string("primary_slot_name", Sighup),
+    Parameter::string("promote_trigger_file", Sighup).until(15),
+    Parameter::boolean("hot_standby", Postmaster),
+    Parameter::integer(
+        "max_standby_archive_delay",
+        Sighup,
+        -1,
+        INT_MAX,
+        Milliseconds,
+    ),
+    Parameter::integer(
+        "max_standby_streaming_delay",
+        Sighup,
+        -1,
+        INT_MAX,
+        Milliseconds,
+    ),
+    Parameter::boolean("wal_receiver_create_temp_slot", Sighup),
+    Parameter::integer(
+        "wal_receiver_status_interval",
+        Sighup,
+        0,
+        INT_MAX / 1000,
+        Seconds,
+    ),
+    Parameter::boolean("hot_standby_feedback", Sighup),
+    Parameter::integer("wal_receiver_timeout", Sighup, 0, INT_MAX, Milliseconds),
+    Parameter::integer(
+        "wal_retrieve_retry_interval",
+        Sighup,
+        1,
+        INT_MAX,
+        Milliseconds,
+    ),
+    Parameter::integer("recovery_min_apply_delay", Sighup, 0, INT_MAX, Milliseconds),
+    Parameter::boolean("sync_replication_slots", Sighup).since(17),
+    Parameter::integer(
+        "max_logical_replication_workers",
+        Postmaster,
+        0,
+        262143,
+        Plain,
+    ),
+    Parameter::integer(
+        "max_sync_workers_per_subscription",
+        Sighup,
+        0,
+        262143,
+        Plain,
+    ),
+    Parameter::integer(
+        "max_parallel_apply_workers_per_subscription",
+        Sighup,
+        0,
+        262143,
+        Plain,
+    )
+    .since(16),
+    Parameter::integer(
+        "max_active_replication_origins",
+        Postmaster,
+        0,
+        262143,
+        Plain,
+    )
+    .since(18),
+    // query tuning
+    Parameter::boolean("enable_async_append", User).since(14),
+    Parameter::boolean("enable_bitmapscan", User),
+    Parameter::boolean("enable_distinct_reordering", User).since(18),
+    Parameter::boolean("enable_gathermerge", User),
+    Parameter::boolean("enable_group_by_reordering", User).since(17),
+    Parameter::boolean("enable_hashagg", User),
+    Parameter::boolean("enable_hashjoin", User),
+    Parameter::boolean("enable_incremental_sort", User),
+    Parameter::boolean("enable_indexscan", User),
+    Parameter::boolean("enable_indexonlyscan", User),
+    Parameter::boolean("enable_material", User),
+    Parameter::boolean("enable_memoize", User).since(14),
+    Parameter::boolean("enable_mergejoin", User),
+    Parameter::boolean("enable_nestloop", User),
+    Parameter::boolean("enable_parallel_append", User),
+    Parameter::boolean("enable_parallel_hash", User),
+    Parameter::boolean("enable_partition_pruning", User),
+    Parameter::boolean("enable_partitionwise_join", User),
+    Parameter::boolean("enable_partitionwise_aggregate", User),
+    Parameter::boolean("enable_presorted_aggregate", User).since(16),
+    Parameter::boolean("enable_self_join_elimination", User).since(18),
+    Parameter::boolean("enable_seqscan", User),
+    Parameter::boolean("enable_sort", User),
+    Parameter::boolean("enable_tidscan", User),
+    Parameter::real("seq_page_cost", User, 0.0, f64::MAX, Plain),
+    Parameter::real("random_page_cost", User, 0.0, f64::MAX, Plain),
+    Parameter::real("cpu_tuple_cost", User, 0.0, f64::MAX, Plain),
+    Parameter::real("cpu_index_tuple_cost", User, 0.0, f64::MAX, Plain),
+    Parameter::real("cpu_operator_cost", User, 0.0, f64::MAX, Plain),
+    Parameter::real("parallel_setup_cost", User, 0.0, f64::MAX, Plain),
+    Parameter::real("parallel_tuple_cost", User, 0.0, f64::MAX, Plain),
+    Parameter::integer("min_parallel_table_scan_size", User, 0, INT_MAX / 3, Blocks),
+    Parameter::integer("min_parallel_index_scan_size", User, 0, INT_MAX / 3, Blocks),
+    Parameter::integer("effective_cache_size", User, 1, INT_MAX, Blocks),
+    Parameter::real("jit_above_cost", User, -1.0, f64::MAX, Plain),
+    Parameter::real("jit_inline_above_cost", User, -1.0, f64::MAX, Plain),
+    Parameter::real("jit_optimize_above_cost", User, -1.0, f64::MAX, Plain),
+    Parameter::boolean("geqo", User),
+    Parameter::integer("geqo_threshold", User, 2, INT_MAX, Plain),
+    Parameter::integer("geqo_effort", User, 1, 10, Plain),
+    Parameter::integer("geqo_pool_size", User, 0, INT_MAX, Plain),
+    Parameter::integer("geqo_generations", User, 0, INT_MAX, Plain),
+    Parameter::real("geqo_selection_bias", User, 1.5, 2.0, Plain),
+    Parameter::real("geqo_seed", User, 0.0, 1.0, Plain),
+    Parameter::integer("default_statistics_target", User, 1, 10000, Plain),
+    Parameter::enumeration("constraint_exclusion", User, &["partition", "on", "off"]).or_bool(),
+    Parameter::real("cursor_tuple_fraction", User, 0.0, 1.0, Plain),
+    Parameter::integer("from_collapse_limit", User, 1, INT_MAX, Plain),
+    Parameter::boolean("jit", User),
+    Parameter::integer("join_collapse_limit", User, 1, INT_MAX, Plain),
+    Parameter::enumeration(
+        "plan_cache_mode",
+        User,
+        &["auto", "force_generic_plan", "force_custom_plan"],
+    ),
+    Parameter::real("recursive_worktable_factor", User, 0.001, 1000000.0, Plain).since(15),
+    // reporting and logging
+    Parameter::string("log_destination", Sighup),
+    Parameter::boolean("logging_collector", Postmaster),
+    Parameter::string("log_directory", Sighup),
+    Parameter::string("log_filename", Sighup),
+    Parameter::integer("log_file_mode", Sighup, 0, 511, Plain),
+    Parameter::integer("log_rotation_age", Sighup, 0, INT_MAX / 60, Minutes),
+    Parameter::integer("log_rotation_size", Sighup, 0, INT_MAX / 1024, Kilobytes),
+    Parameter::boolean("log_truncate_on_rotation", Sighup),
+    Parameter::enumeration(
+        "syslog_facility",
+        Sighup,
+        &[
+            "local0", "local1", "local2", "local3", "local4", "local5", "local6", "local7",
+        ],
+    ),
+    Parameter::string("syslog_ident", Sighup),
+    Parameter::boolean("syslog_sequence_numbers", Sighup),
+    Parameter::boolean("syslog_split_messages", Sighup),
+    Parameter::string("event_source", Postmaster),
+    Parameter::enumeration(
+        "log_min_messages",
+        Superuser,
+        &[
+            "debug5", "debug4", "debug3", "debug2", "debug1", "info", "notice", "warning", "error",
+            "log", "fatal", "panic",
+        ],
+    ),
+    Parameter::enumeration(
+        "log_min_error_statement",
+        Superuser,
+        &[
+            "debug5", "debug4", "debug3", "debug2", "debug1", "info", "notice", "warning", "error",
+            "log", "fatal", "panic",
+        ],
+    ),
+    Parameter::integer(
+        "log_min_duration_statement",
+        Superuser,
+        -1,
+        INT_MAX,
+        Milliseconds,
+    ),
+    Parameter::integer(
+        "log_min_duration_sample",
+        Superuser,
+        -1,
+        INT_MAX,
+        Milliseconds,
+    ),
+    Parameter::real("log_statement_sample_rate", Superuser, 0.0, 1.0, Plain),
+    Parameter::real("log_transaction_sample_rate", Superuser, 0.0, 1.0, Plain),
+    Parameter::integer(
+        "log_startup_progress_interval",
+        Sighup,
+        0,
+        INT_MAX,
+        Milliseconds,
+    )
+    .since(15),
+    Parameter::string("application_name", User),
+    Parameter::boolean("debug_print_parse", User),
+    Parameter::boolean("debug_print_rewritten", User),
+    Parameter::boolean("debug_print_plan", User),
+    Parameter::boolean("debug_pretty_print", User),
+    Parameter::integer(
+        "log_autovacuum_min_duration",
+        Sighup,
+        -1,
+        INT_MAX,
+        Milliseconds,
+    ),
+    Parameter::boolean("log_checkpoints", Sighup),
+    Parameter::boolean("log_connections", SuperuserBackend).until(17),
+    Parameter::string("log_connections", SuperuserBackend).since(18),
+    Parameter::boolean("log_disconnections", SuperuserBackend),
+    Parameter::boolean("log_duration", Superuser),
+    Parameter::enumeration(
+        "log_error_verbosity",
+        Superuser,
+        &["terse", "default", "verbose"],
+    ),
+    Parameter::boolean("log_hostname", Sighup),
+    Parameter::string("log_line_prefix", Sighup),
+    Parameter::boolean("log_lock_waits", Superuser),
+    Parameter::boolean("log_lock_failures", Superuser).since(18),
+    Parameter::boolean("log_recovery_conflict_waits", Sighup).since(14),
+    Parameter::integer(
+        "log_parameter_max_length",
+        Superuser,
+        -1,
+        INT_MAX / 2,
+        Bytes,
+    ),
+    Parameter::integer(
+        "log_parameter_max_length_on_error",
+        User,
+        -1,
+        INT_MAX / 2,
+        Bytes,
+    ),
+    Parameter::enumeration("log_statement", Superuser, &["none", "ddl", "mod", "all"]),
+    Parameter::boolean("log_replication_commands", Superuser),
+    Parameter::integer("log_temp_files", Superuser, -1, INT_MAX, Kilobytes),
+    Parameter::string("log_timezone", Sighup),
+    Parameter::string("cluster_name", Postmaster),
+    Parameter::boolean("update_process_title", Superuser),
+    // run-time statistics
+    Parameter::boolean("track_activities", Superuser),
+    Parameter::integer("track_activity_query_size", Postmaster, 100, 1048576, Bytes),
+    Parameter::boolean("track_counts", Superuser),
+    Parameter::boolean("track_cost_delay_timing", Superuser).since(18),
+    Parameter::boolean("track_io_timing", Superuser),
+    Parameter::boolean("track_wal_io_timing", Superuser).since(14),
+    Parameter::enumeration("track_functions", Superuser, &["none", "pl", "all"]),
+    Parameter::enumeration(
+        "stats_fetch_consistency",
+        User,
+        &["none", "cache", "snapshot"],
+    )
+    .since(15),
+    Parameter::string("stats_temp_directory", Sighup).until(14),
+    Parameter::enumeration(
+        "compute_query_id",
+        Superuser,
+        &["auto", "regress", "on", "off"],
+    )
+    .or_bool()
+    .since(14),
+    Parameter::boolean("log_statement_stats", Superuser),
+    Parameter::boolean("log_parser_stats", Superuser),
+    Parameter::boolean("log_planner_stats", Superuser),
+    Parameter::boolean("log_executor_stats", Superuser),
+    // autovacuum
+    Parameter::boolean("autovacuum", Sighup),
+    Parameter::integer("autovacuum_max_workers", Postmaster, 1, 262143, Plain).until(17),
+    Parameter::integer("autovacuum_max_workers", Sighup, 1, 262143, Plain).since(18),
+    Parameter::integer("autovacuum_worker_slots", Postmaster, 1, 262143, Plain).since(18),
+    Parameter::integer("autovacuum_naptime", Sighup, 1, INT_MAX / 1000, Seconds),
+    Parameter::integer("autovacuum_vacuum_threshold", Sighup, 0, INT_MAX, Plain),
+    Parameter::integer(
+        "autovacuum_vacuum_max_threshold",
+        Sighup,
+        -1,
+        INT_MAX,
+        Plain,
+    )
+    .since(18),
+    Parameter::integer(
+        "autovacuum_vacuum_insert_threshold",
+        Sighup,
+        -1,
+        INT_MAX,
+        Plain,
+    ),
+    Parameter::integer("autovacuum_analyze_threshold", Sighup, 0, INT_MAX, Plain),
+    Parameter::real("autovacuum_vacuum_scale_factor", Sighup, 0.0, 100.0, Plain),
+    Parameter::real(
+        "autovacuum_vacuum_insert_scale_factor",
+        Sighup,
+        0.0,
+        100.0,
+        Plain,
+    ),
+    Parameter::real("autovacuum_analyze_scale_factor", Sighup, 0.0, 100.0, Plain),
+    Parameter::integer(
+        "autovacuum_freeze_max_age",
+        Postmaster,
+        100000,
+        2000000000,
+        Plain,
+    ),
+    Parameter::integer(
+        "autovacuum_multixact_freeze_max_age",
+        Postmaster,
+        10000,
+        2000000000,
+        Plain,
+    ),
+    Parameter::real(
+        "autovacuum_vacuum_cost_delay",
+        Sighup,
+        -1.0,
+        100.0,
+        Milliseconds,
+    ),
+    Parameter::integer("autovacuum_vacuum_cost_limit", Sighup, -1, 10000, Plain),
+    // client connection defaults
+    Parameter::enumeration(
+        "client_min_messages",
+        User,
+        &[
+            "debug5", "debug4", "debug3", "debug2", "debug1", "log", "notice", "warning", "error",
+        ],
+    ),
+    Parameter::string("search_path", User),
+    Parameter::boolean("row_security", User),
+    Parameter::string("default_table_access_method", User),
+    Parameter::string("default_tablespace", User),
+    Parameter::enumeration("default_toast_compression", User, &["pglz", "lz4"]).since(14),
+    Parameter::string("temp_tablespaces", User),
+    Parameter::boolean("check_function_bodies", User),
+    Parameter::enumeration(
+        "default_transaction_isolation",
+        User,
+        &[
+            "serializable",
+            "repeatable read",
+            "read committed",
+            "read uncommitted",
+        ],
+    ),
+    Parameter::boolean("default_transaction_read_only", User),
+    Parameter::boolean("default_transaction_deferrable", User),
+    Parameter::enumeration(
+        "session_replication_role",
+        Superuser,
+        &["origin", "replica", "local"],
+    ),
+    Parameter::integer("statement_timeout", User, 0, INT_MAX, Milliseconds),
+    Parameter::integer("transaction_timeout", User, 0, INT_MAX, Milliseconds).since(17),
+    Parameter::integer("lock_timeout", User, 0, INT_MAX, Milliseconds),
+    Parameter::integer(
+        "idle_in_transaction_session_timeout",
+        User,
+        0,
+        INT_MAX,
+        Milliseconds,
+    ),
+    Parameter::integer("idle_session_timeout", User, 0, INT_MAX, Milliseconds).since(14),
+    Parameter::integer("vacuum_freeze_table_age", User, 0, 2000000000, Plain),
+    Parameter::integer("vacuum_freeze_min_age", User, 0, 1000000000, Plain),
+    Parameter::integer("vacuum_failsafe_age", User, 0, 2100000000, Plain).since(14),
+    Parameter::integer(
+        "vacuum_multixact_freeze_table_age",
+        User,
+        0,
+        2000000000,
+        Plain,
+    ),
+    Parameter::integer(
+        "vacuum_multixact_freeze_min_age",
+        User,
+        0,
+        1000000000,
+        Plain,
+    ),
+    Parameter::integer("vacuum_multixact_failsafe_age", User, 0, 2100000000, Plain).since(14),
+    Parameter::real(
+        "vacuum_max_eager_freeze_failure_rate",
+        User,
+        0.0,
+        1.0,
+        Plain,
+    )
+    .since(18),
+    Parameter::boolean("vacuum_truncate", User).since(18),
+    Parameter::real(
+        "vacuum_cleanup_index_scale_factor",
+        User,
+        0.0,
+        10000000000.0,
+        Plain,
+    )
+    .until(13),
+    Parameter::enumeration("bytea_output", User, &["escape", "hex"]),
+    Parameter::enumeration("xmlbinary", User, &["base64", "hex"]),
+    Parameter::enumeration("xmloption", User, &["content", "document"]),
+    Parameter::integer("gin_pending_list_limit", User, 64, INT_MAX, Kilobytes),
+    Parameter::string("createrole_self_grant", User).since(16),
+    Parameter::boolean("event_triggers", Superuser).since(17),
+    Parameter::string("restrict_nonsystem_relation_kind", User).since(17),
+    Parameter::string("DateStyle", User),
+    Parameter::enumeration(
+        "IntervalStyle",
+        User,
+        &["postgres", "postgres_verbose", "sql_standard", "iso_8601"],
+    ),
+    Parameter::string("TimeZone", User),
+    Parameter::string("timezone_abbreviations", User),
+    Parameter::integer("extra_float_digits", User, -15, 3, Plain),
+    Parameter::string("client_encoding", User),
+    Parameter::string("lc_messages", Superuser),
+    Parameter::string("lc_monetary", User),
+    Parameter::string("lc_numeric", User),
+    Parameter::string("lc_time", User),
+    Parameter::enumeration(
+        "icu_validation_level",
+        User,
+        &[
+            "disabled", "debug5", "debug4", "debug3", "debug2", "debug1", "log", "notice",
+            "warning", "error",
+        ],
+    )
+    .since(16),
+    Parameter::string("default_text_search_config", User),
+    Parameter::string("local_preload_libraries", User),
+    Parameter::string("session_preload_libraries", Superuser),
+    Parameter::string("shared_preload_libraries", Postmaster),
+    Parameter::string("jit_provider", Postmaster),
+    Parameter::string("dynamic_library_path", Superuser),
+    Parameter::string("extension_control_path", Superuser).since(18),
+    Parameter::integer("gin_fuzzy_search_limit", User, 0, INT_MAX, Plain),
+    // lock management
+    Parameter::integer("deadlock_timeout", Superuser, 1, INT_MAX, Milliseconds),
+    Parameter::integer("max_locks_per_transaction", Postmaster, 10, INT_MAX, Plain),
+    Parameter::integer(
+        "max_pred_locks_per_transaction",
+        Postmaster,
+        10,
+        INT_MAX,
+        Plain,
+    ),
+    Parameter::integer(
+        "max_pred_locks_per_relation",
+        Sighup,
+        -INT_MAX,
+        INT_MAX,
+        Plain,
+    ),
+    Parameter::integer("max_pred_locks_per_page", Sighup, 0, INT_MAX, Plain),
+    // version and platform compatibility
+    Parameter::boolean("array_nulls", User),
+    Parameter::enumeration("backslash_quote", User, &["safe_encoding", "on", "off"]).or_bool(),
+    Parameter::boolean("escape_string_warning", User),
+    Parameter::boolean("lo_compat_privileges", Superuser),
+    Parameter::boolean("operator_precedence_warning", User).until(13),
+    Parameter::boolean("quote_all_identifiers", User),
+    Parameter::boolean("standard_conforming_strings", User),
+    Parameter::boolean("synchronize_seqscans", User),
+    Parameter::boolean("transform_null_equals", User),
+    Parameter::boolean("allow_alter_system", Sighup).since(17),
+    Parameter::enumeration("force_parallel_mode", User, &["off", "on", "regress"])
+        .or_bool()
+        .until(15),
+    Parameter::enumeration("debug_parallel_query", User, &["off", "on", "regress"])
+        .or_bool()
+        .since(16),
+    // error handling
+    Parameter::boolean("exit_on_error", User),
+    Parameter::boolean("restart_after_crash", Sighup),
+    Parameter::boolean("data_sync_retry", Postmaster),
+    Parameter::enumeration("recovery_init_sync_method", Sighup, &["fsync", "syncfs"]).since(14),
+    Parameter::boolean("remove_temp_files_after_crash", Sighup).since(14),
+    // preset options
+    Parameter::string("block_size", Internal),
+    Parameter::string("data_checksums", Internal),
+    Parameter::string("data_directory_mode", Internal),
+    Parameter::string("debug_assertions", Internal),
+    Parameter::string("huge_pages_status", Internal).since(17),
+    Parameter::string("in_hot_standby", Internal).since(14),
+    Parameter::string("integer_datetimes", Internal),
+    Parameter::string("lc_collate", Internal).until(15),
+    Parameter::string("lc_ctype", Internal).until(15),
+    Parameter::string("max_function_args", Internal),
+    Parameter::string("max_identifier_length", Internal),
+    Parameter::string("max_index_keys", Internal),
+    Parameter::string("num_os_semaphores", Internal).since(18),
+    Parameter::string("segment_size", Internal),
+    Parameter::string("server_encoding", Internal),
+    Parameter::string("server_version", Internal),
+    Parameter::string("server_version_num", Internal),
+    Parameter::string("shared_memory_size", Internal).since(15),
+    Parameter::string("shared_memory_size_in_huge_pages", Internal).since(15),
+    Parameter::string("ssl_library", Internal),
+    Parameter::string("wal_block_size", Internal),
+    Parameter::string("wal_segment_size", Internal),
+];
+
+/// The context of a parameter in a major version, if the parameter is known.
+///
+/// Parameter names are case insensitive. Versions that are not numbers match the
+/// parameters of all versions.
+///
+/// # Arguments
+/// - `name` - The parameter
+/// - `version` - The PostgreSQL major version
+pub fn context(name: &str, version: &str) -> Option<ParameterContext> {
+    find(name, version).map(|parameter| parameter.context)
+}
+
+/// Checks whether a change of a parameter only takes effect after a restart.
+///
+/// Unknown parameters, such as those of extensions, are assumed to need a restart.
+///
+/// # Arguments
+/// - `name` - The parameter
+/// - `version` - The PostgreSQL major version
+pub fn requires_restart(name: &str, version: &str) -> bool {
+    context(name, version).is_none_or(ParameterContext::requires_restart)
+}
+
+/// Checks a parameter and its value against a major version, returns the problem found.
+///
+/// Parameters of extensions, named `<extension>.<parameter>`, accept any value.
+///
+/// # Arguments
+/// - `name` - The parameter
+/// - `value` - The value
+/// - `version` - The PostgreSQL major version
+pub fn validate(name: &str, value: &str, version: &str) -> Result<(), String> {
+    if value.contains(['\n', '\r', '\0']) {
+        return Err("the value spans several lines".to_string());
+    }
+    if is_extension_parameter(name) {
+        return Ok(());
+    }
+
+    let Some(parameter) = find(name, version) else {
+        return Err(format!("unknown parameter in PostgreSQL {}", version));
+    };
+    if parameter.context == Internal {
+        return Err("read-only parameter".to_string());
+    }
+
+    parameter.check(value)
+}
+
+fn find(name: &str, version: &str) -> Option<&'static Parameter> {
+    let version = version.parse::<u32>().ok();
+    PARAMETERS
+        .iter()
+        .find(|parameter| parameter.name.eq_ignore_ascii_case(name) && parameter.exists_in(version))
+}
+
+fn is_extension_parameter(name: &str) -> bool {
+    name.split_once('.').is_some_and(|(extension, parameter)| {
+        [extension, parameter].iter().all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        })
+    })
+}
+
+/// Checks the spellings the server accepts for a boolean, including unique prefixes.
+fn is_boolean(value: &str) -> bool {
+    let value = value.trim().to_ascii_lowercase();
+    if matches!(value.as_str(), "on" | "of" | "off" | "1" | "0") {
+        return true;
+    }
+    !value.is_empty()
+        && ["true", "false", "yes", "no"]
+            .iter()
+            .any(|spelling| spelling.starts_with(&value))
+}
+
+/// Parses a number with an optional unit and converts it to the base unit.
+fn parse_number(value: &str, unit: Unit) -> Result<f64, String> {
+    let trimmed = value.trim();
+    // units start with a letter other than the exponent of the number
+    let split = trimmed
+        .find(|c: char| c.is_ascii_alphabetic() && !matches!(c, 'e' | 'E'))
+        .unwrap_or(trimmed.len());
+    let (number, suffix) = trimmed.split_at(split);
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+
+    let suffix = suffix.trim();
+    if suffix.is_empty() {
+        return Ok(number);
+    }
+    let Some((units, base)) = unit.units() else {
+        return Err(format!("'{}' does not take a unit", value));
+    };
+    match units.iter().find(|(name, _)| *name == suffix) {
+        Some((_, size)) => Ok(number * size / base),
+        None => Err(format!(
+            "'{}' has an invalid unit, use {}",
+            value,
+            units
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_take_memory_units() {
+        assert!(validate("shared_buffers", "128kB", "17").is_ok());
+        assert!(validate("shared_buffers", "1GB", "17").is_ok());
+        assert!(validate("shared_buffers", "16", "17").is_ok());
+        // 8kB is a single page, the minimum is 16
+        assert!(validate("shared_buffers", "8kB", "17").is_err());
+        assert!(validate("shared_buffers", "120kB", "17").is_err());
+    }
+
+    #[test]
+    fn seconds_take_time_units() {
+        assert!(validate("checkpoint_timeout", "1min", "17").is_ok());
+        assert!(validate("checkpoint_timeout", "30s", "17").is_ok());
+        assert!(validate("checkpoint_timeout", "1d", "17").is_ok());
+        assert!(validate("checkpoint_timeout", "29s", "17").is_err());
+        assert!(validate("checkpoint_timeout", "2d", "17").is_err());
+        assert!(validate("checkpoint_timeout", "5 min", "17").is_ok());
+    }
+
+    #[test]
+    fn integers_stop_at_int_max() {
+        assert!(validate("statement_timeout", "2147483647", "17").is_ok());
+        assert!(validate("statement_timeout", "2147483648", "17").is_err());
+        assert!(validate("statement_timeout", "0", "17").is_ok());
+        assert!(validate("statement_timeout", "-1", "17").is_err());
+        assert!(validate("work_mem", "2147483647kB", "17").is_ok());
+        assert!(validate("work_mem", "2TB", "17").is_err());
+    }
+
+    #[test]
+    fn units_are_checked() {
+        assert!(validate("max_connections", "100kB", "17").is_err());
+        assert!(validate("work_mem", "64XB", "17").is_err());
+        assert!(validate("work_mem", "64mb", "17").is_err());
+        assert!(validate("work_mem", "sixty", "17").is_err());
+        assert!(validate("random_page_cost", "1.1", "17").is_ok());
+        assert!(validate("random_page_cost", "-1", "17").is_err());
+    }
+
+    #[test]
+    fn booleans_and_enumerations() {
+        for value in ["on", "off", "of", "true", "t", "y", "no", "1", "0"] {
+            assert!(validate("ssl", value, "17").is_ok(), "{}", value);
+        }
+        assert!(validate("ssl", "o", "17").is_err());
+        assert!(validate("ssl", "maybe", "17").is_err());
+        assert!(validate("password_encryption", "SCRAM-SHA-256", "17").is_ok());
+        assert!(validate("password_encryption", "plain", "17").is_err());
+    }
+
+    #[test]
+    fn parameters_follow_their_versions() {
+        assert!(validate("reserved_connections", "3", "15").is_err());
+        assert!(validate("reserved_connections", "3", "16").is_ok());
+        assert!(validate("db_user_namespace", "off", "16").is_ok());
+        assert!(validate("db_user_namespace", "off", "17").is_err());
+        assert!(validate("vacuum_defer_cleanup_age", "0", "15").is_ok());
+        assert!(validate("vacuum_defer_cleanup_age", "0", "16").is_err());
+        // versions that are not numbers know the parameters of all versions
+        assert!(validate("db_user_namespace", "off", "latest").is_ok());
+    }
+
+    #[test]
+    fn unknown_and_extension_parameters() {
+        assert!(validate("no_such_parameter", "1", "17").is_err());
+        assert!(validate("pg_stat_statements.max", "anything", "17").is_ok());
+        assert!(validate("work_mem", "64MB\nfsync = off", "17").is_err());
+        assert!(requires_restart("pg_stat_statements.max", "17"));
+        assert!(requires_restart("shared_buffers", "17"));
+        assert!(!requires_restart("WORK_MEM", "17"));
+    }
+}
@@ -6,7 +6,10 @@
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
 use crate::manager::{LABEL_CLUSTER, LABEL_COMPONENT};
-use k8s_openapi::api::core::v1::{HostPathVolumeSource, PersistentVolume, PersistentVolumeSpec};
+use crate::quantity;
+use k8s_openapi::api::core::v1::{
+    HostPathVolumeSource, ObjectReference, PersistentVolume, PersistentVolumeSpec,
+};
 use k8s_openapi::{
     api::core::v1::{PersistentVolumeClaim, PersistentVolumeClaimSpec, VolumeResourceRequirements},
     apimachinery::pkg::api::resource::Quantity,
@@ -14,27 +17,76 @@ use k8s_openapi::{
 use kube::api::ObjectMeta;
 use std::collections::BTreeMap;
 
+/// Storage class of the hostPath volumes created in local mode
+pub const STORAGE_CLASS_LOCAL: &str = "manual";
+
+/// Settings of the volume claims of a component
+#[derive(Clone)]
+pub struct StorageConfig {
+    /// Size in GiB
+    pub size: u32,
+    /// Storage class, the default class of the Kubernetes cluster when absent
+    pub storage_class_name: Option<String>,
+    pub access_mode: String,
+    pub volume_mode: String,
+}
+
+impl StorageConfig {
+    /// The settings for a claim, an existing claim keeps its class and modes as these
+    /// cannot change.
+    ///
+    /// # Arguments
+    /// - `existing` - The claim as it exists in the cluster
+    pub fn for_claim(&self, existing: Option<&PersistentVolumeClaim>) -> StorageConfig {
+        let Some(spec) = existing.and_then(|claim| claim.spec.as_ref()) else {
+            return self.clone();
+        };
+
+        StorageConfig {
+            size: self.size,
+            storage_class_name: spec.storage_class_name.clone(),
+            access_mode: spec
+                .access_modes
+                .as_ref()
+                .and_then(|modes| modes.first().cloned())
+                .unwrap_or_else(|| self.access_mode.clone()),
+            volume_mode: spec
+                .volume_mode
+                .clone()
+                .unwrap_or_else(|| self.volume_mode.clone()),
+        }
+    }
+
+    fn capacity(&self) -> BTreeMap<String, Quantity> {
+        let mut cap: BTreeMap<String, Quantity> = BTreeMap::new();
+        cap.insert("storage".to_owned(), Quantity(format!("{}Gi", self.size)));
+        cap
+    }
+}
+
+/// Number of bytes of a quantity such as `10Gi` or `500M`
+///
+/// # Arguments
+/// - `quantity` - The quantity
+pub fn quantity_bytes(quantity: &Quantity) -> Option<u64> {
+    quantity::bytes(quantity.0.trim())
+}
+
 /// Builds a persistent volume claim object
 ///
 /// # Arguments
 /// - `name` - The name
 /// - `namespace` - The namespace
-/// - `storage` - The storage size
+/// - `storage` - The storage settings
 pub fn build_pvc(
     name: &str,
     namespace: &str,
-    storage: u32,
+    storage: &StorageConfig,
     label_app: &str,
 ) -> PersistentVolumeClaim {
     let mut labels: BTreeMap<String, String> = BTreeMap::new();
     labels.insert("app".to_owned(), label_app.to_owned());
 
-    let mut size: String = storage.to_string().to_owned();
-    size.push_str("Gi");
-
-    let mut cap: BTreeMap<String, Quantity> = BTreeMap::new();
-    cap.insert("storage".to_owned(), Quantity(size));
-
     // Definition of the persistent volume claim
     PersistentVolumeClaim {
         metadata: ObjectMeta {
@@ -44,10 +96,11 @@ pub fn build_pvc(
             ..ObjectMeta::default()
         },
         spec: Some(PersistentVolumeClaimSpec {
-            storage_class_name: Some("manual".to_owned()),
-            access_modes: Some(vec!["ReadWriteMany".to_owned()]),
+            storage_class_name: storage.storage_class_name.clone(),
+            access_modes: Some(vec![storage.access_mode.clone()]),
+            volume_mode: Some(storage.volume_mode.clone()),
             resources: Some(VolumeResourceRequirements {
-                requests: Some(cap.clone()),
+                requests: Some(storage.capacity()),
                 ..VolumeResourceRequirements::default()
             }),
             ..PersistentVolumeClaimSpec::default()
@@ -56,25 +109,23 @@ pub fn build_pvc(
     }
 }
 
-/// Builds a persistent volume object for the manual local storage path.
+/// Builds a hostPath persistent volume object for the local storage mode.
+///
+/// The claim reference reserves the volume for its claim, so claims of other clusters
+/// never bind to it.
 pub fn build_pv(
     name: &str,
-    storage: u32,
+    storage: &StorageConfig,
     label_app: &str,
     host_path: &str,
     cluster_name: &str,
+    claim_ref: ObjectReference,
 ) -> PersistentVolume {
     let mut labels: BTreeMap<String, String> = BTreeMap::new();
     labels.insert("app".to_owned(), label_app.to_owned());
     labels.insert("type".to_owned(), "local".to_owned());
     labels.insert(LABEL_CLUSTER.to_string(), cluster_name.to_string());
 
-    let mut size: String = storage.to_string().to_owned();
-    size.push_str("Gi");
-
-    let mut cap: BTreeMap<String, Quantity> = BTreeMap::new();
-    cap.insert("storage".to_owned(), Quantity(size));
-
     PersistentVolume {
         metadata: ObjectMeta {
             name: Some(name.to_owned()),
@@ -82,13 +133,15 @@ pub fn build_pv(
             ..ObjectMeta::default()
         },
         spec: Some(PersistentVolumeSpec {
-            storage_class_name: Some("manual".to_owned()),
-            capacity: Some(cap),
-            access_modes: Some(vec!["ReadWriteMany".to_owned()]),
+            storage_class_name: storage.storage_class_name.clone(),
+            capacity: Some(storage.capacity()),
+            access_modes: Some(vec![storage.access_mode.clone()]),
+            volume_mode: Some(storage.volume_mode.clone()),
             host_path: Some(HostPathVolumeSource {
                 path: host_path.to_owned(),
                 ..HostPathVolumeSource::default()
             }),
+            claim_ref: Some(claim_ref),
             ..PersistentVolumeSpec::default()
         }),
         ..PersistentVolume::default()
@@ -98,9 +151,10 @@ pub fn build_pv(
 /// Build a pv for pgmoneta
 pub fn build_pgmoneta_pv(
     name: &str,
-    storage: u32,
+    storage: &StorageConfig,
     host_path: &str,
     cluster_name: &str,
+    claim_ref: ObjectReference,
 ) -> PersistentVolume {
     let mut labels: BTreeMap<String, String> = BTreeMap::new();
     labels.insert("app".to_owned(), cluster_name.to_owned() + "-pgmoneta");
@@ -108,12 +162,6 @@ pub fn build_pgmoneta_pv(
     labels.insert(LABEL_CLUSTER.to_string(), cluster_name.to_string());
     labels.insert(LABEL_COMPONENT.to_string(), "pgmoneta".to_string());
 
-    let mut size: String = storage.to_string().to_owned();
-    size.push_str("Gi");
-
-    let mut cap: BTreeMap<String, Quantity> = BTreeMap::new();
-    cap.insert("storage".to_owned(), Quantity(size));
-
     PersistentVolume {
         metadata: ObjectMeta {
             name: Some(name.to_owned()),
@@ -121,13 +169,15 @@ pub fn build_pgmoneta_pv(
             ..ObjectMeta::default()
         },
         spec: Some(PersistentVolumeSpec {
-            storage_class_name: Some("manual".to_owned()),
-            capacity: Some(cap),
-            access_modes: Some(vec!["ReadWriteMany".to_owned()]),
+            storage_class_name: storage.storage_class_name.clone(),
+            capacity: Some(storage.capacity()),
+            access_modes: Some(vec![storage.access_mode.clone()]),
+            volume_mode: Some(storage.volume_mode.clone()),
             host_path: Some(HostPathVolumeSource {
                 path: host_path.to_owned(),
                 ..HostPathVolumeSource::default()
             }),
+            claim_ref: Some(claim_ref),
             ..PersistentVolumeSpec::default()
         }),
         ..PersistentVolume::default()
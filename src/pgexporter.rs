@@ -7,54 +7,27 @@
  */
 
 use crate::crd::v1::ResourceRequirements;
-use crate::workload;
+use crate::credentials::{Credential, Credentials};
+use crate::workload::{self, ContainerImage};
 use k8s_openapi::{
     api::{
         apps::v1::{Deployment, DeploymentSpec},
-        core::v1::{
-            Container, ContainerPort, EnvVar, EnvVarSource, ExecAction, PodSpec, PodTemplateSpec,
-            Probe, Secret, SecretKeySelector,
-        },
+        core::v1::{Container, ContainerPort, EnvVar, ExecAction, PodSpec, PodTemplateSpec, Probe},
     },
     apimachinery::pkg::apis::meta::v1::LabelSelector,
 };
 use kube::api::ObjectMeta;
 use std::collections::BTreeMap;
 use std::fs;
-/// Builds a secret containing the pgexporter password
-///
-/// # Arguments
-/// - `name` - Name of the secret
-/// - `namespace` - Namespace
-/// - `backup_password` - The backup user password
-pub fn build_secret(name: &str, namespace: &str, exporter_password: &str) -> Secret {
-    let mut labels: BTreeMap<String, String> = BTreeMap::new();
-    labels.insert("app".to_owned(), name.to_owned());
-
-    let mut string_data = BTreeMap::new();
-    string_data.insert(
-        "PG_EXPORTER_PASSWORD".to_string(),
-        exporter_password.to_string(),
-    );
-
-    Secret {
-        metadata: ObjectMeta {
-            name: Some(name.to_owned()),
-            namespace: Some(namespace.to_owned()),
-            labels: Some(labels),
-            ..ObjectMeta::default()
-        },
-        string_data: Some(string_data),
-        ..Secret::default()
-    }
-}
 
 pub fn pgexporter_generate() {
     let data = serde_yaml::to_string(&build_deployment(
         "postgresql-pgexporter",
         "default",
+        &ContainerImage::new(workload::PGEXPORTER_IMAGE),
         "postgresql",
-        "postgresql-pgexporter-secret",
+        &Credentials::generated("postgresql").exporter,
+        None,
         None,
     ))
     .expect("Can't serialize...");
@@ -66,14 +39,18 @@ pub fn pgexporter_generate() {
 /// # Arguments
 /// - `name` - Name of the deployment
 /// - `namespace` - Namespace
+/// - `image` - The pgexporter image and how it is pulled
 /// - `primary_name` - Name of the primary service for PG_PRIMARY_NAME env var
-/// - `secret_name` - Name of the secret containing PG_BACKUP_PASSWORD
+/// - `exporter` - The exporter user credential
+/// - `credentials_rotated_at` - Last rotation of the exporter credential, restarts pods on change
 /// - `resources` - Name of the resources for pgexporter
 pub fn build_deployment(
     name: &str,
     namespace: &str,
+    image: &ContainerImage,
     primary_name: &str,
-    secret_name: &str,
+    exporter: &Credential,
+    credentials_rotated_at: Option<&str>,
     resources: Option<&ResourceRequirements>,
 ) -> Deployment {
     let mut labels: BTreeMap<String, String> = BTreeMap::new();
@@ -81,6 +58,15 @@ pub fn build_deployment(
     labels.insert("app".to_owned(), name.to_owned());
     labels.insert("role".to_owned(), "exporter".to_owned());
 
+    // setup annotations for rolling restarts
+    let mut annotations: BTreeMap<String, String> = BTreeMap::new();
+    if let Some(rotated_at) = credentials_rotated_at {
+        annotations.insert(
+            workload::CREDENTIALS_ROTATED_AT.to_string(),
+            rotated_at.to_string(),
+        );
+    }
+
     Deployment {
         metadata: ObjectMeta {
             name: Some(name.to_owned()),
@@ -97,13 +83,14 @@ pub fn build_deployment(
             template: PodTemplateSpec {
                 metadata: Some(ObjectMeta {
                     labels: Some(labels),
+                    annotations: (!annotations.is_empty()).then_some(annotations),
                     ..ObjectMeta::default()
                 }),
                 spec: Some(PodSpec {
                     containers: vec![Container {
                         name: name.to_owned(),
-                        image: Some(workload::PGEXPORTER_IMAGE.to_string()),
-                        image_pull_policy: Some("IfNotPresent".to_string()),
+                        image: Some(image.name.clone()),
+                        image_pull_policy: Some(image.pull_policy.clone()),
                         ports: Some(vec![
                             ContainerPort {
                                 container_port: workload::PGEXPORTER_PORT,
@@ -125,23 +112,8 @@ pub fn build_deployment(
                                 value: Some("5432".to_string()),
                                 ..EnvVar::default()
                             },
-                            EnvVar {
-                                name: "PG_EXPORTER_NAME".to_string(),
-                                value: Some("pgexporter".to_string()),
-                                ..EnvVar::default()
-                            },
-                            EnvVar {
-                                name: "PG_EXPORTER_PASSWORD".to_string(),
-                                value_from: Some(EnvVarSource {
-                                    secret_key_ref: Some(SecretKeySelector {
-                                        name: secret_name.to_string(),
-                                        key: "PG_EXPORTER_PASSWORD".to_string(),
-                                        ..SecretKeySelector::default()
-                                    }),
-                                    ..EnvVarSource::default()
-                                }),
-                                ..EnvVar::default()
-                            },
+                            exporter.username_env("PG_EXPORTER_NAME"),
+                            exporter.password_env("PG_EXPORTER_PASSWORD"),
                         ]),
                         liveness_probe: Some(Probe {
                             initial_delay_seconds: Some(30),
@@ -170,6 +142,7 @@ pub fn build_deployment(
                         resources: k8s_resources,
                         ..Container::default()
                     }],
+                    image_pull_secrets: image.image_pull_secrets(),
                     ..PodSpec::default()
                 }),
             },
@@ -184,11 +157,13 @@ pub fn build_deployment(
 /// # Arguments
 /// - `name` - Name of the deployment
 /// - `namespace` - Namespace
+/// - `image` - The monitoring image and how it is pulled
 /// - `service_host` - Name of the pgexporter service for PGEXPORTER_SERVICE_HOST env var
 /// - `resources` - Name of the resources for pgexporter-mon
 pub fn build_monitoring_deployment(
     name: &str,
     namespace: &str,
+    image: &ContainerImage,
     service_host: &str,
     resources: Option<&ResourceRequirements>,
 ) -> Deployment {
@@ -217,8 +192,8 @@ pub fn build_monitoring_deployment(
                 spec: Some(PodSpec {
                     containers: vec![Container {
                         name: name.to_owned(),
-                        image: Some(workload::PGEXPORTER_MON_IMAGE.to_string()),
-                        image_pull_policy: Some("IfNotPresent".to_string()),
+                        image: Some(image.name.clone()),
+                        image_pull_policy: Some(image.pull_policy.clone()),
                         ports: Some(vec![
                             ContainerPort {
                                 container_port: workload::PGEXPORTER_MON_GRAFANA_PORT,
@@ -259,6 +234,7 @@ pub fn build_monitoring_deployment(
                         resources: k8s_resources,
                         ..Container::default()
                     }],
+                    image_pull_secrets: image.image_pull_secrets(),
                     ..PodSpec::default()
                 }),
             },
@@ -6,14 +6,14 @@
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
 
-use crate::workload;
+use crate::credentials::Credential;
+use crate::workload::{self, ContainerImage};
 use k8s_openapi::{
     api::{
         apps::v1::{Deployment, DeploymentSpec},
         core::v1::{
-            Container, ContainerPort, EnvVar, EnvVarSource, ExecAction,
-            PersistentVolumeClaimVolumeSource, PodSpec, PodTemplateSpec, Probe, Secret,
-            SecretKeySelector, Volume, VolumeMount,
+            Container, ContainerPort, EnvVar, ExecAction, PersistentVolumeClaimVolumeSource,
+            PodSpec, PodTemplateSpec, Probe, Volume, VolumeMount,
         },
     },
     apimachinery::pkg::apis::meta::v1::LabelSelector,
@@ -21,53 +21,38 @@ use k8s_openapi::{
 use kube::api::ObjectMeta;
 use std::collections::BTreeMap;
 
-/// Builds a secret containing the pgmoneta backup password
-///
-/// # Arguments
-/// - `name` - Name of the secret
-/// - `namespace` - Namespace
-/// - `backup_password` - The backup user password
-pub fn build_secret(name: &str, namespace: &str, backup_password: &str) -> Secret {
-    let mut labels: BTreeMap<String, String> = BTreeMap::new();
-    labels.insert("app".to_owned(), name.to_owned());
-
-    let mut string_data = BTreeMap::new();
-    string_data.insert(
-        "PG_BACKUP_PASSWORD".to_string(),
-        backup_password.to_string(),
-    );
-
-    Secret {
-        metadata: ObjectMeta {
-            name: Some(name.to_owned()),
-            namespace: Some(namespace.to_owned()),
-            labels: Some(labels),
-            ..ObjectMeta::default()
-        },
-        string_data: Some(string_data),
-        ..Secret::default()
-    }
-}
-
 /// Builds a pgmoneta deployment object
 ///
 /// # Arguments
 /// - `name` - Name of the deployment
 /// - `namespace` - Namespace
+/// - `image` - The pgmoneta image and how it is pulled
 /// - `primary_name` - Name of the primary service for PG_PRIMARY_NAME env var
 /// - `pvc_name` - Name of the PVC to mount at /home/pgmoneta
-/// - `secret_name` - Name of the secret containing PG_BACKUP_PASSWORD
+/// - `backup` - The backup user credential
+/// - `credentials_rotated_at` - Last rotation of the backup credential, restarts pods on change
 pub fn build_deployment(
     name: &str,
     namespace: &str,
+    image: &ContainerImage,
     primary_name: &str,
     pvc_name: &str,
-    secret_name: &str,
+    backup: &Credential,
+    credentials_rotated_at: Option<&str>,
 ) -> Deployment {
     let mut labels: BTreeMap<String, String> = BTreeMap::new();
     labels.insert("app".to_owned(), name.to_owned());
     labels.insert("role".to_owned(), "backup".to_owned());
 
+    // setup annotations for rolling restarts
+    let mut annotations: BTreeMap<String, String> = BTreeMap::new();
+    if let Some(rotated_at) = credentials_rotated_at {
+        annotations.insert(
+            workload::CREDENTIALS_ROTATED_AT.to_string(),
+            rotated_at.to_string(),
+        );
+    }
+
     Deployment {
         metadata: ObjectMeta {
             name: Some(name.to_owned()),
@@ -84,13 +69,14 @@ pub fn build_deployment(
             template: PodTemplateSpec {
                 metadata: Some(ObjectMeta {
                     labels: Some(labels),
+                    annotations: (!annotations.is_empty()).then_some(annotations),
                     ..ObjectMeta::default()
                 }),
                 spec: Some(PodSpec {
                     containers: vec![Container {
                         name: name.to_owned(),
-                        image: Some(workload::PGMONETA_IMAGE.to_string()),
-                        image_pull_policy: Some("IfNotPresent".to_string()),
+                        image: Some(image.name.clone()),
+                        image_pull_policy: Some(image.pull_policy.clone()),
                         ports: Some(vec![
                             ContainerPort {
                                 container_port: workload::PGMONETA_PORT,
@@ -112,23 +98,8 @@ pub fn build_deployment(
                                 value: Some("5432".to_string()),
                                 ..EnvVar::default()
                             },
-                            EnvVar {
-                                name: "PG_BACKUP_NAME".to_string(),
-                                value: Some("backup_user".to_string()),
-                                ..EnvVar::default()
-                            },
-                            EnvVar {
-                                name: "PG_BACKUP_PASSWORD".to_string(),
-                                value_from: Some(EnvVarSource {
-                                    secret_key_ref: Some(SecretKeySelector {
-                                        name: secret_name.to_string(),
-                                        key: "PG_BACKUP_PASSWORD".to_string(),
-                                        ..SecretKeySelector::default()
-                                    }),
-                                    ..EnvVarSource::default()
-                                }),
-                                ..EnvVar::default()
-                            },
+                            backup.username_env("PG_BACKUP_NAME"),
+                            backup.password_env("PG_BACKUP_PASSWORD"),
                             EnvVar {
                                 name: "PG_BACKUP_SLOT".to_string(),
                                 value: Some("backup".to_string()),
@@ -174,6 +145,7 @@ pub fn build_deployment(
                         }),
                         ..Volume::default()
                     }]),
+                    image_pull_secrets: image.image_pull_secrets(),
                     ..PodSpec::default()
                 }),
             },
@@ -6,11 +6,11 @@
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
 
+use crate::manager::LABEL_CLUSTER;
 use crate::workload::{self, DeploymentConfig};
-use k8s_openapi::api::core::v1::ConfigMapVolumeSource;
 use k8s_openapi::{
     api::{
-        apps::v1::{Deployment, DeploymentSpec},
+        apps::v1::{Deployment, DeploymentSpec, DeploymentStrategy},
         core::v1::{
             Container, ContainerPort, EnvVar, PersistentVolumeClaimVolumeSource, PodSpec,
             PodTemplateSpec, Volume, VolumeMount,
@@ -38,13 +38,54 @@ pub fn build(name: &str, namespace: &str, config: DeploymentConfig) -> Deploymen
         annotations.insert(workload::HASH_CONFIG.to_string(), hash.to_string());
     }
 
+    // the cluster label lets the role Services select members, the selector is immutable
+    let mut pod_labels = labels.clone();
+    pod_labels.insert(LABEL_CLUSTER.to_string(), config.cluster_name.to_string());
+
     let k8s_resources = config.resources.map(workload::map_resources);
 
-    // setup volumes (pvc + optional config map)
+    let mut env = vec![
+        EnvVar {
+            name: "PG_DATABASE".to_string(),
+            value: Some("mydb".to_string()),
+            ..Default::default()
+        },
+        config.credentials.app.username_env("PG_USER_NAME"),
+        config.credentials.app.password_env("PG_USER_PASSWORD"),
+        config
+            .credentials
+            .replication
+            .username_env("PG_REPLICATION_NAME"),
+        config
+            .credentials
+            .replication
+            .password_env("PG_REPLICATION_PASSWORD"),
+        config.credentials.backup.username_env("PG_BACKUP_NAME"),
+        config.credentials.backup.password_env("PG_BACKUP_PASSWORD"),
+        EnvVar {
+            name: "PG_BACKUP_SLOT".to_string(),
+            value: Some("backup".to_string()),
+            ..Default::default()
+        },
+    ];
+    // without managed rules the image writes a pg_hba.conf accepting all addresses
+    if config.hba_config_map.is_none() {
+        env.push(EnvVar {
+            name: "PG_NETWORK_MASK".to_string(),
+            value: Some("all".to_string()),
+            ..Default::default()
+        });
+    }
+    if let Some(superuser) = &config.credentials.superuser {
+        env.push(superuser.username_env("PG_SUPERUSER_NAME"));
+        env.push(superuser.password_env("PG_SUPERUSER_PASSWORD"));
+    }
+
+    // setup volumes (pvc + optional config maps)
     let mut volumes = vec![Volume {
         name: workload::DATA_VOLUME.to_string(),
         persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
-            claim_name: format!("{}-pv-claim", name),
+            claim_name: config.claim_name.to_string(),
             ..Default::default()
         }),
         ..Default::default()
@@ -56,23 +97,12 @@ pub fn build(name: &str, namespace: &str, config: DeploymentConfig) -> Deploymen
         ..Default::default()
     }];
 
-    if let Some(cm_name) = config.config_map_name {
-        volumes.push(Volume {
-            name: workload::CONFIG_VOLUME.to_string(),
-            config_map: Some(ConfigMapVolumeSource {
-                name: cm_name.to_string(),
-                ..Default::default()
-            }),
-            ..Default::default()
-        });
-        volume_mounts.push(VolumeMount {
-            name: workload::CONFIG_VOLUME.to_string(),
-            mount_path: workload::CONFIG_MOUNT.to_string(),
-            sub_path: Some("postgresql.conf".to_string()),
-            ..Default::default()
-        });
+    if let Some(wal_claim_name) = config.wal_claim_name {
+        workload::mount_wal(wal_claim_name, &mut volumes, &mut volume_mounts, &mut env);
     }
 
+    let args = workload::mount_config(&config, &mut volumes, &mut volume_mounts);
+
     // Definition of the deployment
     Deployment {
         metadata: ObjectMeta {
@@ -82,84 +112,48 @@ pub fn build(name: &str, namespace: &str, config: DeploymentConfig) -> Deploymen
             ..Default::default()
         },
         spec: Some(DeploymentSpec {
-            replicas: Some(1i32),
+            replicas: Some(if config.fenced { 0 } else { 1 }),
+            // a rolling update would start a second postmaster on the same volume
+            strategy: Some(DeploymentStrategy {
+                type_: Some("Recreate".to_string()),
+                ..Default::default()
+            }),
             selector: LabelSelector {
                 match_labels: Some(labels.clone()),
                 ..Default::default()
             },
             template: PodTemplateSpec {
                 metadata: Some(ObjectMeta {
-                    labels: Some(labels),
+                    labels: Some(pod_labels),
                     annotations: (!annotations.is_empty()).then_some(annotations),
                     ..Default::default()
                 }),
                 spec: Some(PodSpec {
                     containers: vec![Container {
                         name: name.to_owned(),
-                        image: Some(config.image.to_string()),
-                        image_pull_policy: Some("IfNotPresent".to_string()),
+                        image: Some(config.image.name.clone()),
+                        image_pull_policy: Some(config.image.pull_policy.clone()),
                         resources: k8s_resources,
                         volume_mounts: Some(volume_mounts),
-                        args: config.config_map_name.map(|_| {
-                            vec![
-                                "-c".into(),
-                                "config_file=/etc/postgresql/postgresql.conf".into(),
-                            ]
-                        }),
+                        args,
                         ports: Some(vec![ContainerPort {
                             container_port: 5432,
                             ..Default::default()
                         }]),
-                        env: Some(vec![
-                            EnvVar {
-                                name: "PG_DATABASE".to_string(),
-                                value: Some("mydb".to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "PG_USER_NAME".to_string(),
-                                value: Some("myuser".to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "PG_USER_PASSWORD".to_string(),
-                                value: Some("mypass".to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "PG_REPLICATION_NAME".to_string(),
-                                value: Some("repl_user".to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "PG_REPLICATION_PASSWORD".to_string(),
-                                value: Some("repl_pass".to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "PG_BACKUP_NAME".to_string(),
-                                value: Some("backup_user".to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "PG_BACKUP_PASSWORD".to_string(),
-                                value: Some("backup_pass".to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "PG_BACKUP_SLOT".to_string(),
-                                value: Some("backup".to_string()),
-                                ..Default::default()
-                            },
-                            EnvVar {
-                                name: "PG_NETWORK_MASK".to_string(),
-                                value: Some("all".to_string()),
-                                ..Default::default()
-                            },
-                        ]),
+                        env: Some(env),
                         ..Default::default()
                     }],
                     volumes: Some(volumes),
+                    image_pull_secrets: config.image.image_pull_secrets(),
+                    node_selector: config
+                        .placement
+                        .and_then(|placement| placement.node_selector.clone()),
+                    tolerations: config
+                        .placement
+                        .and_then(|placement| placement.tolerations.clone()),
+                    affinity: config
+                        .placement
+                        .and_then(|placement| placement.affinity.clone()),
                     ..Default::default()
                 }),
             },
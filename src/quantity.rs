@@ -0,0 +1,120 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+/// Binary suffixes of Kubernetes quantities
+const BINARY_SUFFIXES: [(&str, f64); 6] = [
+    ("Ki", 1024.0),
+    ("Mi", 1_048_576.0),
+    ("Gi", 1_073_741_824.0),
+    ("Ti", 1_099_511_627_776.0),
+    ("Pi", 1_125_899_906_842_624.0),
+    ("Ei", 1_152_921_504_606_846_976.0),
+];
+
+/// Decimal suffixes of Kubernetes quantities
+const DECIMAL_SUFFIXES: [(&str, f64); 10] = [
+    ("n", 1e-9),
+    ("u", 1e-6),
+    ("m", 1e-3),
+    ("", 1.0),
+    ("k", 1e3),
+    ("M", 1e6),
+    ("G", 1e9),
+    ("T", 1e12),
+    ("P", 1e15),
+    ("E", 1e18),
+];
+
+/// Parses a Kubernetes quantity, such as `16Gi`, `500m` or `1e3`, into its value.
+///
+/// Returns `None` when the quantity is malformed or negative.
+///
+/// # Arguments
+/// - `quantity` - The quantity
+pub fn parse(quantity: &str) -> Option<f64> {
+    let split = quantity
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '+'))
+        .unwrap_or(quantity.len());
+    let (number, suffix) = quantity.split_at(split);
+    if !number.chars().any(|c| c.is_ascii_digit())
+        || number.matches('.').count() > 1
+        || number[1..].contains('+')
+    {
+        return None;
+    }
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier = if let Some(exponent) = suffix
+        .strip_prefix(['e', 'E'])
+        .filter(|exponent| is_exponent(exponent))
+    {
+        10f64.powi(exponent.parse().ok()?)
+    } else {
+        BINARY_SUFFIXES
+            .iter()
+            .chain(DECIMAL_SUFFIXES.iter())
+            .find(|(name, _)| *name == suffix)
+            .map(|(_, multiplier)| *multiplier)?
+    };
+
+    Some(number * multiplier)
+}
+
+/// Parses a memory quantity into bytes
+///
+/// # Arguments
+/// - `quantity` - The quantity, such as `16Gi`
+pub fn bytes(quantity: &str) -> Option<u64> {
+    parse(quantity).map(|value| value.ceil() as u64)
+}
+
+/// Parses a CPU quantity into millicores
+///
+/// # Arguments
+/// - `quantity` - The quantity, such as `500m` or `2`
+pub fn millicores(quantity: &str) -> Option<u64> {
+    parse(quantity).map(|value| (value * 1000.0).ceil() as u64)
+}
+
+fn is_exponent(exponent: &str) -> bool {
+    let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_and_decimal_suffixes() {
+        assert_eq!(bytes("16Gi"), Some(17_179_869_184));
+        assert_eq!(bytes("500M"), Some(500_000_000));
+        assert_eq!(bytes("1.5Ki"), Some(1536));
+        assert_eq!(bytes("128974848"), Some(128_974_848));
+        assert_eq!(bytes("129e6"), Some(129_000_000));
+        assert_eq!(bytes("1E3"), Some(1000));
+    }
+
+    #[test]
+    fn cpu_in_millicores() {
+        assert_eq!(millicores("500m"), Some(500));
+        assert_eq!(millicores("2"), Some(2000));
+        assert_eq!(millicores("0.1"), Some(100));
+        assert_eq!(millicores("1500u"), Some(2));
+    }
+
+    #[test]
+    fn malformed_quantities() {
+        for quantity in [
+            "", "Gi", "-1Gi", "1.2.3", "1+2", "16gi", "16 Gi", "1e", "1e+",
+        ] {
+            assert_eq!(parse(quantity), None, "{}", quantity);
+        }
+        assert_eq!(parse("+1"), Some(1.0));
+    }
+}
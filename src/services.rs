@@ -5,6 +5,7 @@
  *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
+use crate::manager::{LABEL_CLUSTER, LABEL_ROLE};
 use k8s_openapi::api::core::v1::{Service, ServicePort, ServiceSpec};
 use kube::api::ObjectMeta;
 use std::collections::BTreeMap;
@@ -37,3 +38,48 @@ pub fn build(name: &str, namespace: &str, port: i32) -> Service {
         ..Service::default()
     }
 }
+
+/// Builds a service selecting the members of a cluster by role
+///
+/// # Arguments
+/// - `name` - The name
+/// - `namespace` - The namespace
+/// - `cluster_name` - The name of the cluster
+/// - `role` - The role of the selected members, or any member if absent
+/// - `port` - The port
+pub fn build_role(
+    name: &str,
+    namespace: &str,
+    cluster_name: &str,
+    role: Option<&str>,
+    port: i32,
+) -> Service {
+    let mut labels: BTreeMap<String, String> = BTreeMap::new();
+    labels.insert("app".to_owned(), name.to_owned());
+    labels.insert(LABEL_CLUSTER.to_owned(), cluster_name.to_owned());
+
+    let mut selector: BTreeMap<String, String> = BTreeMap::new();
+    selector.insert(LABEL_CLUSTER.to_owned(), cluster_name.to_owned());
+    if let Some(role) = role {
+        selector.insert(LABEL_ROLE.to_owned(), role.to_owned());
+    }
+
+    Service {
+        metadata: ObjectMeta {
+            name: Some(name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(labels),
+            ..ObjectMeta::default()
+        },
+        spec: Some(ServiceSpec {
+            type_: Some("ClusterIP".to_owned()),
+            ports: Some(vec![ServicePort {
+                port,
+                ..ServicePort::default()
+            }]),
+            selector: Some(selector),
+            ..ServiceSpec::default()
+        }),
+        ..Service::default()
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use crate::Error;
+use crate::crd::v1::ImageOverride;
+use figment::{
+    Figment,
+    providers::{Format, Toml},
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Environment variable naming the operator configuration file
+pub const CONFIG_FILE_ENV: &str = "PGOPR_CONFIG";
+/// Operator configuration file used when the environment variable is not set
+pub const DEFAULT_CONFIG_FILE: &str = "/etc/pgopr/pgopr.toml";
+
+/// Settings of the operator, read from its configuration file
+///
+/// ``` toml
+/// [images.16]
+/// primary = "registry.example.com/pgsql16-primary-rocky10"
+/// ```
+#[derive(Deserialize, Debug, Default)]
+pub struct Settings {
+    /// Image catalog overrides per PostgreSQL major version
+    #[serde(default)]
+    pub images: BTreeMap<String, ImageOverride>,
+}
+
+impl Settings {
+    /// Reads the operator configuration file, a missing file gives the defaults.
+    pub fn load() -> Result<Settings, Error> {
+        let path =
+            std::env::var(CONFIG_FILE_ENV).unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+
+        Figment::new()
+            .merge(Toml::file(&path))
+            .extract()
+            .map_err(|err| Error::ConfigurationError(format!("{}: {}", path, err)))
+    }
+}
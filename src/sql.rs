@@ -0,0 +1,160 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use crate::Error;
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    Api, Client, ResourceExt,
+    api::{AttachParams, ListParams},
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const PSQL_USER: &str = "postgres";
+const PSQL_DATABASE: &str = "postgres";
+const STATUS_SUCCESS: &str = "Success";
+const PHASE_RUNNING: &str = "Running";
+
+/// Executes SQL statements with psql inside the running pod of a cluster member.
+///
+/// Each statement runs in its own transaction, which allows commands such as
+/// `ALTER SYSTEM` that cannot run inside a transaction block. Returns the unaligned,
+/// tuples-only output of all statements.
+///
+/// # Arguments
+/// - `client` - The Kubernetes client.
+/// - `namespace` - Namespace of the member.
+/// - `app` - The `app` label of the member pod.
+/// - `statements` - The SQL statements to execute.
+pub async fn execute(
+    client: Client,
+    namespace: &str,
+    app: &str,
+    statements: &[&str],
+) -> Result<String, Error> {
+    let pod_api: Api<Pod> = Api::namespaced(client, namespace);
+    let pod_name = running_pod(&pod_api, app)
+        .await?
+        .ok_or_else(|| Error::SqlError(format!("No running pod for {}", app)))?;
+
+    let mut command = vec![
+        "psql".to_string(),
+        "-X".to_string(),
+        "-A".to_string(),
+        "-t".to_string(),
+        "-q".to_string(),
+        "-v".to_string(),
+        "ON_ERROR_STOP=1".to_string(),
+        "-U".to_string(),
+        PSQL_USER.to_string(),
+        "-d".to_string(),
+        PSQL_DATABASE.to_string(),
+    ];
+    for statement in statements {
+        command.push("-c".to_string());
+        command.push(statement.to_string());
+    }
+
+    run(&pod_api, &pod_name, command).await
+}
+
+/// Runs a command inside the running pod of a cluster member.
+///
+/// # Arguments
+/// - `client` - The Kubernetes client.
+/// - `namespace` - Namespace of the member.
+/// - `app` - The `app` label of the member pod.
+/// - `command` - The command and its arguments.
+pub async fn command(
+    client: Client,
+    namespace: &str,
+    app: &str,
+    command: &[&str],
+) -> Result<String, Error> {
+    let pod_api: Api<Pod> = Api::namespaced(client, namespace);
+    let pod_name = running_pod(&pod_api, app)
+        .await?
+        .ok_or_else(|| Error::SqlError(format!("No running pod for {}", app)))?;
+
+    run(
+        &pod_api,
+        &pod_name,
+        command.iter().map(|arg| arg.to_string()).collect(),
+    )
+    .await
+}
+
+/// Quotes a string as a SQL literal.
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quotes a string as a SQL identifier.
+pub fn quote_ident(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+async fn running_pod(pod_api: &Api<Pod>, app: &str) -> Result<Option<String>, Error> {
+    let selector = format!("app={app}");
+    let pods = pod_api
+        .list(&ListParams::default().labels(&selector))
+        .await?;
+
+    Ok(pods
+        .into_iter()
+        .find(|pod| {
+            pod.metadata.deletion_timestamp.is_none()
+                && pod
+                    .status
+                    .as_ref()
+                    .and_then(|status| status.phase.as_deref())
+                    == Some(PHASE_RUNNING)
+        })
+        .map(|pod| pod.name_any()))
+}
+
+async fn run(pod_api: &Api<Pod>, pod_name: &str, command: Vec<String>) -> Result<String, Error> {
+    let mut process = pod_api
+        .exec(pod_name, command, &AttachParams::default().stderr(true))
+        .await?;
+
+    // drain both streams together so a full stderr buffer can't stall stdout
+    let (stdout, stderr) = tokio::join!(read_all(process.stdout()), read_all(process.stderr()));
+    let (stdout, stderr) = (stdout?, stderr?);
+
+    let status = match process.take_status() {
+        Some(status) => status.await,
+        None => None,
+    };
+    process
+        .join()
+        .await
+        .map_err(|err| Error::SqlError(err.to_string()))?;
+
+    match status {
+        Some(status) if status.status.as_deref() != Some(STATUS_SUCCESS) => {
+            Err(Error::SqlError(format!(
+                "{} on {}: {}",
+                status.message.unwrap_or_default(),
+                pod_name,
+                stderr.trim()
+            )))
+        }
+        _ => Ok(stdout.trim().to_string()),
+    }
+}
+
+async fn read_all(reader: Option<impl AsyncRead + Unpin>) -> Result<String, Error> {
+    let mut output = String::new();
+    if let Some(mut reader) = reader {
+        reader
+            .read_to_string(&mut output)
+            .await
+            .map_err(|err| Error::SqlError(err.to_string()))?;
+    }
+    Ok(output)
+}
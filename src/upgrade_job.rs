@@ -0,0 +1,229 @@
+/*
+ * Eclipse Public License - v 2.0
+ *
+ *   THE ACCOMPANYING PROGRAM IS PROVIDED UNDER THE TERMS OF THIS ECLIPSE
+ *   PUBLIC LICENSE ("AGREEMENT"). ANY USE, REPRODUCTION OR DISTRIBUTION
+ *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
+ */
+
+use crate::manager::{LABEL_CLUSTER, LABEL_COMPONENT};
+use crate::workload::{self, ContainerImage};
+use k8s_openapi::api::{
+    batch::v1::{Job, JobSpec},
+    core::v1::{
+        Container, EmptyDirVolumeSource, EnvVar, PersistentVolumeClaimVolumeSource, PodSpec,
+        PodTemplateSpec, Volume, VolumeMount,
+    },
+};
+use kube::api::ObjectMeta;
+use std::collections::BTreeMap;
+
+const OLD_BINARIES_VOLUME: &str = "old-binaries";
+const OLD_BINARIES_MOUNT: &str = "/old-binaries";
+
+/// Upgrades the data directory in place, the old binaries are copied next to the new ones.
+///
+/// The new cluster is initialized with the data checksum setting of the old one and takes
+/// over its configuration files. The script succeeds without changes when the data
+/// directory already has the new version, so the Job can be run again.
+const UPGRADE_SCRIPT: &str = r#"set -eu
+OLD_BIN=/usr/pgsql-$PG_OLD_VERSION/bin
+NEW_BIN=/usr/pgsql-$PG_NEW_VERSION/bin
+
+OLD_DATA=
+UPGRADED=
+for version_file in /pgdata/*/PG_VERSION; do
+  [ -f "$version_file" ] || continue
+  case "$(cat "$version_file")" in
+    "$PG_OLD_VERSION") OLD_DATA=$(dirname "$version_file") ;;
+    "$PG_NEW_VERSION") UPGRADED=$(dirname "$version_file") ;;
+  esac
+done
+if [ -z "$OLD_DATA" ] && [ -n "$UPGRADED" ]; then
+  echo "The data directory $UPGRADED already runs PostgreSQL $PG_NEW_VERSION"
+  exit 0
+fi
+if [ -z "$OLD_DATA" ]; then
+  echo "No PostgreSQL $PG_OLD_VERSION data directory below /pgdata" >&2
+  exit 1
+fi
+NEW_DATA=$OLD_DATA.$PG_NEW_VERSION
+rm -rf "$NEW_DATA"
+
+INITDB_OPTS=
+if "$OLD_BIN/pg_controldata" "$OLD_DATA" | grep -q '^Data page checksum version: *0$'; then
+  if [ "$PG_NEW_VERSION" -ge 18 ]; then
+    INITDB_OPTS=--no-data-checksums
+  fi
+else
+  INITDB_OPTS=--data-checksums
+fi
+OLD_WAL=
+if [ -L "$OLD_DATA/pg_wal" ]; then
+  OLD_WAL=$(readlink -f "$OLD_DATA/pg_wal")
+fi
+if [ -n "${PG_WAL_DIRECTORY:-}" ]; then
+  rm -rf "$PG_WAL_DIRECTORY/pg_wal.$PG_NEW_VERSION"
+  INITDB_OPTS="$INITDB_OPTS --waldir=$PG_WAL_DIRECTORY/pg_wal.$PG_NEW_VERSION"
+fi
+"$NEW_BIN/initdb" -D "$NEW_DATA" $INITDB_OPTS
+
+# pg_upgrade connects through the local socket
+cp "$OLD_DATA/pg_hba.conf" "$OLD_DATA/pg_hba.conf.pgopr"
+trap 'mv -f "$OLD_DATA/pg_hba.conf.pgopr" "$OLD_DATA/pg_hba.conf"' EXIT
+sed -i '1i local all all trust' "$OLD_DATA/pg_hba.conf"
+"$NEW_BIN/pg_upgrade" --link -b "$OLD_BIN" -B "$NEW_BIN" -d "$OLD_DATA" -D "$NEW_DATA"
+mv -f "$OLD_DATA/pg_hba.conf.pgopr" "$OLD_DATA/pg_hba.conf"
+trap - EXIT
+
+for file in postgresql.conf postgresql.auto.conf pg_hba.conf pg_ident.conf; do
+  if [ -f "$OLD_DATA/$file" ]; then
+    cp "$OLD_DATA/$file" "$NEW_DATA/$file"
+  fi
+done
+rm -rf "$OLD_DATA"
+mv "$NEW_DATA" "$OLD_DATA"
+if [ -n "$OLD_WAL" ]; then
+  find "$OLD_WAL" -maxdepth 1 -type f -delete
+fi
+echo "Upgraded $OLD_DATA from PostgreSQL $PG_OLD_VERSION to $PG_NEW_VERSION"
+"#;
+
+/// UpgradeConfig describes a major version upgrade of the data directory of a member.
+pub struct UpgradeConfig<'a> {
+    pub cluster_name: &'a str,
+    pub from_version: &'a str,
+    pub to_version: &'a str,
+    /// Image of the old version, provides the old binaries
+    pub from_image: &'a ContainerImage,
+    /// Image of the new version, runs pg_upgrade
+    pub to_image: &'a ContainerImage,
+    /// The claim holding the data directory
+    pub claim_name: &'a str,
+    /// The claim holding the WAL, if the member has a WAL volume
+    pub wal_claim_name: Option<&'a str>,
+}
+
+/// Builds a Job running `pg_upgrade --link` against the data directory of a stopped member
+///
+/// # Arguments
+/// - `name` - Name of the Job
+/// - `namespace` - Namespace
+/// - `config` - The upgrade to run
+pub fn build_job(name: &str, namespace: &str, config: UpgradeConfig) -> Job {
+    let mut pod_labels: BTreeMap<String, String> = BTreeMap::new();
+    pod_labels.insert("app".to_owned(), name.to_owned());
+    pod_labels.insert(LABEL_COMPONENT.to_string(), "upgrade".to_string());
+
+    // the pod stays out of the cluster label, the Services select the members by it
+    let mut labels = pod_labels.clone();
+    labels.insert(LABEL_CLUSTER.to_string(), config.cluster_name.to_string());
+
+    let old_binaries = format!("/usr/pgsql-{}", config.from_version);
+
+    let mut env = vec![
+        EnvVar {
+            name: "PG_OLD_VERSION".to_string(),
+            value: Some(config.from_version.to_string()),
+            ..Default::default()
+        },
+        EnvVar {
+            name: "PG_NEW_VERSION".to_string(),
+            value: Some(config.to_version.to_string()),
+            ..Default::default()
+        },
+    ];
+
+    let mut volumes = vec![
+        Volume {
+            name: workload::DATA_VOLUME.to_string(),
+            persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
+                claim_name: config.claim_name.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+        Volume {
+            name: OLD_BINARIES_VOLUME.to_string(),
+            empty_dir: Some(EmptyDirVolumeSource::default()),
+            ..Default::default()
+        },
+    ];
+
+    // the old binaries keep their path, their libraries are found through it
+    let mut volume_mounts = vec![
+        VolumeMount {
+            name: workload::DATA_VOLUME.to_string(),
+            mount_path: workload::DATA_MOUNT.to_string(),
+            ..Default::default()
+        },
+        VolumeMount {
+            name: OLD_BINARIES_VOLUME.to_string(),
+            mount_path: old_binaries.clone(),
+            ..Default::default()
+        },
+    ];
+
+    if let Some(wal_claim_name) = config.wal_claim_name {
+        workload::mount_wal(wal_claim_name, &mut volumes, &mut volume_mounts, &mut env);
+    }
+
+    Job {
+        metadata: ObjectMeta {
+            name: Some(name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            labels: Some(labels),
+            ..Default::default()
+        },
+        spec: Some(JobSpec {
+            // a failed upgrade needs a look at its logs before another attempt
+            backoff_limit: Some(0),
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(pod_labels),
+                    ..Default::default()
+                }),
+                spec: Some(PodSpec {
+                    restart_policy: Some("Never".to_string()),
+                    init_containers: Some(vec![Container {
+                        name: OLD_BINARIES_VOLUME.to_string(),
+                        image: Some(config.from_image.name.clone()),
+                        image_pull_policy: Some(config.from_image.pull_policy.clone()),
+                        command: Some(vec![
+                            "cp".to_string(),
+                            "-a".to_string(),
+                            format!("{}/.", old_binaries),
+                            OLD_BINARIES_MOUNT.to_string(),
+                        ]),
+                        volume_mounts: Some(vec![VolumeMount {
+                            name: OLD_BINARIES_VOLUME.to_string(),
+                            mount_path: OLD_BINARIES_MOUNT.to_string(),
+                            ..Default::default()
+                        }]),
+                        ..Default::default()
+                    }]),
+                    containers: vec![Container {
+                        name: name.to_owned(),
+                        image: Some(config.to_image.name.clone()),
+                        image_pull_policy: Some(config.to_image.pull_policy.clone()),
+                        command: Some(vec![
+                            "sh".to_string(),
+                            "-c".to_string(),
+                            UPGRADE_SCRIPT.to_string(),
+                        ]),
+                        // pg_upgrade writes its logs to the working directory
+                        working_dir: Some(workload::DATA_MOUNT.to_string()),
+                        volume_mounts: Some(volume_mounts),
+                        env: Some(env),
+                        ..Default::default()
+                    }],
+                    volumes: Some(volumes),
+                    image_pull_secrets: config.to_image.image_pull_secrets(),
+                    ..Default::default()
+                }),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
@@ -6,17 +6,32 @@
  *   OF THE PROGRAM CONSTITUTES RECIPIENT'S ACCEPTANCE OF THIS AGREEMENT.
  */
 
-use crate::crd::v1::ResourceRequirements;
+use crate::crd::v1::{PgOprSpec, PodPlacement, ResourceRequirements};
+use crate::credentials::Credentials;
+use crate::quantity;
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet, StatefulSetSpec};
 use k8s_openapi::api::core::v1::ResourceRequirements as K8sResources;
+use k8s_openapi::api::core::v1::{
+    ConfigMapVolumeSource, EnvVar, LocalObjectReference, PersistentVolumeClaim,
+    PersistentVolumeClaimVolumeSource, Volume, VolumeMount,
+};
 use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 
-pub const PG18_PRIMARY_IMAGE: &str = "pgsql18-primary-rocky10";
-pub const PG18_REPLICA_IMAGE: &str = "pgsql18-replica-rocky10";
 pub const HASH_CONFIG: &str = "pgopr.io/config-hash";
+/// Resources a container may request besides huge pages
+pub const RESOURCE_NAMES: [&str; 3] = ["cpu", "memory", "ephemeral-storage"];
+pub const HUGEPAGES_PREFIX: &str = "hugepages-";
+pub const CREDENTIALS_ROTATED_AT: &str = "pgopr.io/credentials-rotated-at";
 pub const DATA_VOLUME: &str = "pgdata";
 pub const DATA_MOUNT: &str = "/pgdata";
+pub const WAL_VOLUME: &str = "pgwal";
+pub const WAL_MOUNT: &str = "/pgwal";
 pub const CONFIG_VOLUME: &str = "config";
 pub const CONFIG_MOUNT: &str = "/etc/postgresql/postgresql.conf";
+/// The ConfigMap with pg_hba.conf is mounted as a directory, so its changes reach the pods
+pub const HBA_VOLUME: &str = "hba";
+pub const HBA_MOUNT: &str = "/etc/postgresql/hba";
+pub const HBA_FILE: &str = "/etc/postgresql/hba/pg_hba.conf";
 pub const PGMONETA_IMAGE: &str = "pgmoneta-rocky10";
 pub const PGMONETA_PORT: i32 = 5001;
 pub const PGMONETA_METRICS_PORT: i32 = 9100;
@@ -29,11 +44,259 @@ pub const PGEXPORTER_MON_IMAGE: &str = "grafana-rocky10";
 pub const PGEXPORTER_MON_GRAFANA_PORT: i32 = 3000;
 pub const PGEXPORTER_MON_PROMETHEUS_PORT: i32 = 9090;
 
+/// ContainerImage is an image and how the kubelet pulls it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContainerImage {
+    pub name: String,
+    pub pull_policy: String,
+    /// Secrets with the credentials of the registry
+    pub pull_secrets: Vec<String>,
+}
+
+impl ContainerImage {
+    /// An image pulled without credentials when the node does not have it
+    ///
+    /// # Arguments
+    /// - `name` - The image
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            pull_policy: "IfNotPresent".to_string(),
+            pull_secrets: Vec::new(),
+        }
+    }
+
+    /// The pull secrets of a pod running the image
+    pub fn image_pull_secrets(&self) -> Option<Vec<LocalObjectReference>> {
+        (!self.pull_secrets.is_empty()).then(|| {
+            self.pull_secrets
+                .iter()
+                .map(|name| LocalObjectReference { name: name.clone() })
+                .collect()
+        })
+    }
+}
+
 pub struct DeploymentConfig<'a> {
-    pub image: &'static str,
+    pub cluster_name: &'a str,
+    pub image: &'a ContainerImage,
     pub resources: Option<&'a ResourceRequirements>,
     pub config_map_name: Option<&'a str>,
+    /// ConfigMap with pg_hba.conf, the image writes its own when absent
+    pub hba_config_map: Option<&'a str>,
+    /// Hash of the parameters requiring a restart, a change restarts the pod
     pub config_hash: Option<&'a str>,
+    pub credentials: &'a Credentials,
+    /// Scales the member down to zero pods while keeping its storage
+    pub fenced: bool,
+    /// The claim mounted as data directory
+    pub claim_name: &'a str,
+    /// The claim mounted for the WAL, which stays in the data directory when absent
+    pub wal_claim_name: Option<&'a str>,
+    /// Nodes the pod may run on, any node when absent
+    pub placement: Option<&'a PodPlacement>,
+}
+
+/// Turns a member Deployment into a single-pod StatefulSet with the same pod template.
+///
+/// Volumes with a claim template are provisioned by the StatefulSet itself instead of
+/// mounting an existing claim.
+///
+/// # Arguments
+/// - `deployment` - The member Deployment
+/// - `claim_templates` - The claim templates, named after the volumes they replace
+pub fn stateful_set(
+    deployment: Deployment,
+    claim_templates: Vec<PersistentVolumeClaim>,
+) -> StatefulSet {
+    let spec = deployment.spec.unwrap_or_default();
+    let mut template = spec.template;
+
+    if let Some(volumes) = template
+        .spec
+        .as_mut()
+        .and_then(|pod_spec| pod_spec.volumes.as_mut())
+    {
+        volumes.retain(|volume| {
+            !claim_templates
+                .iter()
+                .any(|claim| claim.metadata.name.as_ref() == Some(&volume.name))
+        });
+    }
+
+    let name = deployment.metadata.name.clone();
+    StatefulSet {
+        metadata: deployment.metadata,
+        spec: Some(StatefulSetSpec {
+            replicas: spec.replicas,
+            selector: spec.selector,
+            service_name: name.clone(),
+            template,
+            volume_claim_templates: (!claim_templates.is_empty()).then_some(claim_templates),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Adds the WAL volume to a member pod, the image passes its mount to initdb as WAL
+/// directory.
+///
+/// # Arguments
+/// - `claim_name` - The claim holding the WAL
+/// - `volumes` - The volumes of the pod
+/// - `volume_mounts` - The volume mounts of the PostgreSQL container
+/// - `env` - The environment of the PostgreSQL container
+pub fn mount_wal(
+    claim_name: &str,
+    volumes: &mut Vec<Volume>,
+    volume_mounts: &mut Vec<VolumeMount>,
+    env: &mut Vec<EnvVar>,
+) {
+    volumes.push(Volume {
+        name: WAL_VOLUME.to_string(),
+        persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
+            claim_name: claim_name.to_string(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    volume_mounts.push(VolumeMount {
+        name: WAL_VOLUME.to_string(),
+        mount_path: WAL_MOUNT.to_string(),
+        ..Default::default()
+    });
+    env.push(EnvVar {
+        name: "PG_WAL_DIRECTORY".to_string(),
+        value: Some(WAL_MOUNT.to_string()),
+        ..Default::default()
+    });
+}
+
+/// Adds the configuration volumes to a member pod and returns the arguments of the
+/// PostgreSQL container pointing at the mounted files.
+///
+/// # Arguments
+/// - `config` - The deployment configuration naming the ConfigMaps
+/// - `volumes` - The volumes of the pod
+/// - `volume_mounts` - The volume mounts of the PostgreSQL container
+pub fn mount_config(
+    config: &DeploymentConfig,
+    volumes: &mut Vec<Volume>,
+    volume_mounts: &mut Vec<VolumeMount>,
+) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+
+    if let Some(cm_name) = config.config_map_name {
+        volumes.push(Volume {
+            name: CONFIG_VOLUME.to_string(),
+            config_map: Some(ConfigMapVolumeSource {
+                name: cm_name.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        volume_mounts.push(VolumeMount {
+            name: CONFIG_VOLUME.to_string(),
+            mount_path: CONFIG_MOUNT.to_string(),
+            sub_path: Some("postgresql.conf".to_string()),
+            ..Default::default()
+        });
+        args.push("-c".to_string());
+        args.push(format!("config_file={}", CONFIG_MOUNT));
+    }
+
+    if let Some(cm_name) = config.hba_config_map {
+        volumes.push(Volume {
+            name: HBA_VOLUME.to_string(),
+            config_map: Some(ConfigMapVolumeSource {
+                name: cm_name.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        volume_mounts.push(VolumeMount {
+            name: HBA_VOLUME.to_string(),
+            mount_path: HBA_MOUNT.to_string(),
+            ..Default::default()
+        });
+        args.push("-c".to_string());
+        args.push(format!("hba_file={}", HBA_FILE));
+    }
+
+    (!args.is_empty()).then_some(args)
+}
+
+/// Checks the resource requirements of the members, the replicas with their own, pgexporter
+/// and its monitoring.
+///
+/// Returns the problems found, the names must be known resources, the values quantities and
+/// no request may exceed its limit.
+///
+/// # Arguments
+/// - `spec` - The spec of the cluster
+pub fn validate_resources(spec: &PgOprSpec) -> Option<String> {
+    let pgexporter = spec.pgexporter.as_ref();
+    let problems: Vec<String> = [
+        ("resources".to_string(), spec.resources.as_ref()),
+        (
+            "pgexporter.resources".to_string(),
+            pgexporter.and_then(|pgexporter| pgexporter.resources.as_ref()),
+        ),
+        (
+            "pgexporter.monitoring.resources".to_string(),
+            pgexporter
+                .and_then(|pgexporter| pgexporter.monitoring.as_ref())
+                .and_then(|monitoring| monitoring.resources.as_ref()),
+        ),
+    ]
+    .into_iter()
+    .chain(spec.replica_specs.iter().flatten().map(|replica| {
+        (
+            format!("replicaSpecs {} resources", replica.name),
+            replica.resources.as_ref(),
+        )
+    }))
+    .filter_map(|(field, reqs)| {
+        let problems = resource_problems(reqs?);
+        (!problems.is_empty()).then(|| format!("{} ({})", field, problems.join(", ")))
+    })
+    .collect();
+
+    (!problems.is_empty()).then(|| format!("Invalid resources: {}", problems.join(", ")))
+}
+
+fn resource_problems(reqs: &ResourceRequirements) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (kind, quantities) in [("limit", &reqs.limits), ("request", &reqs.requests)] {
+        for (name, value) in quantities.iter().flatten() {
+            let known = RESOURCE_NAMES.contains(&name.as_str())
+                || name
+                    .strip_prefix(HUGEPAGES_PREFIX)
+                    .is_some_and(|size| quantity::parse(size).is_some());
+            if !known {
+                problems.push(format!("unknown resource '{}'", name));
+            } else if quantity::parse(value).is_none() {
+                problems.push(format!("{} {} '{}' is not a quantity", name, kind, value));
+            }
+        }
+    }
+
+    for (name, request) in reqs.requests.iter().flatten() {
+        let Some(limit) = reqs.limits.as_ref().and_then(|limits| limits.get(name)) else {
+            continue;
+        };
+        if let (Some(requested), Some(limited)) = (quantity::parse(request), quantity::parse(limit))
+            && requested > limited
+        {
+            problems.push(format!(
+                "{} request {} exceeds the limit {}",
+                name, request, limit
+            ));
+        }
+    }
+
+    problems
 }
 
 pub fn map_resources(reqs: &ResourceRequirements) -> K8sResources {
@@ -49,6 +49,54 @@ stop_operator() {
     fi
 }
 
+check_credentials() {
+    local secret="$1"
+    kubectl get secret "$secret"
+    for key in username password; do
+        if [[ -z "$(kubectl get secret "$secret" -o jsonpath="{.data.$key}")" ]]; then
+            echo "Secret $secret is missing the key $key"
+            exit 1
+        fi
+    done
+}
+
+ready_reason() {
+    kubectl get pgopr postgresql -o jsonpath='{.status.conditions[?(@.type=="Ready")].reason}'
+}
+
+check_parameters() {
+    echo "Checking PostgreSQL parameter validation..."
+    # 8kB is a single page, shared_buffers takes at least 16
+    kubectl patch pgopr postgresql --type merge -p '{"spec":{"config":{"shared_buffers":"8kB"}}}'
+    local count=0
+    while [[ "$(ready_reason)" != "InvalidSpec" ]]; do
+        if [ $count -ge 24 ]; then
+            echo "Timeout waiting for the invalid parameter to be refused."
+            exit 1
+        fi
+        echo "Waiting for the invalid parameter to be refused..."
+        sleep 5
+        count=$((count+1))
+    done
+    if ! kubectl get pgopr postgresql -o jsonpath='{.status.conditions[?(@.type=="Ready")].message}' | grep -q "shared_buffers"; then
+        echo "The refusal does not name shared_buffers."
+        exit 1
+    fi
+
+    kubectl patch pgopr postgresql --type merge -p '{"spec":{"config":{"shared_buffers":"128MB","checkpoint_timeout":"1min"}}}'
+    count=0
+    while [[ "$(ready_reason)" == "InvalidSpec" ]]; do
+        if [ $count -ge 24 ]; then
+            echo "Timeout waiting for the valid parameters to be accepted."
+            exit 1
+        fi
+        echo "Waiting for the valid parameters to be accepted..."
+        sleep 5
+        count=$((count+1))
+    done
+    kubectl patch pgopr postgresql --type json -p '[{"op":"remove","path":"/spec/config"}]'
+}
+
 test_operator() {
     echo "Running basic tests for the operator (start/stop)..."
     
@@ -109,6 +157,8 @@ test_operator() {
 
     echo "PgOpr status verified."
 
+    check_parameters
+
     # 3. Provision pgmoneta
     echo "Provisioning pgmoneta..."
     "$PGOPR_BIN" provision pgmoneta
@@ -128,7 +178,7 @@ test_operator() {
     echo "Checking pgmoneta resources..."
     kubectl get deployment postgresql-pgmoneta
     kubectl get pvc postgresql-pgmoneta-pv-claim
-    kubectl get secret postgresql-pgmoneta-secret
+    check_credentials postgresql-backup-credentials
 
     echo "Verifying pgmoneta status..."
     local pgmoneta_ready=$(kubectl get pgopr postgresql -o jsonpath='{.status.pgmoneta.ready}')
@@ -162,7 +212,7 @@ test_operator() {
 
     echo "Checking pgexporter resources..."
     kubectl get deployment postgresql-pgexporter
-    kubectl get secret postgresql-pgexporter-secret
+    check_credentials postgresql-exporter-credentials
 
     echo "Verifying pgexporter status..."
     local pgexporter_ready=$(kubectl get pgopr postgresql -o jsonpath='{.status.pgexporter.ready}')